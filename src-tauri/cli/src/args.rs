@@ -0,0 +1,141 @@
+use std::path::PathBuf;
+
+use ankichess_core::models::puzzle::{DuplicatePolicy, ImportOptions, SamplingMode, TagMatch};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::de::DeserializeOwned;
+
+/// Manage ankichess decks and imports from the command line. The app should be closed
+/// while this runs, both can't have the collection open at the same time.
+#[derive(Parser)]
+#[command(version)]
+pub struct Cli {
+    /// Directory holding collection.ankichess and the puzzle database, the app's data dir by default.
+    #[arg(long, env = "ANKICHESS_DATA_DIR", global = true)]
+    pub data_dir: Option<PathBuf>,
+
+    /// Only print results, no progress.
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// List the decks with their new, learning and due counts.
+    Decks,
+    /// Create a deck and print its id.
+    CreateDeck { name: String },
+    /// Delete a deck and its cards.
+    DeleteDeck { deck: String },
+    /// Import puzzles from the indexed Lichess puzzle database.
+    ImportDb(ImportDbArgs),
+    /// Import puzzles from a CSV file.
+    ImportCsv {
+        /// Deck id or name.
+        #[arg(long)]
+        deck: String,
+        file: PathBuf,
+        /// skip, link or move puzzles that are already in another deck.
+        #[arg(long, default_value = "skip", value_parser = serde_value::<DuplicatePolicy>)]
+        duplicates: DuplicatePolicy,
+    },
+    /// Export a deck to a file.
+    Export {
+        /// Deck id or name.
+        #[arg(long)]
+        deck: String,
+        #[arg(long, value_enum)]
+        format: ExportFormat,
+        file: PathBuf,
+        /// Keep the review state of the cards (csv and apkg).
+        #[arg(long)]
+        scheduling: bool,
+    },
+    /// Delete puzzles no note uses anymore.
+    Cleanup,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Pgn,
+    Apkg,
+}
+
+/// The filters of the app's import dialog.
+#[derive(Args)]
+pub struct ImportDbArgs {
+    /// Deck id or name.
+    #[arg(long)]
+    pub deck: String,
+    #[arg(long)]
+    pub min_rating: Option<u32>,
+    #[arg(long)]
+    pub max_rating: Option<u32>,
+    #[arg(long)]
+    pub min_popularity: Option<i32>,
+    #[arg(long)]
+    pub max_popularity: Option<i32>,
+    #[arg(long)]
+    pub limit: Option<usize>,
+    /// Theme to include, can be repeated.
+    #[arg(long = "theme")]
+    pub themes: Vec<String>,
+    /// Require every --theme instead of any of them.
+    #[arg(long)]
+    pub all_themes: bool,
+    #[arg(long = "exclude-theme")]
+    pub exclude_themes: Vec<String>,
+    /// Opening tag to include, can be repeated.
+    #[arg(long = "opening")]
+    pub openings: Vec<String>,
+    #[arg(long)]
+    pub all_openings: bool,
+    #[arg(long = "exclude-opening")]
+    pub exclude_openings: Vec<String>,
+    /// first, random, rating_stratified or theme_balanced.
+    #[arg(long, default_value = "first", value_parser = serde_value::<SamplingMode>)]
+    pub sampling: SamplingMode,
+    /// Seed of the random sampling modes, the same seed and filters import the same puzzles.
+    #[arg(long)]
+    pub seed: Option<u64>,
+    #[arg(long)]
+    pub rating_bucket_width: Option<u32>,
+    /// skip, link or move puzzles that are already in another deck.
+    #[arg(long, default_value = "skip", value_parser = serde_value::<DuplicatePolicy>)]
+    pub duplicates: DuplicatePolicy,
+}
+
+impl ImportDbArgs {
+    pub fn into_options(self, deck_id: i64) -> ImportOptions {
+        let tags = |tags: Vec<String>| (!tags.is_empty()).then_some(tags);
+        let matching = |all: bool| if all { TagMatch::All } else { TagMatch::Any };
+
+        ImportOptions {
+            deck_id,
+            min_rating: self.min_rating,
+            max_rating: self.max_rating,
+            min_popularity: self.min_popularity,
+            max_popularity: self.max_popularity,
+            limit: self.limit,
+            themes: tags(self.themes),
+            opening_tags: tags(self.openings),
+            theme_match: matching(self.all_themes),
+            exclude_themes: tags(self.exclude_themes),
+            opening_match: matching(self.all_openings),
+            exclude_opening_tags: tags(self.exclude_openings),
+            sampling: self.sampling,
+            seed: self.seed,
+            rating_bucket_width: self.rating_bucket_width,
+            duplicates: self.duplicates,
+        }
+    }
+}
+
+//the values are the serde names, the same ones the app sends
+fn serde_value<T: DeserializeOwned>(value: &str) -> Result<T, String> {
+    serde_json::from_value(serde_json::Value::String(value.to_string()))
+        .map_err(|_| format!("unknown value '{}'", value))
+}
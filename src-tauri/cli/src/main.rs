@@ -0,0 +1,145 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+
+use anki::collection::Collection;
+use ankichess_core::config::CoreConfig;
+use ankichess_core::error::AnkiChessError;
+use ankichess_core::models::import_report::ImportReport;
+use ankichess_core::models::job::{JobInfo, JobKind};
+use ankichess_core::models::puzzle::CsvImportPayload;
+use ankichess_core::services::deck_service::DeckService;
+use ankichess_core::services::import_service::ImportService;
+use ankichess_core::services::job_service::{JobHandle, JobManager, JobObserver};
+use ankichess_core::services::note_service::NoteService;
+use ankichess_core::services::undo_service::UndoJournal;
+use clap::Parser;
+
+mod args;
+
+use args::{Cli, Command, ExportFormat};
+
+//must match the identifier in tauri.conf.json, the app keeps its data in a dir of that name
+const APP_IDENTIFIER: &str = "com.daniele.ankichess";
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), AnkiChessError> {
+    let data_dir = match cli.data_dir {
+        Some(dir) => dir,
+        None => default_data_dir()?,
+    };
+    let config = CoreConfig::new(data_dir);
+    let (col, upgrade) = config.open_collection()?;
+    for message in upgrade.messages().iter().filter(|_| !cli.quiet) {
+        eprintln!("{}", message);
+    }
+    let col = Arc::new(Mutex::new(col));
+    let jobs = Arc::new(JobManager::new(Arc::new(ConsoleProgress { quiet: cli.quiet })));
+    //nothing is undone from the command line, the journal only satisfies the import api
+    let journal = Mutex::new(UndoJournal::default());
+
+    match cli.command {
+        Command::Decks => {
+            for deck in DeckService::get_all_decks(&mut col.lock()?)? {
+                println!("{}\t{}\t{}\t{}\t{}", deck.id.0, deck.name, deck.new_count, deck.learn_count, deck.due_count);
+            }
+        }
+        Command::CreateDeck { name } => {
+            println!("{}", DeckService::create_deck(&mut col.lock()?, &name)?);
+        }
+        Command::DeleteDeck { deck } => {
+            let deck_id = resolve_deck(&col, &deck)?;
+            DeckService::delete_deck(&mut col.lock()?, deck_id)?;
+        }
+        Command::ImportDb(args) => {
+            let db_path = config.puzzle_db_path();
+            if !db_path.exists() {
+                return Err(AnkiChessError::NotFound("Puzzle database has not been indexed yet".into()));
+            }
+            let deck_id = resolve_deck(&col, &args.deck)?;
+            let options = args.into_options(deck_id);
+            let job = jobs.start(JobKind::Import, "Lichess database import");
+            let result = ImportService::import_from_lichess_db(&col, &journal, options, db_path, &job);
+            print_report(&job, result, cli.quiet)?;
+        }
+        Command::ImportCsv { deck, file, duplicates } => {
+            let payload = CsvImportPayload {
+                deck_id: resolve_deck(&col, &deck)?,
+                csv_content: std::fs::read_to_string(&file)?,
+                duplicates,
+            };
+            let job = jobs.start(JobKind::Import, "CSV import");
+            let result = ImportService::import_from_csv(&col, &journal, payload, &job);
+            print_report(&job, result, cli.quiet)?;
+        }
+        Command::Export { deck, format, file, scheduling } => {
+            let deck_id = resolve_deck(&col, &deck)?;
+            let file_path = file.to_string_lossy().to_string();
+            let exported = match format {
+                ExportFormat::Csv => DeckService::export_deck_csv(col.clone(), deck_id, file_path, scheduling).await?,
+                ExportFormat::Pgn => DeckService::export_deck_pgn(col.clone(), deck_id, file_path).await?,
+                ExportFormat::Apkg => DeckService::export_deck_apkg(col.clone(), deck_id, file_path, scheduling, true).await?,
+            };
+            println!("Exported {} puzzles to {}", exported, file.display());
+        }
+        Command::Cleanup => {
+            println!("Removed {} unused puzzles", NoteService::cleanup_unused_puzzles(&col.lock()?)?);
+        }
+    }
+    Ok(())
+}
+
+fn default_data_dir() -> Result<PathBuf, AnkiChessError> {
+    dirs::data_dir()
+        .map(|dir| dir.join(APP_IDENTIFIER))
+        .ok_or_else(|| AnkiChessError::NotFound("No data directory on this system, pass --data-dir".into()))
+}
+
+//a deck is given by id or by its full name, e.g. "Club::Week 12"
+fn resolve_deck(col: &Arc<Mutex<Collection>>, deck: &str) -> Result<i64, AnkiChessError> {
+    let decks = DeckService::get_all_decks(&mut col.lock()?)?;
+    decks
+        .iter()
+        .find(|d| d.name == deck || d.id.0.to_string() == deck)
+        .map(|d| d.id.0)
+        .ok_or_else(|| AnkiChessError::NotFound(format!("No deck named '{}'", deck)))
+}
+
+//the process exits on errors, so only a finished import ends its job
+fn print_report(job: &JobHandle, result: Result<ImportReport, AnkiChessError>, quiet: bool) -> Result<(), AnkiChessError> {
+    let report = result?;
+    job.attach_report(report.id);
+    job.finish(Ok(report.summary()));
+
+    for row in report.skipped_rows.iter().filter(|_| !quiet) {
+        let line = row.line.map(|line| format!("line {}: ", line)).unwrap_or_default();
+        eprintln!("skipped {}{} ({})", line, row.detail, row.reason.as_str());
+    }
+    println!("{} Report {}.", report.summary(), report.id);
+    Ok(())
+}
+
+/// Prints the progress messages of jobs to stderr, so stdout only holds results.
+struct ConsoleProgress {
+    quiet: bool,
+}
+
+impl JobObserver for ConsoleProgress {
+    fn job_updated(&self, info: &JobInfo) {
+        //the final message is printed with the report
+        if !self.quiet && info.finished_at.is_none() {
+            eprintln!("{}", info.message);
+        }
+    }
+}
@@ -0,0 +1,177 @@
+pub mod pgn;
+pub mod position;
+pub mod san;
+pub mod types;
+
+use std::fmt;
+
+use crate::error::AnkiChessError;
+use position::Position;
+use types::Move;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChessError {
+    InvalidFen(String),
+    EmptySolution,
+    InvalidUci { ply: usize, uci: String },
+    IllegalMove { ply: usize, uci: String },
+    InvalidSan { ply: usize, san: String },
+    InvalidPgn(String),
+}
+
+impl fmt::Display for ChessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChessError::InvalidFen(reason) => write!(f, "Invalid FEN: {}", reason),
+            ChessError::EmptySolution => write!(f, "Solution cannot be empty"),
+            ChessError::InvalidUci { ply, uci } => write!(f, "Invalid UCI move '{}' at ply {}", uci, ply),
+            ChessError::IllegalMove { ply, uci } => write!(f, "Illegal move '{}' at ply {}", uci, ply),
+            ChessError::InvalidSan { ply, san } => write!(f, "Illegal or ambiguous move '{}' at ply {}", san, ply),
+            ChessError::InvalidPgn(reason) => write!(f, "Invalid PGN: {}", reason),
+        }
+    }
+}
+
+impl From<ChessError> for AnkiChessError {
+    fn from(err: ChessError) -> Self {
+        AnkiChessError::InvalidInput(err.to_string())
+    }
+}
+
+/// Parses a FEN and a space separated list of UCI moves, checking that every
+/// move is legal in the position reached by the previous ones.
+pub fn parse_line(fen: &str, moves: &str) -> Result<(Position, Vec<Move>), ChessError> {
+    let start = Position::from_fen(fen.trim())?;
+    let mut pos = start.clone();
+    let mut line = Vec::new();
+
+    for (i, uci) in moves.split_whitespace().enumerate() {
+        let ply = i + 1;
+        let mv = Move::from_uci(uci)
+            .ok_or_else(|| ChessError::InvalidUci { ply, uci: uci.to_string() })?;
+        if !pos.play(&mv) {
+            return Err(ChessError::IllegalMove { ply, uci: uci.to_string() });
+        }
+        line.push(mv);
+    }
+
+    if line.is_empty() {
+        return Err(ChessError::EmptySolution);
+    }
+    Ok((start, line))
+}
+
+pub fn validate_puzzle(fen: &str, moves: &str) -> Result<(), ChessError> {
+    parse_line(fen, moves).map(|_| ())
+}
+
+/// Reads a solution written either as UCI (`e2e4 e7e5`) or as SAN movetext
+/// (`1. e4 e5 2. Nf3`), returning it as space separated UCI moves.
+pub fn parse_solution(fen: &str, text: &str) -> Result<String, ChessError> {
+    let mut pos = Position::from_fen(fen.trim())?;
+    let mut line = Vec::new();
+
+    for token in text.split(|c: char| c.is_whitespace() || c == ',') {
+        if matches!(token, "*" | "1-0" | "0-1" | "1/2-1/2") {
+            continue;
+        }
+        //drop move numbers, also when glued to the move as in "1.e4" or "3...Qh4"
+        let token = match token.rfind('.') {
+            Some(i) => &token[i + 1..],
+            None => token,
+        };
+        let token = token.trim_end_matches(['!', '?']);
+        if token.is_empty() {
+            continue;
+        }
+
+        let ply = line.len() + 1;
+        let mv = Move::from_uci(token)
+            .filter(|mv| pos.is_legal(mv))
+            .or_else(|| pos.parse_san(token))
+            .ok_or_else(|| ChessError::InvalidSan { ply, san: token.to_string() })?;
+        pos.play(&mv);
+        line.push(mv.uci());
+    }
+
+    if line.is_empty() {
+        return Err(ChessError::EmptySolution);
+    }
+    Ok(line.join(" "))
+}
+
+/// Key of the position the solver has to find the answer in: pieces, side to move and
+/// castling rights, without move counters or the en passant square. For puzzles whose
+/// first move is the opponent's (`has_setup_move`) that move is played first, so a
+/// Lichess puzzle and a hand-entered copy of the same tactic get the same key.
+pub fn fen_key(fen: &str, moves: &str, has_setup_move: bool) -> Result<String, ChessError> {
+    let mut pos = Position::from_fen(fen.trim())?;
+    if has_setup_move {
+        if let Some(first) = moves.split_whitespace().next() {
+            let mv = Move::from_uci(first)
+                .ok_or_else(|| ChessError::InvalidUci { ply: 1, uci: first.to_string() })?;
+            if !pos.play(&mv) {
+                return Err(ChessError::IllegalMove { ply: 1, uci: first.to_string() });
+            }
+        }
+    }
+    let fen = pos.to_fen();
+    Ok(fen.split_whitespace().take(3).collect::<Vec<_>>().join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    const PUZZLE: &str = "r6k/pp2r2p/4Rp1Q/3p4/8/1N1P2R1/PqP2bPP/7K b - - 0 24";
+
+    #[test]
+    fn valid_solutions_pass() {
+        assert_eq!(validate_puzzle(PUZZLE, "f2g3 e6e7 b2b1 b3c1 b1c1 h6c1"), Ok(()));
+        assert_eq!(validate_puzzle(" 4k3/1P6/8/8/8/8/8/4K3 w - - 0 1 ", "b7b8q"), Ok(()));
+    }
+
+    #[test]
+    fn bad_moves_report_their_ply() {
+        assert_eq!(
+            validate_puzzle(PUZZLE, "f2g3 e6e7 b2b1 b3c1 b1c1 h6c9"),
+            Err(ChessError::InvalidUci { ply: 6, uci: "h6c9".to_string() })
+        );
+        assert_eq!(
+            validate_puzzle(PUZZLE, "f2g3 e2e4"),
+            Err(ChessError::IllegalMove { ply: 2, uci: "e2e4".to_string() })
+        );
+        assert_eq!(
+            ChessError::IllegalMove { ply: 2, uci: "e2e4".to_string() }.to_string(),
+            "Illegal move 'e2e4' at ply 2"
+        );
+    }
+
+    #[test]
+    fn bad_positions_and_empty_solutions_are_rejected() {
+        assert!(matches!(validate_puzzle("8/8/8 w - -", "e2e4"), Err(ChessError::InvalidFen(_))));
+        assert_eq!(validate_puzzle(PUZZLE, " "), Err(ChessError::EmptySolution));
+    }
+
+    #[test]
+    fn solutions_in_uci_or_san() {
+        assert_eq!(parse_solution(START, "e2e4 e7e5").unwrap(), "e2e4 e7e5");
+        assert_eq!(parse_solution(START, "1. e4 e5 2.Nf3 Nc6!? 1-0").unwrap(), "e2e4 e7e5 g1f3 b8c6");
+        assert_eq!(parse_solution(START, "1.f3 e5 2.g4 Qh4#").unwrap(), "f2f3 e7e5 g2g4 d8h4");
+
+        let castles = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(parse_solution(castles, "0-0 O-O-O").unwrap(), "e1g1 e8c8");
+        assert_eq!(parse_solution(castles, "e1g1, e8c8").unwrap(), "e1g1 e8c8");
+        assert_eq!(parse_solution("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "1. b8=N").unwrap(), "b7b8n");
+    }
+
+    #[test]
+    fn unreadable_solutions_are_rejected() {
+        assert_eq!(
+            parse_solution(START, "e4 e5 Ke3"),
+            Err(ChessError::InvalidSan { ply: 3, san: "Ke3".to_string() })
+        );
+        assert_eq!(parse_solution(START, "1-0"), Err(ChessError::EmptySolution));
+    }
+}
@@ -0,0 +1,545 @@
+use crate::chess::position::Position;
+use crate::chess::types::{Color, Move};
+use crate::chess::ChessError;
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Comment command marking the position it is attached to as a puzzle, e.g. `{ [%puzzle] White to move }`.
+/// Games with markers only yield the marked positions.
+pub const PUZZLE_MARKER: &str = "[%puzzle]";
+
+#[derive(Debug, Clone, Default)]
+pub struct PgnGame {
+    pub headers: Vec<(String, String)>,
+    pub mainline: PgnLine,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PgnLine {
+    //comments before the first move of the line
+    pub comments: Vec<String>,
+    pub moves: Vec<PgnMove>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PgnMove {
+    pub san: String,
+    pub comments: Vec<String>,
+    //numeric annotation glyphs, "!" and "?" suffixes included
+    pub nags: Vec<u8>,
+    //alternatives to this move, starting from the position before it
+    pub variations: Vec<PgnLine>,
+}
+
+/// A position taken from a game together with the line that follows it.
+#[derive(Debug, Clone)]
+pub struct PuzzlePosition {
+    pub fen: String,
+    pub moves: Vec<Move>,
+    pub comment: String,
+}
+
+impl PgnGame {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Whether the game starts from a `[FEN]` setup, as study chapters and exercises do.
+    pub fn has_setup(&self) -> bool {
+        self.header("FEN").is_some() && self.header("SetUp") != Some("0")
+    }
+
+    pub fn starting_position(&self) -> Result<Position, ChessError> {
+        match self.header("FEN") {
+            Some(fen) if self.has_setup() => Position::from_fen(fen),
+            _ => Position::from_fen(STARTING_FEN),
+        }
+    }
+
+    /// Finds the positions of the game worth a puzzle, each with the continuation of its
+    /// line as solution. Positions marked with [`PUZZLE_MARKER`], in the mainline or in
+    /// any variation, take precedence. Without markers these are the `[FEN]` setup the
+    /// game starts from and every mainline position where an annotated variation (one
+    /// with comments or glyphs) branches off, solved by the mainline.
+    pub fn puzzle_positions(&self) -> Result<Vec<PuzzlePosition>, ChessError> {
+        let start = self.starting_position()?;
+        let mut found = Vec::new();
+        collect_marked(&self.mainline, &start, 0, &mut found)?;
+        if found.is_empty() {
+            self.collect_derived(&start, &mut found)?;
+        }
+        Ok(found)
+    }
+
+    fn collect_derived(&self, start: &Position, found: &mut Vec<PuzzlePosition>) -> Result<(), ChessError> {
+        let resolved = resolve_line(&self.mainline, start, 0)?;
+
+        for (i, (before, _)) in resolved.iter().enumerate() {
+            let from_setup = i == 0 && self.has_setup();
+            let variations = &self.mainline.moves[i].variations;
+            if !from_setup && !variations.iter().any(is_annotated) {
+                continue;
+            }
+
+            //the text leading up to the position, or what the variation says about it
+            let before_comments = match i {
+                0 => &self.mainline.comments,
+                _ => &self.mainline.moves[i - 1].comments,
+            };
+            let mut comment = clean_comment(before_comments);
+            if comment.is_empty() {
+                if let Some(variation) = variations.iter().find(|v| is_annotated(v)) {
+                    comment = clean_comment(&variation_comments(variation));
+                }
+            }
+
+            found.push(PuzzlePosition {
+                fen: before.to_fen(),
+                moves: resolved[i..].iter().map(|(_, mv)| *mv).collect(),
+                comment,
+            });
+        }
+        Ok(())
+    }
+}
+
+fn resolve_line(line: &PgnLine, start: &Position, base_ply: usize) -> Result<Vec<(Position, Move)>, ChessError> {
+    let mut pos = start.clone();
+    let mut out = Vec::with_capacity(line.moves.len());
+    for (i, pgn_move) in line.moves.iter().enumerate() {
+        let ply = base_ply + i + 1;
+        let mv = pos
+            .parse_san(&pgn_move.san)
+            .ok_or_else(|| ChessError::InvalidSan { ply, san: pgn_move.san.clone() })?;
+        out.push((pos.clone(), mv));
+        pos.play(&mv);
+    }
+    Ok(out)
+}
+
+fn collect_marked(
+    line: &PgnLine,
+    start: &Position,
+    base_ply: usize,
+    found: &mut Vec<PuzzlePosition>,
+) -> Result<(), ChessError> {
+    let resolved = resolve_line(line, start, base_ply)?;
+
+    let push = |pos: &Position, from: usize, comments: &[String], found: &mut Vec<PuzzlePosition>| {
+        if from < resolved.len() {
+            found.push(PuzzlePosition {
+                fen: pos.to_fen(),
+                moves: resolved[from..].iter().map(|(_, mv)| *mv).collect(),
+                comment: clean_comment(comments),
+            });
+        }
+    };
+
+    if has_marker(&line.comments) {
+        push(start, 0, &line.comments, found);
+    }
+
+    for (i, pgn_move) in line.moves.iter().enumerate() {
+        let (before, mv) = &resolved[i];
+        for variation in &pgn_move.variations {
+            collect_marked(variation, before, base_ply + i, found)?;
+        }
+        if has_marker(&pgn_move.comments) {
+            let mut after = before.clone();
+            after.play(mv);
+            push(&after, i + 1, &pgn_move.comments, found);
+        }
+    }
+    Ok(())
+}
+
+fn is_annotated(line: &PgnLine) -> bool {
+    !line.comments.is_empty()
+        || line.moves.iter().any(|mv| !mv.comments.is_empty() || !mv.nags.is_empty() || mv.variations.iter().any(is_annotated))
+}
+
+//comments of the variation itself, not of the ones nested in it
+fn variation_comments(line: &PgnLine) -> Vec<String> {
+    line.comments.iter().chain(line.moves.iter().flat_map(|mv| &mv.comments)).cloned().collect()
+}
+
+fn has_marker(comments: &[String]) -> bool {
+    comments.iter().any(|c| c.contains(PUZZLE_MARKER))
+}
+
+/// Joins comments and strips embedded commands such as `[%cal Ge2e4]` or `[%clk 0:01:00]`.
+pub fn clean_comment(comments: &[String]) -> String {
+    let mut out = String::new();
+    for comment in comments {
+        let mut rest = comment.as_str();
+        let mut text = String::new();
+        while let Some(start) = rest.find("[%") {
+            text.push_str(&rest[..start]);
+            match rest[start..].find(']') {
+                Some(end) => rest = &rest[start + end + 1..],
+                None => {
+                    rest = "";
+                    break;
+                }
+            }
+        }
+        text.push_str(rest);
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !text.is_empty() {
+            if !out.is_empty() {
+                out.push(' ');
+            }
+            out.push_str(&text);
+        }
+    }
+    out
+}
+
+/// Writes a single game starting at `start`. The comment is placed before the first move.
+pub fn write_game(headers: &[(String, String)], start: &Position, moves: &[Move], comment: &str) -> String {
+    let mut out = String::new();
+    for (name, value) in headers {
+        out.push_str(&format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\"")));
+    }
+    out.push('\n');
+
+    let mut words: Vec<String> = Vec::new();
+    let comment = comment.replace('}', ")");
+    if !comment.trim().is_empty() {
+        words.push(format!("{{ {} }}", comment.trim()));
+    }
+
+    let mut pos = start.clone();
+    for (i, mv) in moves.iter().enumerate() {
+        match pos.turn() {
+            Color::White => words.push(format!("{}.", pos.fullmove_number())),
+            Color::Black if i == 0 => words.push(format!("{}...", pos.fullmove_number())),
+            Color::Black => {}
+        }
+        words.push(pos.san(mv));
+        pos.play(mv);
+    }
+    words.push("*".to_string());
+
+    //export format keeps lines under 80 characters
+    let mut line_len = 0;
+    for word in words {
+        if line_len > 0 && line_len + 1 + word.len() > 79 {
+            out.push('\n');
+            line_len = 0;
+        } else if line_len > 0 {
+            out.push(' ');
+            line_len += 1;
+        }
+        line_len += word.len();
+        out.push_str(&word);
+    }
+    out.push_str("\n\n");
+    out
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Nag(u8),
+    Open,
+    Close,
+    Result,
+    San(String),
+}
+
+/// Parses every game of a (possibly multi-game) PGN file.
+pub fn parse_pgn(input: &str) -> Result<Vec<PgnGame>, ChessError> {
+    let tokens = tokenize(input)?;
+
+    let mut games = Vec::new();
+    let mut game = PgnGame::default();
+    let mut has_movetext = false;
+    //stack of lines being built, the mainline is at the bottom
+    let mut stack: Vec<PgnLine> = vec![PgnLine::default()];
+
+    let finish = |game: &mut PgnGame, stack: &mut Vec<PgnLine>, games: &mut Vec<PgnGame>| -> Result<(), ChessError> {
+        if stack.len() != 1 {
+            return Err(ChessError::InvalidPgn("unterminated variation".to_string()));
+        }
+        let mut finished = std::mem::take(game);
+        finished.mainline = std::mem::take(&mut stack[0]);
+        if !finished.headers.is_empty() || !finished.mainline.moves.is_empty() {
+            games.push(finished);
+        }
+        Ok(())
+    };
+
+    for token in tokens {
+        match token {
+            Token::Tag(name, value) => {
+                if has_movetext {
+                    finish(&mut game, &mut stack, &mut games)?;
+                    has_movetext = false;
+                }
+                game.headers.push((name, value));
+            }
+            Token::Comment(text) => {
+                has_movetext = true;
+                let line = stack.last_mut().expect("line stack is never empty");
+                match line.moves.last_mut() {
+                    Some(mv) => mv.comments.push(text),
+                    None => line.comments.push(text),
+                }
+            }
+            //glyphs before the first move of a line have nothing to annotate
+            Token::Nag(nag) => {
+                if let Some(mv) = stack.last_mut().and_then(|l| l.moves.last_mut()) {
+                    mv.nags.push(nag);
+                }
+            }
+            Token::San(san) => {
+                has_movetext = true;
+                stack.last_mut().expect("line stack is never empty").moves.push(PgnMove {
+                    san,
+                    ..Default::default()
+                });
+            }
+            Token::Open => {
+                let parent_has_move = stack.last().map(|l| !l.moves.is_empty()).unwrap_or(false);
+                if !parent_has_move {
+                    return Err(ChessError::InvalidPgn("variation before any move".to_string()));
+                }
+                stack.push(PgnLine::default());
+            }
+            Token::Close => {
+                if stack.len() < 2 {
+                    return Err(ChessError::InvalidPgn("unexpected ')'".to_string()));
+                }
+                let variation = stack.pop().expect("checked above");
+                stack
+                    .last_mut()
+                    .and_then(|l| l.moves.last_mut())
+                    .expect("variation was opened after a move")
+                    .variations
+                    .push(variation);
+            }
+            Token::Result => {
+                finish(&mut game, &mut stack, &mut games)?;
+                has_movetext = false;
+            }
+        }
+    }
+    finish(&mut game, &mut stack, &mut games)?;
+
+    Ok(games)
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ChessError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let mut line_start = true;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        //escape mechanism: lines starting with '%' are ignored
+        if line_start && c == '%' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+        line_start = c == '\n';
+
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '{' => {
+                let start = i + 1;
+                let end = (start..chars.len())
+                    .find(|j| chars[*j] == '}')
+                    .ok_or_else(|| ChessError::InvalidPgn("unterminated comment".to_string()))?;
+                tokens.push(Token::Comment(chars[start..end].iter().collect::<String>().trim().to_string()));
+                i = end + 1;
+            }
+            ';' => {
+                let start = i + 1;
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                tokens.push(Token::Comment(chars[start..i].iter().collect::<String>().trim().to_string()));
+            }
+            '[' => {
+                //a ']' inside the quoted value doesn't close the tag
+                let mut end = i + 1;
+                let mut in_value = false;
+                while end < chars.len() && (in_value || chars[end] != ']') {
+                    match chars[end] {
+                        '\\' if in_value => end += 1,
+                        '"' => in_value = !in_value,
+                        _ => {}
+                    }
+                    end += 1;
+                }
+                if end >= chars.len() {
+                    return Err(ChessError::InvalidPgn("unterminated tag pair".to_string()));
+                }
+                let body: String = chars[i + 1..end].iter().collect();
+                tokens.push(parse_tag(&body)?);
+                i = end + 1;
+            }
+            '(' => {
+                tokens.push(Token::Open);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::Close);
+                i += 1;
+            }
+            '$' => {
+                let start = i + 1;
+                i = start;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+                let digits: String = chars[start..i].iter().collect();
+                tokens.push(Token::Nag(digits.parse().unwrap_or(0)));
+            }
+            _ => {
+                let start = i;
+                while i < chars.len() && !chars[i].is_whitespace() && !"{}()[];$".contains(chars[i]) {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                tokens.extend(classify_word(&word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn parse_tag(body: &str) -> Result<Token, ChessError> {
+    let body = body.trim();
+    let (name, rest) = body
+        .split_once(char::is_whitespace)
+        .ok_or_else(|| ChessError::InvalidPgn(format!("malformed tag pair '[{}]'", body)))?;
+    let rest = rest.trim();
+    if !rest.starts_with('"') || !rest.ends_with('"') || rest.len() < 2 {
+        return Err(ChessError::InvalidPgn(format!("malformed tag pair '[{}]'", body)));
+    }
+    let value = rest[1..rest.len() - 1].replace("\\\"", "\"").replace("\\\\", "\\");
+    Ok(Token::Tag(name.to_string(), value))
+}
+
+fn classify_word(word: &str) -> Vec<Token> {
+    if matches!(word, "1-0" | "0-1" | "1/2-1/2" | "*") {
+        return vec![Token::Result];
+    }
+
+    //move numbers may be glued to the move: "12.e4", "12...Nf6". digits without a dot
+    //after them are part of the move, castling can be written "0-0"
+    let after_number = word.trim_start_matches(|c: char| c.is_ascii_digit());
+    let san = if after_number.is_empty() || after_number.starts_with('.') {
+        after_number.trim_start_matches('.')
+    } else {
+        word
+    };
+
+    //suffix annotations ("e4!?") are glyphs as well
+    let stripped = san.trim_end_matches(['!', '?']);
+
+    let mut tokens = Vec::new();
+    if !stripped.is_empty() {
+        tokens.push(Token::San(stripped.to_string()));
+    }
+    let suffix = match &san[stripped.len()..] {
+        "" => None,
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => Some(0),
+    };
+    tokens.extend(suffix.map(Token::Nag));
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sans(game: &PgnGame) -> Vec<&str> {
+        game.mainline.moves.iter().map(|mv| mv.san.as_str()).collect()
+    }
+
+    #[test]
+    fn castling_written_with_zeros() {
+        let pgn = "[Event \"Casual game\"]\n\n1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 { [%puzzle] White to move } 4. 0-0 Nf6 5. d3 0-0 *\n";
+        let games = parse_pgn(pgn).unwrap();
+        assert_eq!(sans(&games[0]), ["e4", "e5", "Nf3", "Nc6", "Bc4", "Bc5", "0-0", "Nf6", "d3", "0-0"]);
+
+        let marked = games[0].puzzle_positions().unwrap();
+        assert_eq!(marked.len(), 1);
+        assert_eq!(marked[0].moves.len(), 4);
+    }
+
+    #[test]
+    fn glued_move_numbers_are_stripped() {
+        let games = parse_pgn("1.e4 e5 2.Nf3 2...Nc6 3.O-O-O *").unwrap();
+        assert_eq!(sans(&games[0]), ["e4", "e5", "Nf3", "Nc6", "O-O-O"]);
+    }
+
+    #[test]
+    fn brackets_inside_tag_values() {
+        let pgn = "[Event \"Blitz [3+2]\"]\n[Annotator \"a \\\"quoted]\\\" name\"]\n\n1. e4 *\n";
+        let games = parse_pgn(pgn).unwrap();
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].header("Event"), Some("Blitz [3+2]"));
+        assert_eq!(games[0].header("Annotator"), Some("a \"quoted]\" name"));
+        assert_eq!(games[0].mainline.moves.len(), 1);
+    }
+
+    #[test]
+    fn study_chapters_start_at_their_setup() {
+        let pgn = "[Event \"Study: Tactics\"]\n[SetUp \"1\"]\n[FEN \"r6k/pp2r2p/4Rp1Q/3p4/8/1N1P2R1/PqP2bPP/7K b - - 0 24\"]\n\n\
+            { Black to move } 24... Bxg3 25. Rxe7 Qb1+ 26. Nc1 Qxc1+ 27. Qxc1 *\n";
+        let positions = parse_pgn(pgn).unwrap()[0].puzzle_positions().unwrap();
+
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].fen, "r6k/pp2r2p/4Rp1Q/3p4/8/1N1P2R1/PqP2bPP/7K b - - 0 24");
+        assert_eq!(positions[0].moves.len(), 6);
+        assert_eq!(positions[0].comment, "Black to move");
+    }
+
+    #[test]
+    fn annotated_variations_mark_the_mainline_position() {
+        let pgn = "1. e4 e5 2. Nf3 (2. Qh5 Nc6) Nc6 { Develops } 3. Bc4 (3. Bb5? { Spanish } a6) 3... Bc5 \
+            (3... Nd4 $6) 4. c3 *\n";
+        let positions = parse_pgn(pgn).unwrap()[0].puzzle_positions().unwrap();
+
+        assert_eq!(positions.len(), 2);
+        assert_eq!(positions[0].fen, "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+        assert_eq!(positions[0].moves.iter().map(|mv| mv.uci()).collect::<Vec<_>>(), ["f1c4", "f8c5", "c2c3"]);
+        assert_eq!(positions[0].comment, "Develops");
+        assert_eq!(positions[1].moves.len(), 2);
+        assert_eq!(positions[1].comment, "");
+    }
+
+    #[test]
+    fn markers_override_derived_positions() {
+        let pgn = "[SetUp \"1\"]\n[FEN \"r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1\"]\n\n\
+            1. O-O (1. O-O-O { also fine }) O-O-O { [%puzzle] } 2. Rfe1 Rhe8 *\n";
+        let positions = parse_pgn(pgn).unwrap()[0].puzzle_positions().unwrap();
+
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].moves.iter().map(|mv| mv.uci()).collect::<Vec<_>>(), ["f1e1", "h8e8"]);
+    }
+
+    #[test]
+    fn plain_games_have_no_puzzles() {
+        let games = parse_pgn("1. e4 e5 2. Nf3 (2. Bc4 Nf6) Nc6 *").unwrap();
+        assert!(games[0].puzzle_positions().unwrap().is_empty());
+    }
+}
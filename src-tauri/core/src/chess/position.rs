@@ -0,0 +1,623 @@
+use crate::chess::types::*;
+use crate::chess::ChessError;
+
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+const KING_OFFSETS: [(i8, i8); 8] = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+const ROOK_DIRS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const BISHOP_DIRS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+const PROMOTION_ROLES: [Role; 4] = [Role::Queen, Role::Rook, Role::Bishop, Role::Knight];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Position {
+    board: [Option<Piece>; 64],
+    turn: Color,
+    castling: u8,
+    ep_square: Option<Square>,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+}
+
+impl Position {
+    /// Parses a FEN string. The halfmove clock and fullmove number are optional,
+    /// castling rights that are not backed by a king and rook on their original
+    /// squares are dropped.
+    pub fn from_fen(fen: &str) -> Result<Position, ChessError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() < 4 || fields.len() > 6 {
+            return Err(ChessError::InvalidFen(format!(
+                "expected 4 to 6 fields, found {}",
+                fields.len()
+            )));
+        }
+
+        let mut board = [None; 64];
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(ChessError::InvalidFen(format!("expected 8 ranks, found {}", ranks.len())));
+        }
+        for (i, rank_str) in ranks.iter().enumerate() {
+            let rank = 7 - i as u8;
+            let mut file: u8 = 0;
+            for c in rank_str.chars() {
+                if let Some(skip) = c.to_digit(10) {
+                    if skip == 0 || skip > 8 {
+                        return Err(ChessError::InvalidFen(format!("invalid empty square count '{}'", c)));
+                    }
+                    file += skip as u8;
+                } else {
+                    let piece = Piece::from_char(c)
+                        .ok_or_else(|| ChessError::InvalidFen(format!("invalid piece '{}'", c)))?;
+                    if file >= 8 {
+                        return Err(ChessError::InvalidFen(format!("rank {} is too long", rank + 1)));
+                    }
+                    board[Square::new(file, rank).0 as usize] = Some(piece);
+                    file += 1;
+                }
+                if file > 8 {
+                    return Err(ChessError::InvalidFen(format!("rank {} is too long", rank + 1)));
+                }
+            }
+            if file != 8 {
+                return Err(ChessError::InvalidFen(format!("rank {} has {} squares", rank + 1, file)));
+            }
+        }
+
+        let turn = match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            other => return Err(ChessError::InvalidFen(format!("invalid side to move '{}'", other))),
+        };
+
+        let mut castling = 0;
+        if fields[2] != "-" {
+            for c in fields[2].chars() {
+                let right = match c {
+                    'K' => CASTLE_WHITE_KING,
+                    'Q' => CASTLE_WHITE_QUEEN,
+                    'k' => CASTLE_BLACK_KING,
+                    'q' => CASTLE_BLACK_QUEEN,
+                    other => return Err(ChessError::InvalidFen(format!("invalid castling right '{}'", other))),
+                };
+                if castling & right != 0 {
+                    return Err(ChessError::InvalidFen(format!("repeated castling right '{}'", c)));
+                }
+                castling |= right;
+            }
+        }
+
+        let ep_square = match fields[3] {
+            "-" => None,
+            s => {
+                let sq = Square::parse(s)
+                    .ok_or_else(|| ChessError::InvalidFen(format!("invalid en passant square '{}'", s)))?;
+                let expected_rank = if turn == Color::White { 5 } else { 2 };
+                if sq.rank() != expected_rank {
+                    return Err(ChessError::InvalidFen(format!("invalid en passant square '{}'", s)));
+                }
+                Some(sq)
+            }
+        };
+
+        let halfmove_clock = match fields.get(4) {
+            Some(s) => s.parse::<u32>()
+                .map_err(|_| ChessError::InvalidFen(format!("invalid halfmove clock '{}'", s)))?,
+            None => 0,
+        };
+        let fullmove_number = match fields.get(5) {
+            Some(s) => s.parse::<u32>()
+                .map_err(|_| ChessError::InvalidFen(format!("invalid fullmove number '{}'", s)))?
+                .max(1),
+            None => 1,
+        };
+
+        let mut pos = Position { board, turn, castling, ep_square, halfmove_clock, fullmove_number };
+        pos.validate()?;
+        Ok(pos)
+    }
+
+    fn validate(&mut self) -> Result<(), ChessError> {
+        for color in [Color::White, Color::Black] {
+            let kings = self.board.iter()
+                .filter(|p| **p == Some(Piece { color, role: Role::King }))
+                .count();
+            if kings != 1 {
+                return Err(ChessError::InvalidFen(format!(
+                    "expected exactly one {:?} king, found {}",
+                    color, kings
+                )));
+            }
+        }
+
+        for file in 0..8 {
+            for rank in [0, 7] {
+                if let Some(Piece { role: Role::Pawn, .. }) = self.piece_at(Square::new(file, rank)) {
+                    return Err(ChessError::InvalidFen("pawn on the first or last rank".to_string()));
+                }
+            }
+        }
+
+        if self.is_attacked(self.king_square(self.turn.opposite()), self.turn) {
+            return Err(ChessError::InvalidFen("the side not to move is in check".to_string()));
+        }
+
+        for (right, color, rook_file) in [
+            (CASTLE_WHITE_KING, Color::White, 7),
+            (CASTLE_WHITE_QUEEN, Color::White, 0),
+            (CASTLE_BLACK_KING, Color::Black, 7),
+            (CASTLE_BLACK_QUEEN, Color::Black, 0),
+        ] {
+            let rank = color.back_rank();
+            let king_ok = self.piece_at(Square::new(4, rank)) == Some(Piece { color, role: Role::King });
+            let rook_ok = self.piece_at(Square::new(rook_file, rank)) == Some(Piece { color, role: Role::Rook });
+            if !(king_ok && rook_ok) {
+                self.castling &= !right;
+            }
+        }
+
+        if let Some(ep) = self.ep_square {
+            let them = self.turn.opposite();
+            let pawn_sq = ep.offset(0, them.forward()).expect("en passant square is on rank 3 or 6");
+            let origin = ep.offset(0, -them.forward()).expect("en passant square is on rank 3 or 6");
+            if self.piece_at(pawn_sq) != Some(Piece { color: them, role: Role::Pawn })
+                || self.piece_at(ep).is_some()
+                || self.piece_at(origin).is_some()
+            {
+                return Err(ChessError::InvalidFen(format!("invalid en passant square '{}'", ep)));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn to_fen(&self) -> String {
+        format!(
+            "{} {} {} {} {} {}",
+            self.board_fen(),
+            if self.turn == Color::White { "w" } else { "b" },
+            self.castling_fen(),
+            self.ep_square.map(|s| s.to_string()).unwrap_or_else(|| "-".to_string()),
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+
+    pub fn board_fen(&self) -> String {
+        let mut out = String::new();
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                match self.piece_at(Square::new(file, rank)) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            out.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        out.push(piece.char());
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                out.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                out.push('/');
+            }
+        }
+        out
+    }
+
+    fn castling_fen(&self) -> String {
+        let mut out = String::new();
+        for (right, c) in [
+            (CASTLE_WHITE_KING, 'K'),
+            (CASTLE_WHITE_QUEEN, 'Q'),
+            (CASTLE_BLACK_KING, 'k'),
+            (CASTLE_BLACK_QUEEN, 'q'),
+        ] {
+            if self.castling & right != 0 {
+                out.push(c);
+            }
+        }
+        if out.is_empty() {
+            out.push('-');
+        }
+        out
+    }
+
+    pub fn turn(&self) -> Color {
+        self.turn
+    }
+
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    pub fn piece_at(&self, sq: Square) -> Option<Piece> {
+        self.board[sq.0 as usize]
+    }
+
+    fn king_square(&self, color: Color) -> Square {
+        (0..64u8)
+            .map(Square)
+            .find(|sq| self.piece_at(*sq) == Some(Piece { color, role: Role::King }))
+            .expect("position always has both kings")
+    }
+
+    /// Returns true if `sq` is attacked by any piece of color `by`.
+    pub fn is_attacked(&self, sq: Square, by: Color) -> bool {
+        let has = |target: Option<Square>, roles: &[Role]| {
+            target
+                .and_then(|t| self.piece_at(t))
+                .map(|p| p.color == by && roles.contains(&p.role))
+                .unwrap_or(false)
+        };
+
+        // a pawn of color `by` attacks sq from one rank behind (from its own point of view)
+        for df in [-1, 1] {
+            if has(sq.offset(df, -by.forward()), &[Role::Pawn]) {
+                return true;
+            }
+        }
+        if KNIGHT_OFFSETS.iter().any(|(df, dr)| has(sq.offset(*df, *dr), &[Role::Knight])) {
+            return true;
+        }
+        if KING_OFFSETS.iter().any(|(df, dr)| has(sq.offset(*df, *dr), &[Role::King])) {
+            return true;
+        }
+        for (dirs, roles) in [
+            (ROOK_DIRS, [Role::Rook, Role::Queen]),
+            (BISHOP_DIRS, [Role::Bishop, Role::Queen]),
+        ] {
+            for (df, dr) in dirs {
+                let mut cur = sq.offset(df, dr);
+                while let Some(t) = cur {
+                    if let Some(p) = self.piece_at(t) {
+                        if p.color == by && roles.contains(&p.role) {
+                            return true;
+                        }
+                        break;
+                    }
+                    cur = t.offset(df, dr);
+                }
+            }
+        }
+        false
+    }
+
+    pub fn is_check(&self) -> bool {
+        self.is_attacked(self.king_square(self.turn), self.turn.opposite())
+    }
+
+    pub fn is_checkmate(&self) -> bool {
+        self.is_check() && self.legal_moves().is_empty()
+    }
+
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.pseudo_legal_moves()
+            .into_iter()
+            .filter(|mv| {
+                let mut after = self.clone();
+                after.play_unchecked(mv);
+                !after.is_attacked(after.king_square(self.turn), after.turn)
+            })
+            .collect()
+    }
+
+    pub fn is_legal(&self, mv: &Move) -> bool {
+        self.legal_moves().contains(mv)
+    }
+
+    /// Plays a move after checking that it is legal.
+    pub fn play(&mut self, mv: &Move) -> bool {
+        if !self.is_legal(mv) {
+            return false;
+        }
+        self.play_unchecked(mv);
+        true
+    }
+
+    fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::with_capacity(48);
+        let us = self.turn;
+
+        for idx in 0..64u8 {
+            let from = Square(idx);
+            let piece = match self.piece_at(from) {
+                Some(p) if p.color == us => p,
+                _ => continue,
+            };
+
+            match piece.role {
+                Role::Pawn => self.gen_pawn_moves(from, &mut moves),
+                Role::Knight => self.gen_step_moves(from, &KNIGHT_OFFSETS, &mut moves),
+                Role::King => {
+                    self.gen_step_moves(from, &KING_OFFSETS, &mut moves);
+                    self.gen_castling_moves(from, &mut moves);
+                }
+                Role::Bishop => self.gen_slider_moves(from, &BISHOP_DIRS, &mut moves),
+                Role::Rook => self.gen_slider_moves(from, &ROOK_DIRS, &mut moves),
+                Role::Queen => {
+                    self.gen_slider_moves(from, &BISHOP_DIRS, &mut moves);
+                    self.gen_slider_moves(from, &ROOK_DIRS, &mut moves);
+                }
+            }
+        }
+        moves
+    }
+
+    fn gen_pawn_moves(&self, from: Square, moves: &mut Vec<Move>) {
+        let us = self.turn;
+        let dir = us.forward();
+        let last_rank = us.opposite().back_rank();
+
+        let push = |to: Square, moves: &mut Vec<Move>| {
+            if to.rank() == last_rank {
+                for role in PROMOTION_ROLES {
+                    moves.push(Move { from, to, promotion: Some(role) });
+                }
+            } else {
+                moves.push(Move { from, to, promotion: None });
+            }
+        };
+
+        if let Some(one) = from.offset(0, dir) {
+            if self.piece_at(one).is_none() {
+                push(one, moves);
+                if from.rank() == us.pawn_rank() {
+                    if let Some(two) = one.offset(0, dir) {
+                        if self.piece_at(two).is_none() {
+                            push(two, moves);
+                        }
+                    }
+                }
+            }
+        }
+
+        for df in [-1, 1] {
+            if let Some(to) = from.offset(df, dir) {
+                let is_capture = matches!(self.piece_at(to), Some(p) if p.color != us);
+                if is_capture || self.ep_square == Some(to) {
+                    push(to, moves);
+                }
+            }
+        }
+    }
+
+    fn gen_step_moves(&self, from: Square, offsets: &[(i8, i8)], moves: &mut Vec<Move>) {
+        for (df, dr) in offsets {
+            if let Some(to) = from.offset(*df, *dr) {
+                match self.piece_at(to) {
+                    Some(p) if p.color == self.turn => {}
+                    _ => moves.push(Move { from, to, promotion: None }),
+                }
+            }
+        }
+    }
+
+    fn gen_slider_moves(&self, from: Square, dirs: &[(i8, i8)], moves: &mut Vec<Move>) {
+        for (df, dr) in dirs {
+            let mut cur = from.offset(*df, *dr);
+            while let Some(to) = cur {
+                match self.piece_at(to) {
+                    Some(p) => {
+                        if p.color != self.turn {
+                            moves.push(Move { from, to, promotion: None });
+                        }
+                        break;
+                    }
+                    None => moves.push(Move { from, to, promotion: None }),
+                }
+                cur = to.offset(*df, *dr);
+            }
+        }
+    }
+
+    fn gen_castling_moves(&self, from: Square, moves: &mut Vec<Move>) {
+        let us = self.turn;
+        let rank = us.back_rank();
+        if from != Square::new(4, rank) {
+            return;
+        }
+        let (king_side, queen_side) = match us {
+            Color::White => (CASTLE_WHITE_KING, CASTLE_WHITE_QUEEN),
+            Color::Black => (CASTLE_BLACK_KING, CASTLE_BLACK_QUEEN),
+        };
+        let them = us.opposite();
+
+        if self.castling & king_side != 0
+            && self.piece_at(Square::new(5, rank)).is_none()
+            && self.piece_at(Square::new(6, rank)).is_none()
+            && !self.is_attacked(from, them)
+            && !self.is_attacked(Square::new(5, rank), them)
+            && !self.is_attacked(Square::new(6, rank), them)
+        {
+            moves.push(Move { from, to: Square::new(6, rank), promotion: None });
+        }
+
+        if self.castling & queen_side != 0
+            && self.piece_at(Square::new(3, rank)).is_none()
+            && self.piece_at(Square::new(2, rank)).is_none()
+            && self.piece_at(Square::new(1, rank)).is_none()
+            && !self.is_attacked(from, them)
+            && !self.is_attacked(Square::new(3, rank), them)
+            && !self.is_attacked(Square::new(2, rank), them)
+        {
+            moves.push(Move { from, to: Square::new(2, rank), promotion: None });
+        }
+    }
+
+    fn play_unchecked(&mut self, mv: &Move) {
+        let us = self.turn;
+        let piece = self.board[mv.from.0 as usize].expect("move starts on an occupied square");
+        let captured = self.board[mv.to.0 as usize];
+
+        self.board[mv.from.0 as usize] = None;
+
+        let mut is_capture = captured.is_some();
+        if piece.role == Role::Pawn && Some(mv.to) == self.ep_square {
+            let victim = mv.to.offset(0, -us.forward()).expect("en passant victim is on the board");
+            self.board[victim.0 as usize] = None;
+            is_capture = true;
+        }
+
+        if piece.role == Role::King && (mv.from.file() as i8 - mv.to.file() as i8).abs() == 2 {
+            let rank = mv.from.rank();
+            let (rook_from, rook_to) = if mv.to.file() == 6 { (7, 5) } else { (0, 3) };
+            let rook = self.board[Square::new(rook_from, rank).0 as usize].take();
+            self.board[Square::new(rook_to, rank).0 as usize] = rook;
+        }
+
+        self.board[mv.to.0 as usize] = Some(match mv.promotion {
+            Some(role) => Piece { color: us, role },
+            None => piece,
+        });
+
+        for sq in [mv.from, mv.to] {
+            self.castling &= !match (sq.file(), sq.rank()) {
+                (4, 0) => CASTLE_WHITE_KING | CASTLE_WHITE_QUEEN,
+                (7, 0) => CASTLE_WHITE_KING,
+                (0, 0) => CASTLE_WHITE_QUEEN,
+                (4, 7) => CASTLE_BLACK_KING | CASTLE_BLACK_QUEEN,
+                (7, 7) => CASTLE_BLACK_KING,
+                (0, 7) => CASTLE_BLACK_QUEEN,
+                _ => 0,
+            };
+        }
+
+        self.ep_square = None;
+        if piece.role == Role::Pawn && (mv.from.rank() as i8 - mv.to.rank() as i8).abs() == 2 {
+            self.ep_square = mv.from.offset(0, us.forward());
+        }
+
+        if piece.role == Role::Pawn || is_capture {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if us == Color::Black {
+            self.fullmove_number += 1;
+        }
+        self.turn = us.opposite();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    fn perft(pos: &Position, depth: u32) -> u64 {
+        let moves = pos.legal_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        moves.iter()
+            .map(|mv| {
+                let mut next = pos.clone();
+                assert!(next.play(mv));
+                perft(&next, depth - 1)
+            })
+            .sum()
+    }
+
+    fn after(fen: &str, uci: &str) -> String {
+        let mut pos = Position::from_fen(fen).unwrap();
+        assert!(pos.play(&Move::from_uci(uci).unwrap()), "{} is illegal", uci);
+        pos.to_fen()
+    }
+
+    #[test]
+    fn perft_start_position() {
+        let pos = Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert_eq!(perft(&pos, 1), 20);
+        assert_eq!(perft(&pos, 2), 400);
+        assert_eq!(perft(&pos, 3), 8902);
+        assert_eq!(perft(&pos, 4), 197281);
+    }
+
+    #[test]
+    fn perft_kiwipete() {
+        let pos = Position::from_fen(KIWIPETE).unwrap();
+        assert_eq!(perft(&pos, 1), 48);
+        assert_eq!(perft(&pos, 2), 2039);
+        assert_eq!(perft(&pos, 3), 97862);
+    }
+
+    #[test]
+    fn perft_en_passant_and_promotions() {
+        let pos = Position::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+        assert_eq!(perft(&pos, 4), 43238);
+        let pos = Position::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1").unwrap();
+        assert_eq!(perft(&pos, 3), 9467);
+    }
+
+    #[test]
+    fn fen_round_trip() {
+        assert_eq!(Position::from_fen(KIWIPETE).unwrap().to_fen(), KIWIPETE);
+        let short = Position::from_fen("4k3/8/8/8/8/8/8/4K3 b - -").unwrap();
+        assert_eq!(short.to_fen(), "4k3/8/8/8/8/8/8/4K3 b - - 0 1");
+    }
+
+    #[test]
+    fn malformed_fens_are_rejected() {
+        for fen in [
+            "",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/ppppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkx - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e4 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - x 1",
+            "rnbq1bnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNP w - - 0 1",
+            "4k3/8/8/8/8/8/4R3/4K3 w - - 0 1",
+        ] {
+            assert!(matches!(Position::from_fen(fen), Err(ChessError::InvalidFen(_))), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn repeated_castling_rights_are_rejected() {
+        let result = Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KKqq - 0 1");
+        assert_eq!(result, Err(ChessError::InvalidFen("repeated castling right 'K'".to_string())));
+    }
+
+    #[test]
+    fn castling_rights_need_king_and_rook_at_home() {
+        let pos = Position::from_fen("r3k3/8/8/8/8/8/8/4K2R w KQkq - 0 1").unwrap();
+        assert_eq!(pos.to_fen(), "r3k3/8/8/8/8/8/8/4K2R w Kq - 0 1");
+    }
+
+    #[test]
+    fn castling_moves_the_rook() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(after(fen, "e1g1"), "r3k2r/8/8/8/8/8/8/R4RK1 b kq - 1 1");
+        assert_eq!(after(fen, "e1c1"), "r3k2r/8/8/8/8/8/8/2KR3R b kq - 1 1");
+
+        //no castling through an attacked square
+        let pos = Position::from_fen("r3k2r/8/8/8/8/8/5r2/R3K2R w KQkq - 0 1").unwrap();
+        assert!(!pos.is_legal(&Move::from_uci("e1g1").unwrap()));
+        assert!(pos.is_legal(&Move::from_uci("e1c1").unwrap()));
+    }
+
+    #[test]
+    fn en_passant_removes_the_captured_pawn() {
+        let fen = after("4k3/4p3/8/3P4/8/8/8/4K3 b - - 0 1", "e7e5");
+        assert_eq!(fen, "4k3/8/8/3Pp3/8/8/8/4K3 w - e6 0 2");
+        assert_eq!(after(&fen, "d5e6"), "4k3/8/4P3/8/8/8/8/4K3 b - - 0 2");
+    }
+
+    #[test]
+    fn promotions_need_a_piece() {
+        let fen = "8/1P2k3/8/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(after(fen, "b7b8q"), "1Q6/4k3/8/8/8/8/8/4K3 b - - 0 1");
+        assert_eq!(after(fen, "b7b8n"), "1N6/4k3/8/8/8/8/8/4K3 b - - 0 1");
+
+        let pos = Position::from_fen(fen).unwrap();
+        assert!(!pos.is_legal(&Move::from_uci("b7b8").unwrap()));
+    }
+}
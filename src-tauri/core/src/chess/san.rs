@@ -0,0 +1,125 @@
+use crate::chess::position::Position;
+use crate::chess::types::{Move, Role, Square};
+
+
+impl Position {
+    /// Writes a legal move in SAN, including the check or mate suffix.
+    pub fn san(&self, mv: &Move) -> String {
+        let role = self.piece_at(mv.from).map(|p| p.role).unwrap_or(Role::Pawn);
+        let is_capture = self.piece_at(mv.to).is_some()
+            || (role == Role::Pawn && mv.from.file() != mv.to.file());
+
+        let mut san = String::new();
+        if role == Role::King && (mv.from.file() as i8 - mv.to.file() as i8).abs() == 2 {
+            san.push_str(if mv.to.file() == 6 { "O-O" } else { "O-O-O" });
+        } else if role == Role::Pawn {
+            if is_capture {
+                san.push(mv.from.file_char());
+                san.push('x');
+            }
+            san.push_str(&mv.to.to_string());
+            if let Some(promotion) = mv.promotion {
+                san.push('=');
+                san.push(promotion.char().to_ascii_uppercase());
+            }
+        } else {
+            san.push(role.char().to_ascii_uppercase());
+
+            let others: Vec<Move> = self.legal_moves()
+                .into_iter()
+                .filter(|other| {
+                    other.to == mv.to
+                        && other.from != mv.from
+                        && self.piece_at(other.from).map(|p| p.role) == Some(role)
+                })
+                .collect();
+            if !others.is_empty() {
+                let same_file = others.iter().any(|o| o.from.file() == mv.from.file());
+                let same_rank = others.iter().any(|o| o.from.rank() == mv.from.rank());
+                if !same_file {
+                    san.push(mv.from.file_char());
+                } else if !same_rank {
+                    san.push(mv.from.rank_char());
+                } else {
+                    san.push_str(&mv.from.to_string());
+                }
+            }
+
+            if is_capture {
+                san.push('x');
+            }
+            san.push_str(&mv.to.to_string());
+        }
+
+        let mut after = self.clone();
+        after.play(mv);
+        if after.is_checkmate() {
+            san.push('#');
+        } else if after.is_check() {
+            san.push('+');
+        }
+        san
+    }
+
+    /// Resolves a SAN move (`Nbd7`, `exd8=Q+`, `O-O`) against the legal moves of this position.
+    pub fn parse_san(&self, san: &str) -> Option<Move> {
+        let san = san.trim_end_matches(['+', '#', '!', '?']);
+        let legal = self.legal_moves();
+
+        if matches!(san, "O-O" | "0-0" | "O-O-O" | "0-0-0") {
+            let to_file = if san.len() == 3 { 6 } else { 2 };
+            return legal.into_iter().find(|mv| {
+                self.piece_at(mv.from).map(|p| p.role) == Some(Role::King)
+                    && mv.from.file() == 4
+                    && mv.to.file() == to_file
+            });
+        }
+
+        let mut chars: Vec<char> = san.chars().filter(|c| *c != 'x' && *c != '-').collect();
+
+        let role = match chars.first() {
+            Some(c) if c.is_ascii_uppercase() => {
+                let role = Role::from_char(*c)?;
+                chars.remove(0);
+                role
+            }
+            Some(_) => Role::Pawn,
+            None => return None,
+        };
+
+        let mut promotion = None;
+        if let Some(last) = chars.last() {
+            if last.is_ascii_uppercase() {
+                promotion = Some(Role::from_char(*last)?);
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+            }
+        }
+
+        if chars.len() < 2 {
+            return None;
+        }
+        let dest: String = chars[chars.len() - 2..].iter().collect();
+        let to = Square::parse(&dest)?;
+        let disambiguation = &chars[..chars.len() - 2];
+
+        let mut candidates = legal.into_iter().filter(|mv| {
+            mv.to == to
+                && mv.promotion == promotion
+                && self.piece_at(mv.from).map(|p| p.role) == Some(role)
+                && disambiguation.iter().all(|c| match c {
+                    'a'..='h' => mv.from.file_char() == *c,
+                    '1'..='8' => mv.from.rank_char() == *c,
+                    _ => false,
+                })
+        });
+
+        let found = candidates.next()?;
+        if candidates.next().is_some() {
+            return None;
+        }
+        Some(found)
+    }
+}
@@ -0,0 +1,197 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Color {
+    White,
+    Black,
+}
+
+impl Color {
+    pub fn opposite(self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+
+    //rank index (0-based) where this color's pawns start
+    pub fn pawn_rank(self) -> u8 {
+        match self {
+            Color::White => 1,
+            Color::Black => 6,
+        }
+    }
+
+    pub fn back_rank(self) -> u8 {
+        match self {
+            Color::White => 0,
+            Color::Black => 7,
+        }
+    }
+
+    pub fn forward(self) -> i8 {
+        match self {
+            Color::White => 1,
+            Color::Black => -1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Role {
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
+}
+
+impl Role {
+    pub fn from_char(c: char) -> Option<Role> {
+        match c.to_ascii_lowercase() {
+            'p' => Some(Role::Pawn),
+            'n' => Some(Role::Knight),
+            'b' => Some(Role::Bishop),
+            'r' => Some(Role::Rook),
+            'q' => Some(Role::Queen),
+            'k' => Some(Role::King),
+            _ => None,
+        }
+    }
+
+    pub fn char(self) -> char {
+        match self {
+            Role::Pawn => 'p',
+            Role::Knight => 'n',
+            Role::Bishop => 'b',
+            Role::Rook => 'r',
+            Role::Queen => 'q',
+            Role::King => 'k',
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Piece {
+    pub color: Color,
+    pub role: Role,
+}
+
+impl Piece {
+    pub fn from_char(c: char) -> Option<Piece> {
+        let role = Role::from_char(c)?;
+        let color = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+        Some(Piece { color, role })
+    }
+
+    pub fn char(self) -> char {
+        match self.color {
+            Color::White => self.role.char().to_ascii_uppercase(),
+            Color::Black => self.role.char(),
+        }
+    }
+}
+
+/// Board square indexed from a1 = 0 to h8 = 63.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Square(pub u8);
+
+impl Square {
+    pub fn new(file: u8, rank: u8) -> Square {
+        Square(rank * 8 + file)
+    }
+
+    pub fn file(self) -> u8 {
+        self.0 % 8
+    }
+
+    pub fn rank(self) -> u8 {
+        self.0 / 8
+    }
+
+    pub fn offset(self, df: i8, dr: i8) -> Option<Square> {
+        let file = self.file() as i8 + df;
+        let rank = self.rank() as i8 + dr;
+        if (0..8).contains(&file) && (0..8).contains(&rank) {
+            Some(Square::new(file as u8, rank as u8))
+        } else {
+            None
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Square> {
+        let bytes = s.as_bytes();
+        if bytes.len() != 2 {
+            return None;
+        }
+        let file = bytes[0].wrapping_sub(b'a');
+        let rank = bytes[1].wrapping_sub(b'1');
+        if file < 8 && rank < 8 {
+            Some(Square::new(file, rank))
+        } else {
+            None
+        }
+    }
+
+    pub fn file_char(self) -> char {
+        (b'a' + self.file()) as char
+    }
+
+    pub fn rank_char(self) -> char {
+        (b'1' + self.rank()) as char
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.file_char(), self.rank_char())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub from: Square,
+    pub to: Square,
+    pub promotion: Option<Role>,
+}
+
+impl Move {
+    /// Parses the syntax of a UCI move (`e2e4`, `e7e8q`) without checking legality.
+    pub fn from_uci(uci: &str) -> Option<Move> {
+        if uci.len() != 4 && uci.len() != 5 {
+            return None;
+        }
+        let from = Square::parse(uci.get(0..2)?)?;
+        let to = Square::parse(uci.get(2..4)?)?;
+        let promotion = match uci.get(4..) {
+            Some("") | None => None,
+            Some(p) => match Role::from_char(p.chars().next()?)? {
+                Role::Pawn | Role::King => return None,
+                role => Some(role),
+            },
+        };
+        if from == to {
+            return None;
+        }
+        Some(Move { from, to, promotion })
+    }
+
+    pub fn uci(&self) -> String {
+        match self.promotion {
+            Some(role) => format!("{}{}{}", self.from, self.to, role.char()),
+            None => format!("{}{}", self.from, self.to),
+        }
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.uci())
+    }
+}
+
+pub const CASTLE_WHITE_KING: u8 = 1;
+pub const CASTLE_WHITE_QUEEN: u8 = 2;
+pub const CASTLE_BLACK_KING: u8 = 4;
+pub const CASTLE_BLACK_QUEEN: u8 = 8;
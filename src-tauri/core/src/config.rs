@@ -0,0 +1,91 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anki::collection::{Collection, CollectionBuilder};
+
+use crate::error::AnkiChessError;
+use crate::repository::migrations::{SchemaMigrations, SCHEMA_VERSION};
+use crate::services::notetype_service::NotetypeService;
+
+pub const COLLECTION_FILE_NAME: &str = "collection.ankichess";
+pub const PUZZLE_DB_FILE_NAME: &str = "ankichess_puzzles.sqlite";
+pub const PUZZLE_DUMP_FILE_NAME: &str = "lichess_db_puzzle.csv.zst";
+
+/// Where ankichess keeps its files. The app uses its app data dir, scripts and tests
+/// can point it at any directory.
+#[derive(Debug, Clone)]
+pub struct CoreConfig {
+    pub data_dir: PathBuf,
+}
+
+impl CoreConfig {
+    pub fn new(data_dir: impl Into<PathBuf>) -> Self {
+        Self { data_dir: data_dir.into() }
+    }
+
+    pub fn collection_path(&self) -> PathBuf {
+        self.data_dir.join(COLLECTION_FILE_NAME)
+    }
+
+    pub fn puzzle_db_path(&self) -> PathBuf {
+        self.data_dir.join(PUZZLE_DB_FILE_NAME)
+    }
+
+    //the dump is downloaded next to the database and removed once indexed
+    pub fn puzzle_dump_path(&self) -> PathBuf {
+        self.data_dir.join(PUZZLE_DUMP_FILE_NAME)
+    }
+
+    pub fn ensure_data_dir(&self) -> Result<(), AnkiChessError> {
+        if !self.data_dir.exists() {
+            fs::create_dir_all(&self.data_dir)?;
+        }
+        Ok(())
+    }
+
+    /// Opens the collection, creating it on first use, and brings the chess tables and
+    /// notes of older versions up to date. A failed migration fails the open.
+    pub fn open_collection(&self) -> Result<(Collection, CollectionUpgrade), AnkiChessError> {
+        self.ensure_data_dir()?;
+        let path = self.collection_path();
+        let path = path
+            .to_str()
+            .ok_or_else(|| AnkiChessError::InvalidInput(format!("Invalid collection path: {}", path.display())))?;
+
+        let mut col = CollectionBuilder::default().set_collection_path(path).build()?;
+
+        let found = SchemaMigrations::migrate(col.storage.db())?;
+        let upgrade = CollectionUpgrade {
+            schema_from: Some(found).filter(|found| *found < SCHEMA_VERSION),
+            migrated_notes: NotetypeService::migrate_linked_notes(&mut col)?,
+            normalized_sort_fields: NotetypeService::normalize_sort_fields(&mut col)?,
+        };
+        Ok((col, upgrade))
+    }
+}
+
+/// What `open_collection` changed to bring a collection of an older version up to date.
+#[derive(Debug, Default)]
+pub struct CollectionUpgrade {
+    //the schema version the chess tables were upgraded from
+    pub schema_from: Option<u32>,
+    pub migrated_notes: usize,
+    pub normalized_sort_fields: usize,
+}
+
+impl CollectionUpgrade {
+    /// One line per change, for the app and scripts to log. Empty when nothing changed.
+    pub fn messages(&self) -> Vec<String> {
+        let mut messages = Vec::new();
+        if let Some(found) = self.schema_from {
+            messages.push(format!("Upgraded chess tables from schema version {} to {}", found, SCHEMA_VERSION));
+        }
+        if self.migrated_notes > 0 {
+            messages.push(format!("Moved {} notes to the chess notetype", self.migrated_notes));
+        }
+        if self.normalized_sort_fields > 0 {
+            messages.push(format!("Updated the sort field of {} notes", self.normalized_sort_fields));
+        }
+        messages
+    }
+}
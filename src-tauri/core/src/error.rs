@@ -15,7 +15,6 @@ pub enum AnkiChessError {
     AnkiError(String),
     JsonError(String),
     IoError(String),
-    TauriError(String),
     NotFound(String),
     InvalidInput(String),
     MutexPoison(String),
@@ -24,6 +23,7 @@ pub enum AnkiChessError {
     CsvError(String),
     TimeError(String),
     JoinError(String),
+    Cancelled(String),
 }
 
 impl fmt::Display for AnkiChessError {
@@ -50,12 +50,6 @@ impl From<std::io::Error> for AnkiChessError {
     }
 }
 
-impl From<tauri::Error> for AnkiChessError {
-    fn from(err: tauri::Error) -> Self {
-        AnkiChessError::TauriError(err.to_string())
-    }
-}
-
 impl<T> From<PoisonError<T>> for AnkiChessError {
     fn from(err: PoisonError<T>) -> Self {
         AnkiChessError::MutexPoison(err.to_string())
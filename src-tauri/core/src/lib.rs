@@ -0,0 +1,10 @@
+//! Puzzle database, imports, decks and chess notes of ankichess. Nothing here needs the
+//! Tauri app: progress goes to a `JobObserver` and file locations come from a `CoreConfig`.
+
+pub mod chess;
+pub mod config;
+pub mod error;
+pub mod models;
+pub mod repository;
+pub mod services;
+pub mod shared;
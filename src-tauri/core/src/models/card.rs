@@ -22,6 +22,39 @@ pub struct UpdateNotePayload {
 }
 
 
+/// Scheduling state carried by the full CSV format. Only review cards keep their
+/// schedule on import, `due_in_days` is relative to the day of export.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CardSchedule {
+    pub card_type: u32,
+    pub due_in_days: i32,
+    pub interval: u32,
+    pub ease_factor: u32,
+    pub reps: u32,
+    pub lapses: u32,
+}
+
+/// How a puzzle went, sent along with the rating when a card is answered.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PuzzleAttempt {
+    pub milliseconds_taken: u32,
+    pub wrong_attempts: u32,
+    pub hints_used: u32,
+}
+
+/// Totals over every recorded attempt of a card.
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PuzzleReviewStats {
+    pub reviews: u32,
+    pub clean_solves: u32,
+    pub wrong_attempts: u32,
+    pub hints_used: u32,
+    pub total_ms: i64,
+    pub average_ms: i64,
+    pub last_ms: i64,
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct BrowseOptions {
@@ -54,6 +87,18 @@ pub struct BrowseCardInfo {
     pub has_setup_move: bool,
 }
 
+/// A note with its field values in notetype order, for tools that work on Anki notes
+/// rather than puzzles. Chess notes take their values from the linked puzzle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoteFields {
+    pub note_id: i64,
+    pub notetype: String,
+    pub tags: Vec<String>,
+    pub fields: Vec<(String, String)>,
+    pub card_ids: Vec<i64>,
+    pub modified_secs: i64,
+}
+
 #[derive(Debug, Serialize, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct PaginatedBrowseResult {
@@ -87,4 +132,6 @@ pub struct StudyCard {
     pub opening_tags: String,  
     pub comment: String,
     pub has_setup_move: bool,
+    pub review_stats: PuzzleReviewStats,
+    pub auto_grade: bool,
 }
\ No newline at end of file
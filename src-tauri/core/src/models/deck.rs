@@ -16,3 +16,12 @@ pub struct DeckLimitsPayload {
     pub new_cards_per_day: u32,
     pub reviews_per_day: u32,
 }
+
+/// Per-deck automatic grading. With `auto_grade` on the study screen skips the rating
+/// buttons; a `target_seconds` of 0 leaves solving time out of the grade.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeckGradingPayload {
+    pub auto_grade: bool,
+    pub target_seconds: u32,
+}
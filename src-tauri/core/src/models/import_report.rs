@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+
+use crate::chess::ChessError;
+
+//only the first skipped rows are kept, the counts stay exact
+pub const MAX_REPORTED_ROWS: usize = 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    MissingColumn,
+    MalformedFen,
+    IllegalMove,
+    DuplicateId,
+    //same position as a puzzle with another id
+    DuplicatePosition,
+    InvalidValue,
+    NoPuzzle,
+    MultipleTemplates,
+}
+
+impl SkipReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkipReason::MissingColumn => "missing_column",
+            SkipReason::MalformedFen => "malformed_fen",
+            SkipReason::IllegalMove => "illegal_move",
+            SkipReason::DuplicateId => "duplicate_id",
+            SkipReason::DuplicatePosition => "duplicate_position",
+            SkipReason::InvalidValue => "invalid_value",
+            SkipReason::NoPuzzle => "no_puzzle",
+            SkipReason::MultipleTemplates => "multiple_templates",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        [
+            SkipReason::MissingColumn,
+            SkipReason::MalformedFen,
+            SkipReason::IllegalMove,
+            SkipReason::DuplicateId,
+            SkipReason::DuplicatePosition,
+            SkipReason::InvalidValue,
+            SkipReason::NoPuzzle,
+            SkipReason::MultipleTemplates,
+        ]
+        .into_iter()
+        .find(|reason| reason.as_str() == value)
+    }
+}
+
+impl From<&ChessError> for SkipReason {
+    fn from(err: &ChessError) -> Self {
+        match err {
+            ChessError::InvalidFen(_) => SkipReason::MalformedFen,
+            ChessError::EmptySolution => SkipReason::MissingColumn,
+            ChessError::InvalidUci { .. } | ChessError::IllegalMove { .. } | ChessError::InvalidSan { .. } => SkipReason::IllegalMove,
+            ChessError::InvalidPgn(_) => SkipReason::InvalidValue,
+        }
+    }
+}
+
+/// A row that was not imported. `line` is the line in a csv file, the game number in a
+/// pgn file and the note number in a package; rows read from the puzzle database have none.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedRow {
+    pub line: Option<u64>,
+    pub reason: SkipReason,
+    pub puzzle_id: Option<String>,
+    pub detail: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    //0 until the report is saved
+    pub id: i64,
+    pub source: String,
+    pub deck_id: i64,
+    pub created_at: u64,
+    pub processed: usize,
+    pub imported: usize,
+    //notes added for puzzles of other decks, and notes moved here from other decks
+    pub linked: usize,
+    pub moved: usize,
+    pub skipped: usize,
+    pub skipped_rows: Vec<SkippedRow>,
+}
+
+impl ImportReport {
+    pub fn new(source: &str, deck_id: i64) -> Self {
+        Self { source: source.to_string(), deck_id, ..Default::default() }
+    }
+
+    pub fn summary(&self) -> String {
+        let mut parts = vec![format!("Added {} new notes", self.imported)];
+        if self.linked > 0 {
+            parts.push(format!("linked {} from other decks", self.linked));
+        }
+        if self.moved > 0 {
+            parts.push(format!("moved {} from other decks", self.moved));
+        }
+        parts.push(format!("skipped {}", self.skipped));
+        format!("{}.", parts.join(", "))
+    }
+
+    pub fn skip(&mut self, line: Option<u64>, reason: SkipReason, puzzle_id: Option<&str>, detail: impl Into<String>) {
+        self.skipped += 1;
+        if self.skipped_rows.len() < MAX_REPORTED_ROWS {
+            self.skipped_rows.push(SkippedRow {
+                line,
+                reason,
+                puzzle_id: puzzle_id.map(str::to_string),
+                detail: detail.into(),
+            });
+        }
+    }
+}
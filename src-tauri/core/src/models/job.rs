@@ -0,0 +1,45 @@
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    Import,
+    Download,
+    Index,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct JobCounts {
+    pub processed: usize,
+    pub imported: usize,
+    pub skipped: usize,
+    pub total: Option<usize>,
+}
+
+/// State of a background job as sent with every `JOB_PROGRESS` event.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct JobInfo {
+    pub id: u64,
+    pub kind: JobKind,
+    pub label: String,
+    pub status: JobStatus,
+    pub message: String,
+    #[serde(flatten)]
+    pub counts: JobCounts,
+    pub error: Option<String>,
+    //set by imports once their report is saved
+    pub report_id: Option<i64>,
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+}
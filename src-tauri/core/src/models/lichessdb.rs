@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::import_report::SkippedRow;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadProgress {
+    pub downloaded: u64,
+    pub total: u64,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexingProgress {
+    pub status: String,
+    pub processed_count: u64,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DbStatus {
+    pub db_exists: bool,
+    pub last_updated: Option<u64>,
+    pub puzzle_count: i64,
+}
+
+/// Outcome of the last (re-)index of the puzzle database. `initial` is set when the
+/// database was empty, in that case no per-puzzle changes are recorded.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PuzzleDbChangeReport {
+    pub indexed: u64,
+    pub initial: bool,
+    pub added: u64,
+    pub removed: u64,
+    pub updated: u64,
+    #[serde(default)]
+    pub changes: Vec<PuzzleChange>,
+    //rows of the dump that could not be read
+    #[serde(default)]
+    pub skipped: u64,
+    #[serde(default)]
+    pub skipped_rows: Vec<SkippedRow>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PuzzleChange {
+    pub puzzle_id: String,
+    pub change: String,
+    pub old_rating: Option<i32>,
+    pub new_rating: Option<i32>,
+    pub old_popularity: Option<i32>,
+    pub new_popularity: Option<i32>,
+    pub old_themes: Option<String>,
+    pub new_themes: Option<String>,
+}
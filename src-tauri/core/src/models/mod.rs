@@ -2,4 +2,6 @@ pub mod card;
 pub mod deck;
 pub mod puzzle;
 pub mod lichessdb;
-pub mod bootstrap;
\ No newline at end of file
+pub mod bootstrap;
+pub mod job;
+pub mod import_report;
\ No newline at end of file
@@ -0,0 +1,229 @@
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ImportOptions {
+    pub deck_id: i64,
+    pub min_rating: Option<u32>,
+    pub max_rating: Option<u32>,
+    pub min_popularity: Option<i32>,
+    pub max_popularity: Option<i32>,
+    pub limit: Option<usize>,
+    pub themes: Option<Vec<String>>,
+    pub opening_tags: Option<Vec<String>>,
+    /// Whether a puzzle needs one (`any`) or every (`all`) theme of `themes`.
+    #[serde(default)]
+    pub theme_match: TagMatch,
+    pub exclude_themes: Option<Vec<String>>,
+    #[serde(default)]
+    pub opening_match: TagMatch,
+    pub exclude_opening_tags: Option<Vec<String>>,
+    /// How `limit` puzzles are picked from the matches, only used when `limit` is set.
+    #[serde(default)]
+    pub sampling: SamplingMode,
+    /// Seed of the random modes, the same seed and filters give the same puzzles.
+    pub seed: Option<u64>,
+    /// Width of the rating buckets of `rating_stratified`, 100 by default.
+    pub rating_bucket_width: Option<u32>,
+    #[serde(default)]
+    pub duplicates: DuplicatePolicy,
+}
+
+/// What an import does with a puzzle that is already in another deck of the collection,
+/// matched by puzzle id or by position. Puzzles already in the target deck are always skipped.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicatePolicy {
+    #[default]
+    Skip,
+    /// Adds a note to the target deck that shares the existing puzzle.
+    Link,
+    /// Moves the existing cards to the target deck.
+    Move,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SamplingMode {
+    /// The first matches by puzzle id.
+    #[default]
+    First,
+    Random,
+    /// The same number of puzzles from every rating bucket that has matches.
+    RatingStratified,
+    /// The same number of puzzles for each theme in `themes`.
+    ThemeBalanced,
+}
+
+/// How a list of themes or opening tags is combined. Tags are always matched whole,
+/// `mate` does not match `mateIn2`.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatch {
+    #[default]
+    Any,
+    All,
+}
+
+/// What an import with some `ImportOptions` would do. `seed` is set for the random
+/// sampling modes, passing it back to the import picks the same puzzles.
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportPreview {
+    pub matching: u64,
+    pub already_in_deck: u64,
+    pub in_other_decks: u64,
+    pub to_import: u64,
+    pub seed: Option<u64>,
+    pub rating_histogram: Vec<RatingBucket>,
+    pub theme_breakdown: Vec<ThemeCount>,
+    pub sample: Vec<ChessPuzzle>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RatingBucket {
+    pub from: u32,
+    pub to: u32,
+    pub count: u64,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ThemeCount {
+    pub theme: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CsvImportPayload {
+    pub deck_id: i64,
+    pub csv_content: String,
+    #[serde(default)]
+    pub duplicates: DuplicatePolicy,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PgnImportPayload {
+    pub deck_id: i64,
+    pub pgn_content: String,
+    #[serde(default)]
+    pub duplicates: DuplicatePolicy,
+}
+
+/// Names of the note fields holding the puzzle. Unset entries are detected from
+/// common field names (`FEN`, `Moves`/`Solution`, `Comment`...).
+#[derive(Debug, Deserialize, Default)]
+pub struct ApkgFieldMapping {
+    pub fen_field: Option<String>,
+    pub solution_field: Option<String>,
+    pub comment_field: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApkgImportPayload {
+    pub file_path: String,
+    pub field_mapping: Option<ApkgFieldMapping>,
+}
+
+//full lichess open db data
+#[derive(Debug, Deserialize)]
+pub struct PuzzleRecord {
+    #[serde(rename = "PuzzleId")]
+    pub puzzle_id: String,
+    #[serde(rename = "FEN")]
+    pub fen: String,
+    #[serde(rename = "Moves")]
+    pub moves: String,
+    #[serde(rename = "Rating")]
+    pub rating: u32,
+    #[serde(rename = "RatingDeviation")] 
+    pub rating_deviation: i32,
+    #[serde(rename = "Popularity")]
+    pub popularity: i32,
+    #[serde(rename = "NbPlays")]        
+    pub nb_plays: i32,
+    #[serde(rename = "Themes")]
+    pub themes: String,
+    #[serde(rename = "GameUrl")]      
+    pub game_url: String,
+    #[serde(rename = "OpeningTags")]    
+    pub opening_tags: Option<String>,  
+}
+
+use serde::{Serialize};
+use uuid::Uuid;
+
+use crate::models::card::AddNotePayload;
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChessPuzzle {
+    pub puzzle_id: String,
+    pub fen: String,
+    pub moves: String,
+    pub rating: i32,
+    pub rating_deviation: i32,
+    pub popularity: i32,
+    pub nb_plays: i32,
+    pub themes: String,
+    pub game_url: String,
+    pub opening_tags: String,
+    pub comment: String,
+    pub has_setup_move: bool, 
+}
+
+impl Default for ChessPuzzle {
+    fn default() -> Self {
+        Self {
+            puzzle_id: "".to_string(),
+            fen: "".to_string(),
+            moves: "".to_string(),
+            rating: 0,
+            rating_deviation: 0,
+            popularity: 0,
+            nb_plays: 0,
+            themes: "manual".to_string(),
+            game_url: "".to_string(),
+            opening_tags: "".to_string(),
+            comment: "".to_string(),
+            has_setup_move: false,
+        }
+    }
+}
+
+
+
+
+
+
+impl From<AddNotePayload> for ChessPuzzle {
+    fn from(payload: AddNotePayload) -> Self {
+        Self {
+            
+            puzzle_id: Uuid::new_v4().simple().to_string(),
+            
+            fen: payload.fen,
+            moves: payload.solution, 
+            rating: payload.rating.unwrap_or(0),
+            themes: payload.themes.unwrap_or_else(|| "manual".to_string()),
+            game_url: payload.game_url.unwrap_or_default(),
+            opening_tags: payload.opening_tags.unwrap_or_default(),
+            comment: payload.comment.unwrap_or_default(),
+            
+            
+            has_setup_move: false, 
+            rating_deviation: 0,
+            popularity: 0,
+            nb_plays: 0,
+        }
+    }
+}
+
+//a chess note of the collection, as seen by the duplicate check of the imports
+#[derive(Debug, Clone)]
+pub struct LinkedPuzzle {
+    pub nid: i64,
+    pub deck_id: i64,
+    pub puzzle_id: String,
+    pub fen_key: Option<String>,
+}
@@ -0,0 +1,484 @@
+use rusqlite::{params, Connection, OptionalExtension, Result, Statement, ToSql};
+
+use crate::models::lichessdb::{PuzzleChange, PuzzleDbChangeReport};
+use crate::models::puzzle::{ChessPuzzle, ImportOptions, PuzzleRecord, RatingBucket, TagMatch, ThemeCount};
+
+//columns whose change is worth reporting, nb_plays and rating deviation move with every dump
+const REPORTED_CHANGE: &str = "p.FEN IS NOT s.FEN OR p.Moves IS NOT s.Moves OR p.Rating IS NOT s.Rating
+    OR p.Popularity IS NOT s.Popularity OR p.Themes IS NOT s.Themes OR p.OpeningTags IS NOT s.OpeningTags";
+
+const ANY_CHANGE: &str = "p.FEN IS NOT s.FEN OR p.Moves IS NOT s.Moves OR p.Rating IS NOT s.Rating
+    OR p.RatingDeviation IS NOT s.RatingDeviation OR p.Popularity IS NOT s.Popularity OR p.NbPlays IS NOT s.NbPlays
+    OR p.Themes IS NOT s.Themes OR p.GameUrl IS NOT s.GameUrl OR p.OpeningTags IS NOT s.OpeningTags";
+
+pub struct LichessdbRepository;
+
+impl LichessdbRepository {
+    pub fn init_tables(conn: &Connection) -> Result<()> {
+        conn.execute_batch(
+            "BEGIN;
+            CREATE TABLE IF NOT EXISTS puzzles (
+                PuzzleId TEXT PRIMARY KEY,
+                FEN TEXT NOT NULL,
+                Moves TEXT NOT NULL,
+                Rating INTEGER NOT NULL,
+                RatingDeviation INTEGER,
+                Popularity INTEGER NOT NULL,
+                NbPlays INTEGER,
+                Themes TEXT,
+                GameUrl TEXT,
+                OpeningTags TEXT
+            );
+            CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT);
+            CREATE INDEX IF NOT EXISTS idx_rating ON puzzles (Rating);
+            CREATE INDEX IF NOT EXISTS idx_popularity ON puzzles (Popularity);
+
+            CREATE TABLE IF NOT EXISTS puzzle_changes (
+                PuzzleId TEXT PRIMARY KEY,
+                Change TEXT NOT NULL,
+                OldRating INTEGER,
+                NewRating INTEGER,
+                OldPopularity INTEGER,
+                NewPopularity INTEGER,
+                OldThemes TEXT,
+                NewThemes TEXT
+            );
+
+            CREATE TABLE IF NOT EXISTS puzzle_themes (
+                Theme TEXT NOT NULL COLLATE NOCASE,
+                PuzzleId TEXT NOT NULL,
+                PRIMARY KEY (Theme, PuzzleId)
+            ) WITHOUT ROWID;
+            CREATE INDEX IF NOT EXISTS idx_themes_puzzle ON puzzle_themes (PuzzleId);
+
+            CREATE TABLE IF NOT EXISTS puzzle_openings (
+                Opening TEXT NOT NULL COLLATE NOCASE,
+                PuzzleId TEXT NOT NULL,
+                PRIMARY KEY (Opening, PuzzleId)
+            ) WITHOUT ROWID;
+            CREATE INDEX IF NOT EXISTS idx_openings_puzzle ON puzzle_openings (PuzzleId);
+            COMMIT;"
+        )
+    }
+
+    /// Starts an empty staging table the new dump is loaded into before diffing.
+    pub fn create_staging(conn: &Connection) -> Result<()> {
+        conn.execute_batch(
+            "DROP TABLE IF EXISTS puzzles_staging;
+            CREATE TABLE puzzles_staging (
+                PuzzleId TEXT PRIMARY KEY,
+                FEN TEXT NOT NULL,
+                Moves TEXT NOT NULL,
+                Rating INTEGER NOT NULL,
+                RatingDeviation INTEGER,
+                Popularity INTEGER NOT NULL,
+                NbPlays INTEGER,
+                Themes TEXT,
+                GameUrl TEXT,
+                OpeningTags TEXT
+            );"
+        )
+    }
+
+    pub fn drop_staging(conn: &Connection) -> Result<()> {
+        conn.execute_batch("DROP TABLE IF EXISTS puzzles_staging;")
+    }
+
+    pub fn prepare_staging_insert(conn: &Connection) -> Result<Statement<'_>> {
+        conn.prepare(
+            "INSERT OR REPLACE INTO puzzles_staging
+            (PuzzleId, FEN, Moves, Rating, RatingDeviation, Popularity, NbPlays, Themes, GameUrl, OpeningTags)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"
+        )
+    }
+
+    pub fn insert_staging(stmt: &mut Statement, record: &PuzzleRecord) -> Result<()> {
+        stmt.execute(params![
+            record.puzzle_id, record.fen, record.moves, record.rating,
+            record.rating_deviation, record.popularity, record.nb_plays,
+            record.themes, record.game_url, record.opening_tags.as_deref().unwrap_or_default()
+        ])?;
+        Ok(())
+    }
+
+    /// Records how the staged dump differs from `puzzles` in `puzzle_changes`, then brings
+    /// `puzzles` in line with it, touching only rows that changed. On the first index the
+    /// per-puzzle report is skipped, every row would be an addition.
+    pub fn apply_staging(conn: &Connection) -> Result<PuzzleDbChangeReport> {
+        let existing: u64 = conn.query_row("SELECT COUNT(*) FROM puzzles", [], |row| row.get(0))?;
+        let staged: u64 = conn.query_row("SELECT COUNT(*) FROM puzzles_staging", [], |row| row.get(0))?;
+
+        conn.execute("DELETE FROM puzzle_changes", [])?;
+
+        let mut report = PuzzleDbChangeReport { indexed: staged, initial: existing == 0, ..Default::default() };
+
+        Self::ensure_tag_index(conn)?;
+        conn.execute_batch(
+            "DROP TABLE IF EXISTS temp.puzzles_retag;
+             CREATE TEMP TABLE puzzles_retag AS
+             SELECT s.PuzzleId FROM puzzles_staging s
+             LEFT JOIN puzzles p ON p.PuzzleId = s.PuzzleId
+             WHERE p.PuzzleId IS NULL OR p.Themes IS NOT s.Themes OR p.OpeningTags IS NOT s.OpeningTags;"
+        )?;
+
+        if !report.initial {
+            report.added = conn.execute(
+                "INSERT INTO puzzle_changes (PuzzleId, Change, NewRating, NewPopularity, NewThemes)
+                 SELECT s.PuzzleId, 'added', s.Rating, s.Popularity, s.Themes
+                 FROM puzzles_staging s
+                 WHERE NOT EXISTS (SELECT 1 FROM puzzles p WHERE p.PuzzleId = s.PuzzleId)",
+                [],
+            )? as u64;
+
+            report.removed = conn.execute(
+                "INSERT INTO puzzle_changes (PuzzleId, Change, OldRating, OldPopularity, OldThemes)
+                 SELECT p.PuzzleId, 'removed', p.Rating, p.Popularity, p.Themes
+                 FROM puzzles p
+                 WHERE NOT EXISTS (SELECT 1 FROM puzzles_staging s WHERE s.PuzzleId = p.PuzzleId)",
+                [],
+            )? as u64;
+
+            report.updated = conn.execute(
+                &format!(
+                    "INSERT INTO puzzle_changes
+                     (PuzzleId, Change, OldRating, NewRating, OldPopularity, NewPopularity, OldThemes, NewThemes)
+                     SELECT s.PuzzleId, 'updated', p.Rating, s.Rating, p.Popularity, s.Popularity, p.Themes, s.Themes
+                     FROM puzzles_staging s
+                     JOIN puzzles p ON p.PuzzleId = s.PuzzleId
+                     WHERE {}",
+                    REPORTED_CHANGE
+                ),
+                [],
+            )? as u64;
+        } else {
+            report.added = staged;
+        }
+
+        conn.execute(
+            "DELETE FROM puzzles
+             WHERE NOT EXISTS (SELECT 1 FROM puzzles_staging s WHERE s.PuzzleId = puzzles.PuzzleId)",
+            [],
+        )?;
+
+        conn.execute(
+            &format!(
+                "UPDATE puzzles AS p
+                 SET FEN = s.FEN, Moves = s.Moves, Rating = s.Rating, RatingDeviation = s.RatingDeviation,
+                     Popularity = s.Popularity, NbPlays = s.NbPlays, Themes = s.Themes,
+                     GameUrl = s.GameUrl, OpeningTags = s.OpeningTags
+                 FROM puzzles_staging s
+                 WHERE p.PuzzleId = s.PuzzleId AND ({})",
+                ANY_CHANGE
+            ),
+            [],
+        )?;
+
+        conn.execute(
+            "INSERT INTO puzzles
+             (PuzzleId, FEN, Moves, Rating, RatingDeviation, Popularity, NbPlays, Themes, GameUrl, OpeningTags)
+             SELECT s.PuzzleId, s.FEN, s.Moves, s.Rating, s.RatingDeviation, s.Popularity, s.NbPlays,
+                    s.Themes, s.GameUrl, s.OpeningTags
+             FROM puzzles_staging s
+             WHERE NOT EXISTS (SELECT 1 FROM puzzles p WHERE p.PuzzleId = s.PuzzleId)",
+            [],
+        )?;
+
+        for table in ["puzzle_themes", "puzzle_openings"] {
+            conn.execute(
+                &format!(
+                    "DELETE FROM {table}
+                     WHERE PuzzleId IN (SELECT PuzzleId FROM puzzles_retag)
+                        OR NOT EXISTS (SELECT 1 FROM puzzles p WHERE p.PuzzleId = {table}.PuzzleId)"
+                ),
+                [],
+            )?;
+        }
+        Self::insert_tags(conn, "SELECT PuzzleId FROM puzzles_retag")?;
+        conn.execute_batch("DROP TABLE temp.puzzles_retag;")?;
+
+        Ok(report)
+    }
+
+    /// Fills `puzzle_themes` and `puzzle_openings` from scratch once, for databases indexed
+    /// before those tables existed.
+    pub fn ensure_tag_index(conn: &Connection) -> Result<()> {
+        let indexed: Option<String> = conn
+            .query_row("SELECT value FROM meta WHERE key = 'tag_index'", [], |row| row.get(0))
+            .optional()?;
+        if indexed.is_some() {
+            return Ok(());
+        }
+
+        conn.execute("DELETE FROM puzzle_themes", [])?;
+        conn.execute("DELETE FROM puzzle_openings", [])?;
+        Self::insert_tags(conn, "SELECT PuzzleId FROM puzzles")?;
+        conn.execute("INSERT OR REPLACE INTO meta (key, value) VALUES ('tag_index', '1')", [])?;
+        Ok(())
+    }
+
+    //splits the space separated Themes and OpeningTags of the selected puzzles into their own rows
+    fn insert_tags(conn: &Connection, puzzle_ids: &str) -> Result<()> {
+        for (table, tag_column, source_column) in [
+            ("puzzle_themes", "Theme", "Themes"),
+            ("puzzle_openings", "Opening", "OpeningTags"),
+        ] {
+            conn.execute(
+                &format!(
+                    "WITH RECURSIVE split(PuzzleId, Tag, Rest) AS (
+                         SELECT PuzzleId, '', COALESCE({source_column}, '') || ' '
+                         FROM puzzles WHERE PuzzleId IN ({puzzle_ids})
+                         UNION ALL
+                         SELECT PuzzleId, substr(Rest, 1, instr(Rest, ' ') - 1), substr(Rest, instr(Rest, ' ') + 1)
+                         FROM split WHERE Rest <> ''
+                     )
+                     INSERT OR IGNORE INTO {table} ({tag_column}, PuzzleId)
+                     SELECT Tag, PuzzleId FROM split WHERE Tag <> ''"
+                ),
+                [],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Builds the `WHERE` clause selecting the puzzles matching `options` and its parameters.
+    /// Columns are unqualified, so the clause applies to a query on `puzzles`.
+    pub fn puzzle_filter(options: &ImportOptions) -> (String, Vec<Box<dyn ToSql>>) {
+        let mut clauses: Vec<String> = vec!["1=1".to_string()];
+        let mut params: Vec<Box<dyn ToSql>> = Vec::new();
+
+        if let Some(min) = options.min_rating { clauses.push("Rating >= ?".into()); params.push(Box::new(min)); }
+        if let Some(max) = options.max_rating { clauses.push("Rating <= ?".into()); params.push(Box::new(max)); }
+        if let Some(min) = options.min_popularity { clauses.push("Popularity >= ?".into()); params.push(Box::new(min)); }
+        if let Some(max) = options.max_popularity { clauses.push("Popularity <= ?".into()); params.push(Box::new(max)); }
+
+        let mut tag_clause = |table: &str, column: &str, tags: &Option<Vec<String>>, matching: Option<TagMatch>| {
+            let tags = clean_tags(tags);
+            if tags.is_empty() {
+                return;
+            }
+            let placeholders = vec!["?"; tags.len()].join(",");
+            let lookup = format!("SELECT PuzzleId FROM {} WHERE {} IN ({})", table, column, placeholders);
+            clauses.push(match matching {
+                Some(TagMatch::Any) => format!("PuzzleId IN ({})", lookup),
+                Some(TagMatch::All) => format!("PuzzleId IN ({} GROUP BY PuzzleId HAVING COUNT(*) = {})", lookup, tags.len()),
+                None => format!("PuzzleId NOT IN ({})", lookup),
+            });
+            params.extend(tags.into_iter().map(|t| Box::new(t) as Box<dyn ToSql>));
+        };
+
+        tag_clause("puzzle_themes", "Theme", &options.themes, Some(options.theme_match));
+        tag_clause("puzzle_themes", "Theme", &options.exclude_themes, None);
+        tag_clause("puzzle_openings", "Opening", &options.opening_tags, Some(options.opening_match));
+        tag_clause("puzzle_openings", "Opening", &options.exclude_opening_tags, None);
+
+        (clauses.join(" AND "), params)
+    }
+
+    pub fn count_matching(conn: &Connection, options: &ImportOptions) -> Result<u64> {
+        let (filter, params) = Self::puzzle_filter(options);
+        conn.query_row(
+            &format!("SELECT COUNT(*) FROM puzzles WHERE {}", filter),
+            rusqlite::params_from_iter(params.iter().map(|p| p.as_ref())),
+            |row| row.get(0),
+        )
+    }
+
+    //how many of the given puzzle ids match, in chunks to stay under the variable limit
+    pub fn count_matching_ids(conn: &Connection, options: &ImportOptions, puzzle_ids: &[String]) -> Result<u64> {
+        let (filter, params) = Self::puzzle_filter(options);
+        let mut count = 0;
+        for chunk in puzzle_ids.chunks(500) {
+            let placeholders = vec!["?"; chunk.len()].join(",");
+            let sql = format!("SELECT COUNT(*) FROM puzzles WHERE {} AND PuzzleId IN ({})", filter, placeholders);
+            let all_params = params.iter().map(|p| p.as_ref()).chain(chunk.iter().map(|id| id as &dyn ToSql));
+            count += conn.query_row(&sql, rusqlite::params_from_iter(all_params), |row| row.get::<_, u64>(0))?;
+        }
+        Ok(count)
+    }
+
+    pub fn rating_histogram(conn: &Connection, options: &ImportOptions, width: u32) -> Result<Vec<RatingBucket>> {
+        let (filter, params) = Self::puzzle_filter(options);
+        let mut stmt = conn.prepare(&format!(
+            "SELECT Rating / {width} AS Bucket, COUNT(*) FROM puzzles WHERE {filter} GROUP BY Bucket ORDER BY Bucket"
+        ))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(params.iter().map(|p| p.as_ref())), |row| {
+            let bucket: u32 = row.get(0)?;
+            Ok(RatingBucket { from: bucket * width, to: (bucket + 1) * width - 1, count: row.get(1)? })
+        })?;
+        rows.collect()
+    }
+
+    //most common themes among the matches
+    pub fn theme_breakdown(conn: &Connection, options: &ImportOptions, limit: usize) -> Result<Vec<ThemeCount>> {
+        let (filter, mut params) = Self::puzzle_filter(options);
+        params.push(Box::new(limit as i64));
+        let mut stmt = conn.prepare(&format!(
+            "SELECT Theme, COUNT(*) AS Matches FROM puzzle_themes
+             WHERE PuzzleId IN (SELECT PuzzleId FROM puzzles WHERE {})
+             GROUP BY Theme ORDER BY Matches DESC, Theme LIMIT ?",
+            filter
+        ))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(params.iter().map(|p| p.as_ref())), |row| {
+            Ok(ThemeCount { theme: row.get(0)?, count: row.get(1)? })
+        })?;
+        rows.collect()
+    }
+
+    pub fn get_changes(conn: &Connection, limit: usize) -> Result<Vec<PuzzleChange>> {
+        let mut stmt = conn.prepare(
+            "SELECT PuzzleId, Change, OldRating, NewRating, OldPopularity, NewPopularity, OldThemes, NewThemes
+             FROM puzzle_changes
+             ORDER BY Change, PuzzleId
+             LIMIT ?1"
+        )?;
+
+        let rows = stmt.query_map(params![limit as i64], |row| {
+            Ok(PuzzleChange {
+                puzzle_id: row.get(0)?,
+                change: row.get(1)?,
+                old_rating: row.get(2)?,
+                new_rating: row.get(3)?,
+                old_popularity: row.get(4)?,
+                new_popularity: row.get(5)?,
+                old_themes: row.get(6)?,
+                new_themes: row.get(7)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    //only the metadata columns, fen and moves of imported puzzles are left alone
+    pub fn get_metadata(conn: &Connection, puzzle_id: &str) -> Result<Option<ChessPuzzle>> {
+        conn.query_row(
+            "SELECT Rating, RatingDeviation, Popularity, NbPlays, Themes, OpeningTags
+             FROM puzzles WHERE PuzzleId = ?1",
+            params![puzzle_id],
+            |row| Ok(ChessPuzzle {
+                puzzle_id: puzzle_id.to_string(),
+                rating: row.get(0)?,
+                rating_deviation: row.get::<_, Option<i32>>(1)?.unwrap_or(0),
+                popularity: row.get(2)?,
+                nb_plays: row.get::<_, Option<i32>>(3)?.unwrap_or(0),
+                themes: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                opening_tags: row.get::<_, Option<String>>(5)?.unwrap_or_default(),
+                ..Default::default()
+            }),
+        )
+        .optional()
+    }
+}
+
+//trimmed, without blanks and without case-insensitive duplicates, so COUNT(*) in an `all` match is exact
+fn clean_tags(tags: &Option<Vec<String>>) -> Vec<String> {
+    let mut cleaned: Vec<String> = Vec::new();
+    for tag in tags.iter().flatten().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        if !cleaned.iter().any(|c| c.eq_ignore_ascii_case(tag)) {
+            cleaned.push(tag.to_string());
+        }
+    }
+    cleaned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: &str, rating: u32, themes: &str, nb_plays: i32) -> PuzzleRecord {
+        PuzzleRecord {
+            puzzle_id: id.to_string(),
+            fen: "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1".to_string(),
+            moves: "d1d8".to_string(),
+            rating,
+            rating_deviation: 80,
+            popularity: 90,
+            nb_plays,
+            themes: themes.to_string(),
+            game_url: String::new(),
+            opening_tags: None,
+        }
+    }
+
+    //one index run: stage the dump, diff it against `puzzles` and drop the staging table
+    fn index(conn: &Connection, records: &[PuzzleRecord]) -> PuzzleDbChangeReport {
+        LichessdbRepository::create_staging(conn).unwrap();
+        {
+            let mut stmt = LichessdbRepository::prepare_staging_insert(conn).unwrap();
+            for record in records {
+                LichessdbRepository::insert_staging(&mut stmt, record).unwrap();
+            }
+        }
+        let report = LichessdbRepository::apply_staging(conn).unwrap();
+        LichessdbRepository::drop_staging(conn).unwrap();
+        report
+    }
+
+    #[test]
+    fn apply_staging_counts_added_removed_and_updated_puzzles() {
+        let conn = Connection::open_in_memory().unwrap();
+        LichessdbRepository::init_tables(&conn).unwrap();
+
+        let first = index(&conn, &[record("a", 1000, "fork", 1), record("b", 1200, "pin", 1), record("c", 1500, "mate", 1)]);
+        assert!(first.initial);
+        assert_eq!((first.indexed, first.added, first.removed, first.updated), (3, 3, 0, 0));
+        assert!(LichessdbRepository::get_changes(&conn, 10).unwrap().is_empty());
+
+        //a new play count alone is not a reported change, but the row is still brought up to date
+        let second = index(&conn, &[record("a", 1050, "fork", 5), record("b", 1200, "pin", 9), record("d", 900, "skewer", 1)]);
+        assert!(!second.initial);
+        assert_eq!((second.indexed, second.added, second.removed, second.updated), (3, 1, 1, 1));
+
+        let changes = LichessdbRepository::get_changes(&conn, 10).unwrap();
+        let change_of = |id: &str| changes.iter().find(|c| c.puzzle_id == id).map(|c| c.change.as_str());
+        assert_eq!(change_of("a"), Some("updated"));
+        assert_eq!(change_of("b"), None);
+        assert_eq!(change_of("c"), Some("removed"));
+        assert_eq!(change_of("d"), Some("added"));
+
+        let b = LichessdbRepository::get_metadata(&conn, "b").unwrap().unwrap();
+        assert_eq!(b.nb_plays, 9);
+        assert!(LichessdbRepository::get_metadata(&conn, "c").unwrap().is_none());
+    }
+
+    fn matching_ids(conn: &Connection, options: serde_json::Value) -> Vec<String> {
+        let options: ImportOptions = serde_json::from_value(options).unwrap();
+        let (filter, params) = LichessdbRepository::puzzle_filter(&options);
+        let mut stmt = conn.prepare(&format!("SELECT PuzzleId FROM puzzles WHERE {} ORDER BY PuzzleId", filter)).unwrap();
+        stmt.query_map(rusqlite::params_from_iter(params.iter()), |row| row.get(0))
+            .unwrap()
+            .map(|id| id.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn tags_match_whole_words_in_all_any_and_exclude_filters() {
+        let conn = Connection::open_in_memory().unwrap();
+        LichessdbRepository::init_tables(&conn).unwrap();
+
+        let mut a = record("a", 1000, "mate mateIn2 short", 1);
+        a.opening_tags = Some("Sicilian_Defense Sicilian_Defense_Najdorf".to_string());
+        let mut c = record("c", 1000, "fork  middlegame", 1);
+        c.opening_tags = Some("French_Defense".to_string());
+        index(&conn, &[a, record("b", 1000, "mateIn2 backRankMate", 1), c]);
+
+        //`mate` is not a substring match for `mateIn2` or `backRankMate`
+        assert_eq!(matching_ids(&conn, serde_json::json!({ "deck_id": 1, "themes": ["mate"] })), ["a"]);
+        assert_eq!(matching_ids(&conn, serde_json::json!({ "deck_id": 1, "opening_tags": ["Sicilian"] })), Vec::<String>::new());
+
+        assert_eq!(matching_ids(&conn, serde_json::json!({ "deck_id": 1, "themes": ["mateIn2", "fork"] })), ["a", "b", "c"]);
+        assert_eq!(
+            matching_ids(&conn, serde_json::json!({ "deck_id": 1, "themes": ["mateIn2", "backrankmate", " "], "theme_match": "all" })),
+            ["b"]
+        );
+        assert_eq!(
+            matching_ids(&conn, serde_json::json!({ "deck_id": 1, "opening_tags": ["sicilian_defense", "SICILIAN_DEFENSE"], "opening_match": "all" })),
+            ["a"]
+        );
+
+        assert_eq!(
+            matching_ids(&conn, serde_json::json!({ "deck_id": 1, "themes": ["mateIn2"], "exclude_themes": ["backRankMate"] })),
+            ["a"]
+        );
+        assert_eq!(matching_ids(&conn, serde_json::json!({ "deck_id": 1, "exclude_themes": ["mate"] })), ["b", "c"]);
+        assert_eq!(matching_ids(&conn, serde_json::json!({ "deck_id": 1, "exclude_opening_tags": ["French_Defense"] })), ["a", "b"]);
+    }
+}
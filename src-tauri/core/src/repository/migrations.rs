@@ -0,0 +1,184 @@
+use rusqlite::{params, Connection, Result};
+
+use crate::error::AnkiChessError;
+use crate::repository::puzzle_repo::PuzzleRepository;
+
+pub const META_SCHEMA_VERSION: &str = "schema_version";
+
+struct Migration {
+    version: u32,
+    description: &'static str,
+    up: fn(&Connection) -> Result<()>,
+}
+
+//append only: a released migration is never edited, changes go into a new one
+const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, description: "puzzles and note links", up: puzzles_and_links },
+    Migration { version: 2, description: "review stats", up: review_stats },
+    Migration { version: 3, description: "deck grading", up: deck_grading },
+    Migration { version: 4, description: "import reports", up: import_reports },
+    Migration { version: 5, description: "position keys and linked/moved counts", up: duplicate_detection },
+];
+
+/// The version of the chess tables this build writes.
+pub const SCHEMA_VERSION: u32 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+/// Versioned changes to the app_chess_* tables. The version is kept in app_chess_meta.
+pub struct SchemaMigrations;
+
+impl SchemaMigrations {
+    /// Runs the migrations the collection hasn't seen yet and returns the version it was at.
+    /// Collections written by a newer ankichess are refused rather than modified.
+    pub fn migrate(conn: &Connection) -> Result<u32, AnkiChessError> {
+        //the version lives in the meta table, so that one exists before any migration
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS app_chess_meta (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );"
+        )?;
+
+        let found = Self::version(conn)?;
+        if found > SCHEMA_VERSION {
+            return Err(AnkiChessError::DatabaseError(format!(
+                "The chess tables are at schema version {}, this version of ankichess only knows up to {}",
+                found, SCHEMA_VERSION
+            )));
+        }
+
+        for migration in MIGRATIONS.iter().filter(|m| m.version > found) {
+            Self::apply(conn, migration).map_err(|e| {
+                AnkiChessError::DatabaseError(format!(
+                    "Migration {} ({}) failed: {}",
+                    migration.version, migration.description, e
+                ))
+            })?;
+        }
+        Ok(found)
+    }
+
+    /// 0 for new collections and for those from before the tables were versioned.
+    pub fn version(conn: &Connection) -> Result<u32> {
+        Ok(PuzzleRepository::get_meta(conn, META_SCHEMA_VERSION)?
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0))
+    }
+
+    //a savepoint works both inside and outside of an open transaction
+    fn apply(conn: &Connection, migration: &Migration) -> Result<()> {
+        conn.execute_batch("SAVEPOINT chess_migration")?;
+        let result = (migration.up)(conn)
+            .and_then(|_| PuzzleRepository::set_meta(conn, META_SCHEMA_VERSION, &migration.version.to_string()));
+
+        match result {
+            Ok(()) => conn.execute_batch("RELEASE chess_migration"),
+            Err(e) => {
+                conn.execute_batch("ROLLBACK TO chess_migration; RELEASE chess_migration")?;
+                Err(e)
+            }
+        }
+    }
+}
+
+//unversioned collections can already have any of the tables up to version 5,
+//so those migrations only create what is missing
+
+fn puzzles_and_links(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS app_chess_puzzles (
+            puzzle_id TEXT PRIMARY KEY,
+            fen TEXT NOT NULL,
+            moves TEXT NOT NULL,
+            rating INTEGER,
+            rating_deviation INTEGER,
+            popularity INTEGER,
+            nb_plays INTEGER,
+            themes TEXT,
+            game_url TEXT,
+            opening_tags TEXT,
+            comment TEXT,
+            has_setup_move INTEGER DEFAULT 0
+        );
+        CREATE TABLE IF NOT EXISTS app_chess_note_links (
+            nid INTEGER PRIMARY KEY,
+            puzzle_id TEXT NOT NULL,
+            FOREIGN KEY(puzzle_id) REFERENCES app_chess_puzzles(puzzle_id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_link_puzzle_id ON app_chess_note_links(puzzle_id);
+        CREATE INDEX IF NOT EXISTS idx_puzzles_rating ON app_chess_puzzles(rating);
+        CREATE INDEX IF NOT EXISTS idx_puzzles_popularity ON app_chess_puzzles(popularity);
+        CREATE INDEX IF NOT EXISTS idx_puzzles_themes ON app_chess_puzzles(themes);
+        CREATE INDEX IF NOT EXISTS idx_puzzles_opening_tags ON app_chess_puzzles(opening_tags);"
+    )
+}
+
+fn review_stats(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS app_chess_reviews (
+            revlog_id INTEGER PRIMARY KEY,
+            cid INTEGER NOT NULL,
+            rating INTEGER NOT NULL,
+            ms_taken INTEGER NOT NULL DEFAULT 0,
+            wrong_attempts INTEGER NOT NULL DEFAULT 0,
+            hints_used INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE INDEX IF NOT EXISTS idx_reviews_cid ON app_chess_reviews(cid);"
+    )
+}
+
+fn deck_grading(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS app_chess_deck_grading (
+            deck_id INTEGER PRIMARY KEY,
+            auto_grade INTEGER NOT NULL DEFAULT 0,
+            target_seconds INTEGER NOT NULL DEFAULT 0
+        );"
+    )
+}
+
+fn import_reports(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS app_chess_import_reports (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            deck_id INTEGER NOT NULL,
+            source TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            processed INTEGER NOT NULL,
+            imported INTEGER NOT NULL,
+            skipped INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS app_chess_import_skips (
+            report_id INTEGER NOT NULL,
+            line INTEGER,
+            reason TEXT NOT NULL,
+            puzzle_id TEXT,
+            detail TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_import_skips_report ON app_chess_import_skips(report_id);"
+    )
+}
+
+//position keys of existing puzzles are filled in by the next import
+fn duplicate_detection(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS app_chess_fen_keys (
+            puzzle_id TEXT PRIMARY KEY,
+            fen_key TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_fen_keys_key ON app_chess_fen_keys(fen_key);"
+    )?;
+    add_column(conn, "app_chess_import_reports", "linked", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column(conn, "app_chess_import_reports", "moved", "INTEGER NOT NULL DEFAULT 0")
+}
+
+fn add_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2)",
+        params![table, column],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,3 @@
+pub mod lichessdb_repo;
+pub mod migrations;
+pub mod puzzle_repo;
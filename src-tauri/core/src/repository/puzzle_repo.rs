@@ -0,0 +1,630 @@
+use std::collections::HashMap;
+
+use rusqlite::{params, Connection, Result};
+
+use crate::error::AnkiChessError;
+use crate::models::card::{PuzzleAttempt, PuzzleReviewStats};
+use crate::models::deck::DeckGradingPayload;
+use crate::models::import_report::{ImportReport, SkipReason, SkippedRow};
+use crate::models::puzzle::{ChessPuzzle, LinkedPuzzle};
+use crate::repository::migrations::SchemaMigrations;
+
+pub struct PuzzleRepository;
+
+impl PuzzleRepository {
+    
+    /// Creates the chess tables or brings them up to the current schema, see `SchemaMigrations`.
+    pub fn init_tables(conn: &Connection) -> std::result::Result<(), AnkiChessError> {
+        SchemaMigrations::migrate(conn).map(|_| ())
+    }
+
+    pub fn get_meta(conn: &Connection, key: &str) -> Result<Option<String>> {
+        let result = conn.query_row(
+            "SELECT value FROM app_chess_meta WHERE key = ?1",
+            params![key],
+            |row| row.get::<_, String>(0),
+        );
+
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn set_meta(conn: &Connection, key: &str, value: &str) -> Result<()> {
+        conn.execute(
+            "INSERT OR REPLACE INTO app_chess_meta (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
+    
+    pub fn save(conn: &Connection, puzzle: &ChessPuzzle) -> Result<()> {
+        let mut stmt = conn.prepare(
+            "INSERT INTO app_chess_puzzles 
+            (puzzle_id, fen, moves, rating, rating_deviation, popularity, nb_plays, themes, game_url, opening_tags, comment, has_setup_move) 
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+            ON CONFLICT(puzzle_id) DO UPDATE SET
+                fen=excluded.fen,
+                moves=excluded.moves,
+                comment=excluded.comment" 
+        )?;
+
+        stmt.execute(params![
+            puzzle.puzzle_id,
+            puzzle.fen,
+            puzzle.moves,
+            puzzle.rating,
+            puzzle.rating_deviation,
+            puzzle.popularity,
+            puzzle.nb_plays,
+            puzzle.themes,
+            puzzle.game_url,
+            puzzle.opening_tags,
+            puzzle.comment,
+            puzzle.has_setup_move as i32 
+        ])?;
+        Ok(())
+    }
+
+    
+    pub fn create_link(conn: &Connection, nid: i64, puzzle_id: &str) -> Result<()> {
+        conn.execute(
+            "INSERT INTO app_chess_note_links (nid, puzzle_id) VALUES (?1, ?2)",
+            params![nid, puzzle_id],
+        )?;
+        Ok(())
+    }
+
+    
+    
+    pub fn update_fields_by_nid(
+        conn: &Connection, 
+        nid: i64, 
+        fen: &str, 
+        moves: &str, 
+        comment: &str
+    ) -> Result<bool> {
+        
+        
+        
+        let updated_count = conn.execute(
+            "UPDATE app_chess_puzzles
+             SET fen = ?1, moves = ?2, comment = ?3
+             WHERE puzzle_id = (
+                 SELECT puzzle_id 
+                 FROM app_chess_note_links 
+                 WHERE nid = ?4
+             )",
+            params![fen, moves, comment, nid],
+        )?;
+        //the position changed, its key is computed again on the next import
+        conn.execute(
+            "DELETE FROM app_chess_fen_keys WHERE puzzle_id = (SELECT puzzle_id FROM app_chess_note_links WHERE nid = ?1)",
+            params![nid],
+        )?;
+
+        Ok(updated_count > 0)
+    }
+
+    pub fn get_by_nid(conn: &Connection, nid: i64) -> Result<Option<ChessPuzzle>> {
+        let sql = "
+            SELECT p.* FROM app_chess_puzzles p
+            JOIN app_chess_note_links l ON p.puzzle_id = l.puzzle_id
+            WHERE l.nid = ?1
+        ";
+
+        let result = conn.query_row(sql, params![nid], |row| {
+            Ok(ChessPuzzle {
+                puzzle_id: row.get("puzzle_id")?,
+                fen: row.get("fen")?,
+                moves: row.get("moves")?,
+                rating: row.get("rating")?,
+                rating_deviation: row.get("rating_deviation")?,
+                popularity: row.get("popularity")?,
+                nb_plays: row.get("nb_plays")?,
+                themes: row.get("themes")?,
+                game_url: row.get("game_url")?,
+                opening_tags: row.get("opening_tags")?,
+                comment: row.get("comment")?,
+                has_setup_move: row.get::<_, i32>("has_setup_move")? != 0, 
+            })
+        });
+
+        match result {
+            Ok(puzzle) => Ok(Some(puzzle)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn delete_links(conn: &Connection, nids: &[i64]) -> Result<()> {
+        if nids.is_empty() {
+            return Ok(());
+        }
+
+        
+        let placeholders = std::iter::repeat("?")
+            .take(nids.len())
+            .collect::<Vec<_>>()
+            .join(",");
+
+        
+        let sql = format!(
+            "DELETE FROM app_chess_note_links WHERE nid IN ({})",
+            placeholders
+        );
+
+        
+        let params: Vec<&dyn rusqlite::ToSql> = nids
+            .iter()
+            .map(|n| n as &dyn rusqlite::ToSql)
+            .collect();
+
+        
+        conn.execute(&sql, &*params)?;
+
+        Ok(())
+    }
+
+    
+    
+    pub fn get_batch_by_nids(conn: &Connection, nids: &[i64]) -> Result<HashMap<i64, ChessPuzzle>> {
+        if nids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        
+        
+        
+        let placeholders = std::iter::repeat("?").take(nids.len()).collect::<Vec<_>>().join(",");
+        let sql = format!(
+            "SELECT l.nid, p.* FROM app_chess_puzzles p
+             JOIN app_chess_note_links l ON p.puzzle_id = l.puzzle_id
+             WHERE l.nid IN ({})", 
+            placeholders
+        );
+
+        
+        let params: Vec<&dyn rusqlite::ToSql> = nids.iter().map(|n| n as &dyn rusqlite::ToSql).collect();
+
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(&*params, |row| {
+            let nid: i64 = row.get("nid")?;
+            let puzzle = ChessPuzzle {
+                puzzle_id: row.get("puzzle_id")?,
+                fen: row.get("fen")?,
+                moves: row.get("moves")?,
+                rating: row.get("rating")?,
+                rating_deviation: row.get("rating_deviation")?,
+                popularity: row.get("popularity")?,
+                nb_plays: row.get("nb_plays")?,
+                themes: row.get("themes")?,
+                game_url: row.get("game_url")?,
+                opening_tags: row.get("opening_tags")?,
+                comment: row.get("comment")?,
+                has_setup_move: row.get::<_, i32>("has_setup_move")? != 0,
+            };
+            Ok((nid, puzzle))
+        })?;
+
+        let mut result_map = HashMap::new();
+        for row in rows {
+            let (nid, puzzle) = row?;
+            result_map.insert(nid, puzzle);
+        }
+
+        Ok(result_map)
+    }
+
+    
+    
+    pub fn save_batch_puzzles(conn: &Connection, puzzles: &[ChessPuzzle]) -> Result<()> {
+        let mut stmt = conn.prepare(
+            "INSERT OR IGNORE INTO app_chess_puzzles 
+            (puzzle_id, fen, moves, rating, rating_deviation, popularity, nb_plays, themes, game_url, opening_tags, comment, has_setup_move) 
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )?;
+
+        for p in puzzles {
+            stmt.execute(params![
+                p.puzzle_id,
+                p.fen,
+                p.moves,
+                p.rating,
+                p.rating_deviation,
+                p.popularity,
+                p.nb_plays,
+                p.themes,
+                p.game_url,
+                p.opening_tags,
+                p.comment,
+                p.has_setup_move as i32
+            ])?;
+        }
+        Ok(())
+    }
+
+    
+    pub fn save_batch_links(conn: &Connection, links: &[(i64, String)]) -> Result<()> {
+        let mut stmt = conn.prepare(
+            "INSERT OR REPLACE INTO app_chess_note_links (nid, puzzle_id) VALUES (?, ?)"
+        )?;
+
+        for (nid, puzzle_id) in links {
+            stmt.execute(params![nid, puzzle_id])?;
+        }
+        Ok(())
+    }
+
+    //linked notes grouped by their current notetype id
+    pub fn get_linked_nids_not_of_notetype(conn: &Connection, ntid: i64) -> Result<HashMap<i64, Vec<i64>>> {
+        let mut stmt = conn.prepare(
+            "SELECT n.mid, l.nid
+             FROM app_chess_note_links l
+             JOIN notes n ON n.id = l.nid
+             WHERE n.mid != ?1"
+        )?;
+
+        let rows = stmt.query_map(params![ntid], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))?;
+
+        let mut map: HashMap<i64, Vec<i64>> = HashMap::new();
+        for row in rows {
+            let (mid, nid) = row?;
+            map.entry(mid).or_default().push(nid);
+        }
+        Ok(map)
+    }
+
+    pub fn get_card_ids_by_nids(conn: &Connection, nids: &[i64]) -> Result<HashMap<i64, i64>> {
+        if nids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let placeholders = std::iter::repeat("?").take(nids.len()).collect::<Vec<_>>().join(",");
+        let sql = format!("SELECT nid, id FROM cards WHERE nid IN ({}) ORDER BY ord", placeholders);
+        let params: Vec<&dyn rusqlite::ToSql> = nids.iter().map(|n| n as &dyn rusqlite::ToSql).collect();
+
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(&*params, |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))?;
+
+        //one card per note, keep the first template if a note type ever adds more
+        let mut map = HashMap::new();
+        for row in rows {
+            let (nid, cid) = row?;
+            map.entry(nid).or_insert(cid);
+        }
+        Ok(map)
+    }
+
+    
+    pub fn _exists_in_deck(conn: &Connection, puzzle_id: &str, deck_id: i64) -> Result<bool> {
+        let mut stmt = conn.prepare(
+            "SELECT 1 
+             FROM app_chess_note_links l
+             JOIN cards c ON l.nid = c.nid
+             WHERE l.puzzle_id = ?1 AND c.did = ?2
+             LIMIT 1"
+        )?;
+        
+        Ok(stmt.exists(params![puzzle_id, deck_id])?)
+    }
+
+    //clear cache
+    pub fn delete_unused_puzzles(conn: &Connection) -> Result<usize> {
+        let sql = "
+            DELETE FROM app_chess_puzzles 
+            WHERE puzzle_id NOT IN (
+                SELECT DISTINCT puzzle_id FROM app_chess_note_links
+            )
+        ";
+        let count = conn.execute(sql, [])?;
+        conn.execute(
+            "DELETE FROM app_chess_fen_keys WHERE puzzle_id NOT IN (SELECT puzzle_id FROM app_chess_puzzles)",
+            [],
+        )?;
+        Ok(count)
+    }
+
+    /// Deletes the given puzzles unless a note still links to them.
+    pub fn delete_unlinked_puzzles(conn: &Connection, puzzle_ids: &[String]) -> Result<usize> {
+        if puzzle_ids.is_empty() {
+            return Ok(0);
+        }
+
+        let placeholders = vec!["?"; puzzle_ids.len()].join(",");
+        let params: Vec<&dyn rusqlite::ToSql> = puzzle_ids.iter().map(|id| id as &dyn rusqlite::ToSql).collect();
+        let sql = format!(
+            "DELETE FROM app_chess_puzzles WHERE puzzle_id IN ({})
+             AND puzzle_id NOT IN (SELECT puzzle_id FROM app_chess_note_links)",
+            placeholders
+        );
+        let count = conn.execute(&sql, &*params)?;
+        conn.execute(
+            "DELETE FROM app_chess_fen_keys WHERE puzzle_id NOT IN (SELECT puzzle_id FROM app_chess_puzzles)",
+            [],
+        )?;
+        Ok(count)
+    }
+
+    pub fn get_all_puzzle_ids(conn: &Connection) -> Result<Vec<String>> {
+        let mut stmt = conn.prepare("SELECT puzzle_id FROM app_chess_puzzles")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        rows.collect()
+    }
+
+    //refreshes dump metadata only, fen, moves and the user's comment stay as imported
+    pub fn update_metadata(conn: &Connection, puzzle: &ChessPuzzle) -> Result<bool> {
+        let changed = conn.execute(
+            "UPDATE app_chess_puzzles
+             SET rating = ?2, rating_deviation = ?3, popularity = ?4, nb_plays = ?5, themes = ?6, opening_tags = ?7
+             WHERE puzzle_id = ?1
+               AND (rating IS NOT ?2 OR rating_deviation IS NOT ?3 OR popularity IS NOT ?4
+                    OR nb_plays IS NOT ?5 OR themes IS NOT ?6 OR opening_tags IS NOT ?7)",
+            params![
+                puzzle.puzzle_id, puzzle.rating, puzzle.rating_deviation, puzzle.popularity,
+                puzzle.nb_plays, puzzle.themes, puzzle.opening_tags
+            ],
+        )?;
+        Ok(changed > 0)
+    }
+
+    pub fn get_nids_by_puzzle_ids(conn: &Connection, puzzle_ids: &[String]) -> Result<Vec<i64>> {
+        if puzzle_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let placeholders = std::iter::repeat("?").take(puzzle_ids.len()).collect::<Vec<_>>().join(",");
+        let sql = format!("SELECT nid FROM app_chess_note_links WHERE puzzle_id IN ({})", placeholders);
+        let params: Vec<&dyn rusqlite::ToSql> = puzzle_ids.iter().map(|id| id as &dyn rusqlite::ToSql).collect();
+
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(&*params, |row| row.get::<_, i64>(0))?;
+        rows.collect()
+    }
+
+    pub fn get_last_revlog_id(conn: &Connection, cid: i64) -> Result<Option<i64>> {
+        let result = conn.query_row(
+            "SELECT id FROM revlog WHERE cid = ?1 ORDER BY id DESC LIMIT 1",
+            params![cid],
+            |row| row.get::<_, i64>(0),
+        );
+
+        match result {
+            Ok(id) => Ok(Some(id)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save_review(conn: &Connection, revlog_id: i64, cid: i64, rating: u8, attempt: &PuzzleAttempt) -> Result<()> {
+        conn.execute(
+            "INSERT OR REPLACE INTO app_chess_reviews
+            (revlog_id, cid, rating, ms_taken, wrong_attempts, hints_used)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![revlog_id, cid, rating, attempt.milliseconds_taken, attempt.wrong_attempts, attempt.hints_used],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_review(conn: &Connection, revlog_id: i64) -> Result<()> {
+        conn.execute("DELETE FROM app_chess_reviews WHERE revlog_id = ?1", params![revlog_id])?;
+        Ok(())
+    }
+
+    //only reviews still present in the revlog count, so anki side deletions are respected
+    pub fn get_review_stats(conn: &Connection, cid: i64) -> Result<PuzzleReviewStats> {
+        let mut stats = conn.query_row(
+            "SELECT COUNT(*),
+                    COALESCE(SUM(r.wrong_attempts = 0 AND r.hints_used = 0), 0),
+                    COALESCE(SUM(r.wrong_attempts), 0),
+                    COALESCE(SUM(r.hints_used), 0),
+                    COALESCE(SUM(r.ms_taken), 0)
+             FROM app_chess_reviews r
+             JOIN revlog rl ON rl.id = r.revlog_id
+             WHERE r.cid = ?1",
+            params![cid],
+            |row| Ok(PuzzleReviewStats {
+                reviews: row.get(0)?,
+                clean_solves: row.get(1)?,
+                wrong_attempts: row.get(2)?,
+                hints_used: row.get(3)?,
+                total_ms: row.get(4)?,
+                ..Default::default()
+            }),
+        )?;
+
+        if stats.reviews > 0 {
+            stats.average_ms = stats.total_ms / stats.reviews as i64;
+            stats.last_ms = conn.query_row(
+                "SELECT r.ms_taken FROM app_chess_reviews r
+                 JOIN revlog rl ON rl.id = r.revlog_id
+                 WHERE r.cid = ?1 ORDER BY r.revlog_id DESC LIMIT 1",
+                params![cid],
+                |row| row.get(0),
+            )?;
+        }
+        Ok(stats)
+    }
+
+    pub fn get_deck_grading(conn: &Connection, deck_id: i64) -> Result<DeckGradingPayload> {
+        let result = conn.query_row(
+            "SELECT auto_grade, target_seconds FROM app_chess_deck_grading WHERE deck_id = ?1",
+            params![deck_id],
+            |row| Ok(DeckGradingPayload {
+                auto_grade: row.get(0)?,
+                target_seconds: row.get(1)?,
+            }),
+        );
+
+        match result {
+            Ok(grading) => Ok(grading),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(DeckGradingPayload::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn set_deck_grading(conn: &Connection, deck_id: i64, grading: &DeckGradingPayload) -> Result<()> {
+        conn.execute(
+            "INSERT OR REPLACE INTO app_chess_deck_grading (deck_id, auto_grade, target_seconds)
+            VALUES (?1, ?2, ?3)",
+            params![deck_id, grading.auto_grade, grading.target_seconds],
+        )?;
+        Ok(())
+    }
+
+    /// Saves a report with its skipped rows and returns its id. Only the latest
+    /// `keep` reports are kept.
+    pub fn save_import_report(conn: &Connection, report: &ImportReport, keep: usize) -> Result<i64> {
+        conn.execute(
+            "INSERT INTO app_chess_import_reports (deck_id, source, created_at, processed, imported, linked, moved, skipped)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                report.deck_id, report.source, report.created_at as i64, report.processed as i64,
+                report.imported as i64, report.linked as i64, report.moved as i64, report.skipped as i64
+            ],
+        )?;
+        let id = conn.last_insert_rowid();
+
+        let mut stmt = conn.prepare_cached(
+            "INSERT INTO app_chess_import_skips (report_id, line, reason, puzzle_id, detail) VALUES (?1, ?2, ?3, ?4, ?5)"
+        )?;
+        for row in &report.skipped_rows {
+            stmt.execute(params![id, row.line.map(|line| line as i64), row.reason.as_str(), row.puzzle_id, row.detail])?;
+        }
+
+        conn.execute(
+            "DELETE FROM app_chess_import_reports WHERE id NOT IN
+            (SELECT id FROM app_chess_import_reports ORDER BY id DESC LIMIT ?1)",
+            params![keep as i64],
+        )?;
+        conn.execute(
+            "DELETE FROM app_chess_import_skips WHERE report_id NOT IN (SELECT id FROM app_chess_import_reports)",
+            [],
+        )?;
+        Ok(id)
+    }
+
+    pub fn get_import_report(conn: &Connection, id: i64) -> Result<Option<ImportReport>> {
+        let mut report = match Self::query_import_reports(conn, "WHERE id = ?1", params![id])?.pop() {
+            Some(report) => report,
+            None => return Ok(None),
+        };
+
+        let mut stmt = conn.prepare(
+            "SELECT line, reason, puzzle_id, detail FROM app_chess_import_skips WHERE report_id = ?1 ORDER BY rowid"
+        )?;
+        report.skipped_rows = stmt
+            .query_map(params![id], |row| Ok(SkippedRow {
+                line: row.get::<_, Option<i64>>(0)?.map(|line| line as u64),
+                reason: SkipReason::parse(&row.get::<_, String>(1)?).unwrap_or(SkipReason::InvalidValue),
+                puzzle_id: row.get(2)?,
+                detail: row.get(3)?,
+            }))?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Some(report))
+    }
+
+    //summaries only, the skipped rows are loaded with get_import_report
+    pub fn list_import_reports(conn: &Connection, deck_id: Option<i64>) -> Result<Vec<ImportReport>> {
+        match deck_id {
+            Some(deck_id) => Self::query_import_reports(conn, "WHERE deck_id = ?1 ORDER BY id DESC", params![deck_id]),
+            None => Self::query_import_reports(conn, "ORDER BY id DESC", params![]),
+        }
+    }
+
+    fn query_import_reports(conn: &Connection, clause: &str, params: &[&dyn rusqlite::ToSql]) -> Result<Vec<ImportReport>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, deck_id, source, created_at, processed, imported, linked, moved, skipped FROM app_chess_import_reports {}",
+            clause
+        ))?;
+        let reports = stmt
+            .query_map(params, |row| Ok(ImportReport {
+                id: row.get(0)?,
+                deck_id: row.get(1)?,
+                source: row.get(2)?,
+                created_at: row.get::<_, i64>(3)? as u64,
+                processed: row.get::<_, i64>(4)? as usize,
+                imported: row.get::<_, i64>(5)? as usize,
+                linked: row.get::<_, i64>(6)? as usize,
+                moved: row.get::<_, i64>(7)? as usize,
+                skipped: row.get::<_, i64>(8)? as usize,
+                skipped_rows: Vec::new(),
+            }))?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(reports)
+    }
+
+    //puzzles saved since the keys were last computed
+    pub fn get_puzzles_without_fen_key(conn: &Connection) -> Result<Vec<ChessPuzzle>> {
+        let mut stmt = conn.prepare(
+            "SELECT p.puzzle_id, p.fen, p.moves, p.has_setup_move FROM app_chess_puzzles p
+             LEFT JOIN app_chess_fen_keys k ON k.puzzle_id = p.puzzle_id
+             WHERE k.puzzle_id IS NULL"
+        )?;
+        let rows = stmt.query_map([], |row| Ok(ChessPuzzle {
+            puzzle_id: row.get(0)?,
+            fen: row.get(1)?,
+            moves: row.get(2)?,
+            has_setup_move: row.get::<_, i32>(3)? != 0,
+            ..Default::default()
+        }))?;
+        rows.collect()
+    }
+
+    pub fn save_fen_keys(conn: &Connection, keys: &[(String, String)]) -> Result<()> {
+        let mut stmt = conn.prepare(
+            "INSERT OR REPLACE INTO app_chess_fen_keys (puzzle_id, fen_key) VALUES (?, ?)"
+        )?;
+        for (puzzle_id, fen_key) in keys {
+            stmt.execute(params![puzzle_id, fen_key])?;
+        }
+        Ok(())
+    }
+
+    /// Every linked note of the collection with the deck of its card and the position key of its puzzle.
+    pub fn get_linked_puzzles(conn: &Connection) -> Result<Vec<LinkedPuzzle>> {
+        let mut stmt = conn.prepare(
+            "SELECT l.nid, MIN(c.did), l.puzzle_id, k.fen_key FROM app_chess_note_links l
+             JOIN cards c ON c.nid = l.nid
+             LEFT JOIN app_chess_fen_keys k ON k.puzzle_id = l.puzzle_id
+             GROUP BY l.nid"
+        )?;
+        let rows = stmt.query_map([], |row| Ok(LinkedPuzzle {
+            nid: row.get(0)?,
+            deck_id: row.get(1)?,
+            puzzle_id: row.get(2)?,
+            fen_key: row.get(3)?,
+        }))?;
+        rows.collect()
+    }
+
+    pub fn get_by_puzzle_ids(conn: &Connection, puzzle_ids: &[String]) -> Result<Vec<ChessPuzzle>> {
+        let mut stmt = conn.prepare_cached("SELECT * FROM app_chess_puzzles WHERE puzzle_id = ?1")?;
+        let mut puzzles = Vec::with_capacity(puzzle_ids.len());
+        for puzzle_id in puzzle_ids {
+            let puzzle = stmt.query_row(params![puzzle_id], |row| Ok(ChessPuzzle {
+                puzzle_id: row.get("puzzle_id")?,
+                fen: row.get("fen")?,
+                moves: row.get("moves")?,
+                rating: row.get("rating")?,
+                rating_deviation: row.get("rating_deviation")?,
+                popularity: row.get("popularity")?,
+                nb_plays: row.get("nb_plays")?,
+                themes: row.get("themes")?,
+                game_url: row.get("game_url")?,
+                opening_tags: row.get("opening_tags")?,
+                comment: row.get("comment")?,
+                has_setup_move: row.get::<_, i32>("has_setup_move")? != 0,
+            }));
+            match puzzle {
+                Ok(puzzle) => puzzles.push(puzzle),
+                Err(rusqlite::Error::QueryReturnedNoRows) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(puzzles)
+    }
+}
@@ -0,0 +1,383 @@
+use anki::services::CardsService;
+use csv::WriterBuilder;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use anki::deckconfig::UpdateDeckConfigsRequest;
+use anki::{collection::Collection, prelude::*};
+use anki_proto::deck_config::UpdateDeckConfigsMode;
+use anki_proto::decks::DeckTreeNode;
+use anki_proto::import_export::ExportAnkiPackageOptions;
+
+use crate::chess;
+use crate::chess::pgn;
+use crate::error::AnkiChessError;
+use crate::models::deck::{DeckGradingPayload, DeckInfo, DeckLimitsPayload};
+use crate::models::puzzle::ChessPuzzle;
+use crate::repository::puzzle_repo::PuzzleRepository;
+use crate::services::notetype_service::NotetypeService;
+use crate::shared::csv_format::{PUZZLE_COLUMNS, SCHEDULING_COLUMNS};
+use crate::shared::utils::{get_deck_name, to_proto_card_id};
+use anki::prelude::BoolKey::Fsrs;
+
+pub struct DeckService;
+
+impl DeckService {
+    pub fn create_deck(col: &mut Collection, deck_name: &str) -> Result<i64, AnkiChessError> {
+        let deck = col.get_or_create_normal_deck(deck_name)?;
+        Ok(deck.id.0)
+    }
+
+    pub fn get_all_decks(col: &mut Collection) -> Result<Vec<DeckInfo>, AnkiChessError> {
+        let deck_names = col
+            .get_all_deck_names(false)
+            .map_err(AnkiChessError::from)?;
+
+        let timing = TimestampSecs::now();
+        let root_node = col.deck_tree(Some(timing))?;
+        let mut tree_map = HashMap::new();
+        Self::flatten_deck_tree_into_map(root_node, &mut tree_map);
+
+        let mut result = Vec::new();
+        for (deck_id, name) in deck_names {
+            let node_opt = tree_map.get(&deck_id);
+            let (new_count, learn_count, due_count) = node_opt
+                .map(|n| (n.new_count, n.learn_count, n.review_count))
+                .unwrap_or((0, 0, 0));
+
+            result.push(DeckInfo {
+                id: deck_id,
+                name,
+                new_count,
+                learn_count,
+                due_count,
+            });
+        }
+        Ok(result)
+    }
+
+    pub fn delete_deck(col: &mut Collection, deck_id: i64) -> Result<(), AnkiChessError> {
+        let did = DeckId(deck_id);
+
+        let search_query = format!("did:{}", deck_id);
+        let card_ids = col.search_cards(&search_query, anki::search::SortMode::NoOrder)?;
+
+        let mut nids_to_clean = HashSet::new();
+        for cid in card_ids {
+            let proto_cid = to_proto_card_id(cid.0);
+            if let Ok(card) = col.get_card(proto_cid) {
+                nids_to_clean.insert(card.note_id);
+            }
+        }
+
+        let nids_vec: Vec<i64> = nids_to_clean.into_iter().collect();
+
+        if !nids_vec.is_empty() {
+            col.storage.db().execute("BEGIN TRANSACTION", [])?;
+            PuzzleRepository::delete_links(col.storage.db(), &nids_vec)?;
+            col.storage.db().execute("COMMIT", [])?;
+        }
+
+        col.remove_decks_and_child_decks(&[did])?;
+
+        Ok(())
+    }
+
+    pub fn get_deck_limits(
+        col: &mut Collection,
+        deck_id: i64,
+    ) -> Result<DeckLimitsPayload, AnkiChessError> {
+        let deck = col
+            .get_deck(DeckId(deck_id))?
+            .ok_or_else(|| AnkiChessError::NotFound(format!("Deck {} not found", deck_id)))?;
+
+        let config_id = deck.config_id().ok_or_else(|| {
+            AnkiChessError::NotFound("Deck has no config (maybe filtered deck?)".to_string())
+        })?;
+
+        let config = col
+            .get_deck_config(config_id, true)?
+            .ok_or_else(|| AnkiChessError::NotFound(format!("Config {} not found", config_id)))?;
+
+        Ok(DeckLimitsPayload {
+            new_cards_per_day: config.inner.new_per_day,
+            reviews_per_day: config.inner.reviews_per_day,
+        })
+    }
+
+    pub fn set_deck_limits(
+        col: &mut Collection,
+        deck_id: i64,
+        limits: DeckLimitsPayload,
+    ) -> Result<(), AnkiChessError> {
+        let target_did = DeckId(deck_id);
+
+        let deck_opts = col.get_deck_configs_for_update(target_did)?;
+
+        let current_deck_info = deck_opts
+            .current_deck
+            .ok_or_else(|| AnkiChessError::NotFound("Deck info not found".to_string()))?;
+
+        let current_conf_id = current_deck_info.config_id;
+
+        let config_entry = deck_opts
+            .all_config
+            .iter()
+            .find(|c| c.config.as_ref().map(|cc| cc.id).unwrap_or(0) == current_conf_id)
+            .ok_or_else(|| AnkiChessError::NotFound("Config not found".to_string()))?;
+
+        let use_count = config_entry.use_count;
+        let is_default = current_conf_id == 1;
+
+        // preparing config (cloning if shared across decks, edit if unique)
+        let mut config: DeckConfig = if use_count > 1 || is_default {
+            let mut new_conf: DeckConfig = config_entry.config.clone().unwrap().into();
+            new_conf.id = DeckConfigId(0); 
+            new_conf.name = format!("{} (Custom)", current_deck_info.name);
+            new_conf
+        } else {
+            config_entry.config.clone().unwrap().into()
+        };
+
+        config.inner.new_per_day = limits.new_cards_per_day;
+        config.inner.reviews_per_day = limits.reviews_per_day;
+
+        let update_req = UpdateDeckConfigsRequest {
+            target_deck_id: target_did,
+            configs: vec![config],
+            removed_config_ids: vec![],
+            mode: UpdateDeckConfigsMode::Normal,
+
+            card_state_customizer: deck_opts.card_state_customizer,
+            limits: Default::default(),
+            new_cards_ignore_review_limit: deck_opts.new_cards_ignore_review_limit,
+            apply_all_parent_limits: deck_opts.apply_all_parent_limits,
+            fsrs: col.get_config_bool(Fsrs),
+            fsrs_reschedule: false,
+            fsrs_health_check: deck_opts.fsrs_health_check,
+        };
+
+        col.update_deck_configs(update_req)?;
+        Ok(())
+    }
+
+    pub fn get_deck_grading(col: &mut Collection, deck_id: i64) -> Result<DeckGradingPayload, AnkiChessError> {
+        Ok(PuzzleRepository::get_deck_grading(col.storage.db(), deck_id)?)
+    }
+
+    pub fn set_deck_grading(
+        col: &mut Collection,
+        deck_id: i64,
+        grading: DeckGradingPayload,
+    ) -> Result<(), AnkiChessError> {
+        col.get_deck(DeckId(deck_id))?
+            .ok_or_else(|| AnkiChessError::NotFound(format!("Deck {} not found", deck_id)))?;
+        PuzzleRepository::set_deck_grading(col.storage.db(), deck_id, &grading)?;
+        Ok(())
+    }
+
+    /// Writes the deck in the headered CSV format read back by `ImportService::import_from_csv`.
+    /// With `include_scheduling` every row also carries the review state of its card.
+    pub async fn export_deck_csv(
+        col_arc: Arc<Mutex<Collection>>,
+        deck_id: i64,
+        file_path: String,
+        include_scheduling: bool,
+    ) -> Result<usize, AnkiChessError> {
+        tokio::task::spawn_blocking(move || -> Result<usize, AnkiChessError> {
+            let mut col = col_arc
+                .lock()
+                .map_err(|_| AnkiChessError::DatabaseError("Lock poisoned".into()))?;
+
+            let search_query = format!("did:{}", deck_id);
+            let card_ids = col.search_cards(&search_query, anki::search::SortMode::NoOrder)?;
+
+            let mut cards = HashMap::new();
+            for cid in card_ids {
+                let proto_cid = to_proto_card_id(cid.0);
+                if let Ok(card) = col.get_card(proto_cid) {
+                    cards.entry(card.note_id).or_insert(card);
+                }
+            }
+            let mut nids_vec: Vec<i64> = cards.keys().copied().collect();
+            nids_vec.sort();
+
+            let puzzles_map = PuzzleRepository::get_batch_by_nids(col.storage.db(), &nids_vec)?;
+            let today = col.timing_today()?.days_elapsed as i32;
+
+            let mut wtr = WriterBuilder::new()
+                .has_headers(false)
+                .from_path(file_path)?;
+
+            let mut header: Vec<&str> = PUZZLE_COLUMNS.iter().map(|c| c.header()).collect();
+            if include_scheduling {
+                header.extend(SCHEDULING_COLUMNS.iter().map(|c| c.header()));
+            }
+            wtr.write_record(&header)?;
+
+            let mut count = 0;
+
+            for nid in nids_vec {
+                let Some(puzzle) = puzzles_map.get(&nid) else { continue };
+
+                let mut record = vec![
+                    puzzle.puzzle_id.clone(),
+                    puzzle.fen.clone(),
+                    puzzle.moves.clone(),
+                    puzzle.rating.to_string(),
+                    puzzle.rating_deviation.to_string(),
+                    puzzle.popularity.to_string(),
+                    puzzle.nb_plays.to_string(),
+                    puzzle.themes.clone(),
+                    puzzle.game_url.clone(),
+                    puzzle.opening_tags.clone(),
+                    puzzle.comment.clone(),
+                    puzzle.has_setup_move.to_string(),
+                ];
+
+                if include_scheduling {
+                    let card = &cards[&nid];
+                    //only review cards have a day based due, learning cards count as due today
+                    let due_in_days = if card.ctype == 2 { card.due - today } else { 0 };
+                    record.extend([
+                        card.ctype.to_string(),
+                        due_in_days.to_string(),
+                        card.interval.to_string(),
+                        card.ease_factor.to_string(),
+                        card.reps.to_string(),
+                        card.lapses.to_string(),
+                    ]);
+                }
+
+                wtr.write_record(&record)?;
+                count += 1;
+            }
+
+            wtr.flush()?;
+            Ok(count)
+        })
+        .await?
+    }
+
+    /// Writes every card of the deck as a PGN game starting from the puzzle FEN,
+    /// with the solution as SAN mainline. Puzzles whose solution cannot be replayed
+    /// are left out, the returned count only includes written games.
+    pub async fn export_deck_pgn(
+        col_arc: Arc<Mutex<Collection>>,
+        deck_id: i64,
+        file_path: String,
+    ) -> Result<usize, AnkiChessError> {
+        tokio::task::spawn_blocking(move || -> Result<usize, AnkiChessError> {
+            let mut col = col_arc.lock()?;
+            let deck_name = get_deck_name(&mut col, DeckId(deck_id))?;
+
+            let search_query = format!("did:{}", deck_id);
+            let card_ids = col.search_cards(&search_query, anki::search::SortMode::NoOrder)?;
+
+            let mut nids = HashSet::new();
+            for cid in card_ids {
+                let proto_cid = to_proto_card_id(cid.0);
+                if let Ok(card) = col.get_card(proto_cid) {
+                    nids.insert(card.note_id);
+                }
+            }
+            let nids_vec: Vec<i64> = nids.into_iter().collect();
+
+            let puzzles_map = PuzzleRepository::get_batch_by_nids(col.storage.db(), &nids_vec)?;
+            let mut puzzles: Vec<(&i64, &ChessPuzzle)> = puzzles_map.iter().collect();
+            puzzles.sort_by_key(|(nid, _)| **nid);
+
+            let mut out = String::new();
+            let mut count = 0;
+
+            for (_, puzzle) in puzzles {
+                let (start, moves) = match chess::parse_line(&puzzle.fen, &puzzle.moves) {
+                    Ok(line) => line,
+                    Err(_) => continue,
+                };
+
+                let mut headers = vec![
+                    ("Event".to_string(), deck_name.clone()),
+                    ("Site".to_string(), if puzzle.game_url.is_empty() { "?".to_string() } else { puzzle.game_url.clone() }),
+                    ("Date".to_string(), "????.??.??".to_string()),
+                    ("Round".to_string(), "-".to_string()),
+                    ("White".to_string(), "?".to_string()),
+                    ("Black".to_string(), "?".to_string()),
+                    ("Result".to_string(), "*".to_string()),
+                    ("SetUp".to_string(), "1".to_string()),
+                    ("FEN".to_string(), start.to_fen()),
+                    ("PuzzleId".to_string(), puzzle.puzzle_id.clone()),
+                ];
+                if puzzle.rating > 0 {
+                    headers.push(("PuzzleRating".to_string(), puzzle.rating.to_string()));
+                }
+                if !puzzle.themes.is_empty() {
+                    headers.push(("PuzzleThemes".to_string(), puzzle.themes.clone()));
+                }
+                if !puzzle.opening_tags.is_empty() {
+                    headers.push(("Opening".to_string(), puzzle.opening_tags.replace('_', " ")));
+                }
+
+                out.push_str(&pgn::write_game(&headers, &start, &moves, &puzzle.comment));
+                count += 1;
+            }
+
+            std::fs::write(file_path, out)?;
+            Ok(count)
+        })
+        .await?
+    }
+
+    /// Writes the deck as an `.apkg` that Anki desktop and AnkiDroid can open.
+    /// `legacy` produces the older package layout for clients that predate the v3 format.
+    pub async fn export_deck_apkg(
+        col_arc: Arc<Mutex<Collection>>,
+        deck_id: i64,
+        file_path: String,
+        include_scheduling: bool,
+        legacy: bool,
+    ) -> Result<usize, AnkiChessError> {
+        tokio::task::spawn_blocking(move || -> Result<usize, AnkiChessError> {
+            let mut col = col_arc.lock()?;
+
+            let search_query = format!("did:{}", deck_id);
+            let card_ids = col.search_cards(&search_query, anki::search::SortMode::NoOrder)?;
+
+            let mut nids = HashSet::new();
+            for cid in card_ids {
+                let proto_cid = to_proto_card_id(cid.0);
+                if let Ok(card) = col.get_card(proto_cid) {
+                    nids.insert(card.note_id);
+                }
+            }
+            let nids_vec: Vec<i64> = nids.into_iter().collect();
+
+            //the package carries the notetype, so make sure every note renders with the board template
+            NotetypeService::migrate_linked_notes(&mut col)?;
+            NotetypeService::refresh_notes(&mut col, &nids_vec, true)?;
+
+            let options = ExportAnkiPackageOptions {
+                with_scheduling: include_scheduling,
+                with_deck_configs: include_scheduling,
+                with_media: false,
+                legacy,
+            };
+            let count = col.export_apkg(&file_path, options, search_query.as_str(), None)?;
+            Ok(count)
+        })
+        .await?
+    }
+
+    fn flatten_deck_tree_into_map(node: DeckTreeNode, map: &mut HashMap<DeckId, DeckTreeNode>) {
+        let children = node.children;
+
+        let node_data = DeckTreeNode {
+            children: vec![],
+            ..node
+        };
+        map.insert(DeckId(node_data.deck_id), node_data);
+        for child in children {
+            Self::flatten_deck_tree_into_map(child, map);
+        }
+    }
+}
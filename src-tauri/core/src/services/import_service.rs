@@ -0,0 +1,1033 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use rusqlite::{Connection};
+use tempfile::TempDir;
+use uuid::Uuid;
+use csv;
+
+use anki::{collection::{Collection, CollectionBuilder}, prelude::*, services::{CardsService, NotesService}, text::html_to_text_line};
+use anki_proto::cards::UpdateCardsRequest;
+use anki_proto::import_export::{ExportAnkiPackageOptions, ImportAnkiPackageOptions};
+
+use crate::chess::{self, pgn, position::Position};
+use crate::error::AnkiChessError;
+use crate::models::card::CardSchedule;
+use crate::models::import_report::{ImportReport, SkipReason};
+use crate::models::job::JobCounts;
+use crate::models::puzzle::{ApkgFieldMapping, ApkgImportPayload, ChessPuzzle, CsvImportPayload, DuplicatePolicy, ImportOptions, ImportPreview, PgnImportPayload, SamplingMode};
+use crate::repository::lichessdb_repo::LichessdbRepository;
+use crate::repository::puzzle_repo::{ PuzzleRepository};
+use crate::services::job_service::JobHandle;
+use crate::services::undo_service::{SideTableChange, UndoJournal};
+use crate::services::notetype_service::{
+    NotetypeService, FIELD_GAME_URL, FIELD_OPENING_TAGS, FIELD_POSITION, FIELD_RATING, FIELD_THEMES,
+};
+use crate::shared::csv_format::{CsvColumn, CsvLayout};
+use crate::shared::duplicates::{DuplicateAction, DuplicateIndex};
+use crate::shared::sampling::StratifiedSampler;
+use crate::shared::utils::{format_anki_sfld, get_deck_name, to_proto_card_id, to_proto_note_id};
+
+
+
+
+#[derive(Debug, Clone)]
+struct LichessDbRow {
+    puzzle_id: String,
+    fen: String,
+    moves: String,
+    rating: Option<u32>,
+    rating_deviation: Option<i32>, 
+    popularity: Option<i32>,
+    nb_plays: Option<i32>,
+    themes: String,
+    game_url: Option<String>,
+    opening_tags: Option<String>,
+}
+
+const LICHESS_DB_COLUMNS: &str = "PuzzleId, FEN, Moves, Rating, RatingDeviation, Popularity, NbPlays, Themes, GameUrl, OpeningTags";
+
+impl LichessDbRow {
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(LichessDbRow {
+            puzzle_id: row.get(0)?,
+            fen: row.get(1)?,
+            moves: row.get(2)?,
+            rating: row.get(3).ok(),
+            rating_deviation: row.get(4).ok(),
+            popularity: row.get(5).ok(),
+            nb_plays: row.get(6).ok(),
+            themes: row.get(7)?,
+            game_url: row.get(8).ok(),
+            opening_tags: row.get(9).ok(),
+        })
+    }
+
+    fn into_puzzle(self) -> ChessPuzzle {
+        ChessPuzzle {
+            puzzle_id: self.puzzle_id,
+            fen: self.fen,
+            moves: self.moves,
+            rating: self.rating.unwrap_or(0) as i32,
+            rating_deviation: self.rating_deviation.unwrap_or(0),
+            popularity: self.popularity.unwrap_or(0),
+            nb_plays: self.nb_plays.unwrap_or(0),
+            themes: self.themes,
+            game_url: self.game_url.unwrap_or_default(),
+            opening_tags: self.opening_tags.unwrap_or_default(),
+            comment: String::new(),
+            has_setup_move: true, 
+        }
+    }
+}
+
+pub struct ImportService;
+
+impl ImportService {
+
+
+    
+    
+    
+    pub fn import_from_lichess_db(
+        col_arc: &Arc<Mutex<Collection>>,
+        journal: &Mutex<UndoJournal>,
+        payload: ImportOptions,
+        db_path: PathBuf,
+        job: &JobHandle,
+    ) -> Result<ImportReport, AnkiChessError> {
+        let _start_time = Instant::now();
+
+        
+        let deck_id = DeckId(payload.deck_id);
+
+        job.message("Checking duplicates...");
+
+        //the collection is only locked to read the deck and then once per batch, so the app stays usable
+        let (nt, mut duplicates) = Self::prepare_import(col_arc, deck_id)?;
+        let excluded_ids = duplicates.excluded_ids(payload.duplicates);
+        let ids_in_deck = duplicates.ids_in_deck();
+
+        
+        let conn = Connection::open(db_path)?;
+        LichessdbRepository::init_tables(&conn)?;
+        LichessdbRepository::ensure_tag_index(&conn)?;
+
+        let (filter, mut params_vec) = LichessdbRepository::puzzle_filter(&payload);
+        let mut query = format!("SELECT {} FROM puzzles WHERE {}", LICHESS_DB_COLUMNS, filter);
+
+        //sampling needs every match, the limit is applied while sampling
+        let sample_limit = payload.limit.filter(|_| payload.sampling != SamplingMode::First);
+        let seed = payload.seed.unwrap_or_else(Self::new_seed);
+        if sample_limit.is_none() {
+            if let Some(limit) = payload.limit { query.push_str(" LIMIT ?"); params_vec.push(Box::new(limit)); }
+        }
+
+        let params_sql = rusqlite::params_from_iter(params_vec.iter().map(|p| p.as_ref()));
+        let mut stmt = conn.prepare(&query)?;
+        
+        let puzzle_iter = stmt.query_map(params_sql, LichessDbRow::from_row)?;
+
+        let mut report = ImportReport::new("lichess_db", payload.deck_id);
+
+        let puzzle_iter: Box<dyn Iterator<Item = rusqlite::Result<LichessDbRow>> + '_> = match sample_limit {
+            None => Box::new(puzzle_iter),
+            Some(limit) => {
+                job.message("Sampling puzzles...");
+
+                let (sample, skipped) = Self::sample_matches(puzzle_iter, &payload, &excluded_ids, limit, seed, job.cancel_flag())?;
+                for puzzle_id in skipped {
+                    report.processed += 1;
+                    let detail = if ids_in_deck.contains(&puzzle_id) { "Already in the deck" } else { "Already in another deck" };
+                    report.skip(None, SkipReason::DuplicateId, Some(&puzzle_id), detail);
+                }
+                Box::new(sample.into_iter().map(Ok))
+            }
+        };
+
+        
+        const BATCH_SIZE: usize = 500;
+        let mut pending = PendingWrites::default();
+
+        for puzzle_res in puzzle_iter {
+            let row = puzzle_res?;
+            report.processed += 1;
+
+            let puzzle = row.into_puzzle();
+            let action = duplicates.resolve(&puzzle, payload.duplicates);
+            pending.push(action, puzzle, None, &mut report);
+
+            
+            if pending.len() >= BATCH_SIZE {
+                job.check_cancelled()?;
+                Self::write_pending(&mut col_arc.lock()?, &mut journal.lock()?, &nt, deck_id, &mut pending, &mut report)?;
+                
+                job.progress(format!("Importing... ({} analyzed)", report.processed), Self::counts(&report, None));
+            }
+        }
+
+        
+        if !pending.is_empty() {
+            job.check_cancelled()?;
+            Self::write_pending(&mut col_arc.lock()?, &mut journal.lock()?, &nt, deck_id, &mut pending, &mut report)?;
+        }
+
+        let message = match sample_limit {
+            Some(_) => format!("Done! {} (seed {})", report.summary(), seed),
+            None => format!("Done! {}", report.summary()),
+        };
+        job.progress(message, Self::counts(&report, None));
+
+        Self::save_report(col_arc, report)
+    }
+
+    /// Counts what an import with `payload` would match and add, without writing anything.
+    /// The sample is picked like the import picks its puzzles, so with the returned seed
+    /// the import starts with the same puzzles. The collection is only locked to read the deck.
+    pub fn preview_lichess_import(
+        col_arc: Arc<Mutex<Collection>>,
+        payload: ImportOptions,
+        db_path: PathBuf,
+        sample_size: usize,
+    ) -> Result<ImportPreview, AnkiChessError> {
+        let duplicates = {
+            let col = col_arc.lock()?;
+            PuzzleRepository::init_tables(col.storage.db())?;
+            Self::load_duplicates(&col, payload.deck_id)?
+        };
+        let ids_in_deck = duplicates.ids_in_deck();
+        let excluded_ids = duplicates.excluded_ids(payload.duplicates);
+
+        if !db_path.exists() {
+            return Err(AnkiChessError::NotFound("Puzzle database has not been indexed yet".into()));
+        }
+        let conn = Connection::open(db_path)?;
+        LichessdbRepository::init_tables(&conn)?;
+        LichessdbRepository::ensure_tag_index(&conn)?;
+
+        let matching = LichessdbRepository::count_matching(&conn, &payload)?;
+        let in_deck: Vec<String> = ids_in_deck.iter().cloned().collect();
+        let already_in_deck = LichessdbRepository::count_matching_ids(&conn, &payload, &in_deck)?;
+        let in_collection: Vec<String> = duplicates.excluded_ids(DuplicatePolicy::Skip).into_iter().collect();
+        let in_other_decks = LichessdbRepository::count_matching_ids(&conn, &payload, &in_collection)?.saturating_sub(already_in_deck);
+        //only puzzle ids are counted here, copies of a position under another id are found while importing
+        let skipped = match payload.duplicates {
+            DuplicatePolicy::Skip => already_in_deck + in_other_decks,
+            DuplicatePolicy::Link | DuplicatePolicy::Move => already_in_deck,
+        };
+        let new_matches = matching.saturating_sub(skipped);
+        let to_import = payload.limit.map_or(new_matches, |limit| new_matches.min(limit as u64));
+
+        let width = payload.rating_bucket_width.unwrap_or(100).max(1);
+        let rating_histogram = LichessdbRepository::rating_histogram(&conn, &payload, width)?;
+        let theme_breakdown = LichessdbRepository::theme_breakdown(&conn, &payload, 30)?;
+
+        let sampled = payload.sampling != SamplingMode::First;
+        let seed = payload.seed.unwrap_or_else(Self::new_seed);
+        let sample_size = payload.limit.map_or(sample_size, |limit| limit.min(sample_size));
+
+        let (filter, params_vec) = LichessdbRepository::puzzle_filter(&payload);
+        let query = format!("SELECT {} FROM puzzles WHERE {}", LICHESS_DB_COLUMNS, filter);
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(params_vec.iter().map(|p| p.as_ref())), LichessDbRow::from_row)?;
+
+        let sample = if sampled {
+            Self::sample_matches(rows, &payload, &excluded_ids, sample_size, seed, &AtomicBool::new(false))?.0
+        } else {
+            let mut first = Vec::new();
+            for row in rows {
+                let row = row?;
+                if first.len() >= sample_size {
+                    break;
+                }
+                if !excluded_ids.contains(&row.puzzle_id) {
+                    first.push(row);
+                }
+            }
+            first
+        };
+
+        Ok(ImportPreview {
+            matching,
+            already_in_deck,
+            in_other_decks,
+            to_import,
+            seed: sampled.then_some(seed),
+            rating_histogram,
+            theme_breakdown,
+            sample: sample.into_iter().map(LichessDbRow::into_puzzle).collect(),
+        })
+    }
+
+    fn new_seed() -> u64 {
+        std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.subsec_nanos() as u64).unwrap_or(0)
+    }
+
+    /// Picks `limit` of the matching rows the way `payload.sampling` asks for.
+    fn sample_matches(
+        rows: impl Iterator<Item = rusqlite::Result<LichessDbRow>>,
+        payload: &ImportOptions,
+        excluded_ids: &HashSet<String>,
+        limit: usize,
+        seed: u64,
+        cancel: &AtomicBool,
+    ) -> Result<(Vec<LichessDbRow>, Vec<String>), AnkiChessError> {
+        let themes: Vec<String> = payload.themes.iter().flatten()
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect();
+        let width = payload.rating_bucket_width.unwrap_or(100).max(1);
+
+        match payload.sampling {
+            SamplingMode::RatingStratified => Self::sample_rows(rows, excluded_ids, limit, seed, cancel, |row| {
+                vec![row.rating.unwrap_or(0) / width]
+            }),
+            SamplingMode::ThemeBalanced if !themes.is_empty() => Self::sample_rows(rows, excluded_ids, limit, seed, cancel, |row| {
+                (0..themes.len())
+                    .filter(|&i| row.themes.split_whitespace().any(|t| t.eq_ignore_ascii_case(&themes[i])))
+                    .collect()
+            }),
+            _ => Self::sample_rows(rows, excluded_ids, limit, seed, cancel, |_| vec![()]),
+        }
+    }
+
+    /// Streams the matching rows through a sampler, `strata_of` names the buckets a row
+    /// counts towards. Puzzles that would be skipped as duplicates are left out so they don't
+    /// use up the limit. Returns the sample and the ids that were left out.
+    fn sample_rows<K: Ord>(
+        rows: impl Iterator<Item = rusqlite::Result<LichessDbRow>>,
+        excluded_ids: &HashSet<String>,
+        limit: usize,
+        seed: u64,
+        cancel: &AtomicBool,
+        strata_of: impl Fn(&LichessDbRow) -> Vec<K>,
+    ) -> Result<(Vec<LichessDbRow>, Vec<String>), AnkiChessError> {
+        let mut sampler = StratifiedSampler::new(limit, seed);
+        let mut skipped = Vec::new();
+
+        for (index, row) in rows.enumerate() {
+            if index % 10_000 == 0 && cancel.load(Ordering::Relaxed) {
+                return Err(AnkiChessError::Cancelled("Import cancelled while sampling".into()));
+            }
+            let row = row?;
+            if excluded_ids.contains(&row.puzzle_id) {
+                skipped.push(row.puzzle_id);
+                continue;
+            }
+            let mut strata = strata_of(&row);
+            if let Some(last) = strata.pop() {
+                for stratum in strata {
+                    sampler.offer(stratum, &row.puzzle_id, row.clone());
+                }
+                let id = row.puzzle_id.clone();
+                sampler.offer(last, &id, row);
+            }
+        }
+
+        Ok((sampler.into_sample(), skipped))
+    }
+
+    
+    
+    
+    pub fn import_from_csv(
+        col_arc: &Arc<Mutex<Collection>>,
+        journal: &Mutex<UndoJournal>,
+        payload: CsvImportPayload,
+        job: &JobHandle,
+    ) -> Result<ImportReport, AnkiChessError> {
+        let deck_id = DeckId(payload.deck_id);
+        let (nt, mut duplicates) = Self::prepare_import(col_arc, deck_id)?;
+
+        let mut rdr = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(payload.csv_content.as_bytes());
+        let mut records = rdr.records().peekable();
+
+        let layout = match records.peek() {
+            Some(Ok(first)) => CsvLayout::detect(first),
+            _ => CsvLayout::detect(&csv::StringRecord::new()),
+        };
+        if layout.has_header {
+            records.next();
+        }
+        
+        const BATCH_SIZE: usize = 500;
+        let mut report = ImportReport::new("csv", payload.deck_id);
+
+        //every row is checked before anything is written, rows that can't be imported end up in the report
+        let mut rows: Vec<(u64, ChessPuzzle, DuplicateAction)> = Vec::new();
+        let mut schedules: HashMap<String, CardSchedule> = HashMap::new();
+        for result in records {
+            report.processed += 1;
+            let record = match result {
+                Ok(record) => record,
+                Err(e) if e.is_io_error() => return Err(e.into()),
+                Err(e) => {
+                    let line = e.position().map(|p| p.line());
+                    report.skip(line, SkipReason::InvalidValue, None, e.to_string());
+                    continue;
+                }
+            };
+
+            let line = record.position().map(|p| p.line()).unwrap_or(report.processed as u64);
+            let (puzzle, schedule) = match Self::puzzle_from_csv_row(&layout, &record) {
+                Ok(parsed) => parsed,
+                Err((reason, detail)) => {
+                    let id = layout.get(&record, CsvColumn::PuzzleId).filter(|id| !id.is_empty());
+                    report.skip(Some(line), reason, id, detail);
+                    continue;
+                }
+            };
+
+            let action = duplicates.resolve(&puzzle, payload.duplicates);
+            if let Some(schedule) = schedule {
+                schedules.insert(puzzle.puzzle_id.clone(), schedule);
+            }
+            rows.push((line, puzzle, action));
+        }
+
+        let total = rows.len();
+        let mut pending = PendingWrites::default();
+        let mut rows = rows.into_iter().peekable();
+        while let Some((line, puzzle, action)) = rows.next() {
+            pending.push(action, puzzle, Some(line), &mut report);
+            if pending.len() < BATCH_SIZE && rows.peek().is_some() {
+                continue;
+            }
+
+            job.check_cancelled()?;
+            {
+                let mut col = col_arc.lock()?;
+                Self::write_pending(&mut col, &mut journal.lock()?, &nt, deck_id, &mut pending, &mut report)?;
+                if !schedules.is_empty() {
+                    Self::apply_schedules(&mut col, &pending.links, &schedules)?;
+                }
+            }
+
+            job.progress(format!("Processing CSV... ({})", report.imported), Self::counts(&report, Some(total)));
+        }
+
+        job.progress(format!("CSV Done! {}", report.summary()), Self::counts(&report, None));
+
+        Self::save_report(col_arc, report)
+    }
+
+    /// Reads one csv row into a puzzle and, when the file has scheduling columns, its schedule.
+    /// A row that can't be imported gives the reason and a description of what is wrong.
+    fn puzzle_from_csv_row(
+        layout: &CsvLayout,
+        record: &csv::StringRecord,
+    ) -> Result<(ChessPuzzle, Option<CardSchedule>), (SkipReason, String)> {
+        if record.len() < 2 {
+            return Err((SkipReason::MissingColumn, "Expected at least a FEN and a solution".to_string()));
+        }
+
+        let fen = layout.get(record, CsvColumn::Fen).unwrap_or("").to_string();
+        let moves = layout.get(record, CsvColumn::Moves).unwrap_or("").to_string();
+        if fen.is_empty() {
+            return Err((SkipReason::MissingColumn, "FEN is empty".to_string()));
+        }
+        if moves.is_empty() {
+            return Err((SkipReason::MissingColumn, "Solution is empty".to_string()));
+        }
+
+        chess::validate_puzzle(&fen, &moves).map_err(|e| (SkipReason::from(&e), e.to_string()))?;
+
+        let puzzle_id = match layout.get(record, CsvColumn::PuzzleId) {
+            Some(id) if !id.is_empty() => id.to_string(),
+            _ => format!("csv_{}", Uuid::new_v4().simple())
+        };
+
+        let text = |column: CsvColumn| layout.get(record, column).unwrap_or("").to_string();
+        let has_setup_move = match layout.get(record, CsvColumn::HasSetupMove) {
+            Some(value) => matches!(value.to_ascii_lowercase().as_str(), "1" | "true" | "yes"),
+            None => layout.is_lichess_format(),
+        };
+
+        let puzzle = ChessPuzzle {
+            puzzle_id,
+            fen,
+            moves,
+            rating: parse_column(layout, record, CsvColumn::Rating)?,
+            rating_deviation: parse_column(layout, record, CsvColumn::RatingDeviation)?,
+            popularity: parse_column(layout, record, CsvColumn::Popularity)?,
+            nb_plays: parse_column(layout, record, CsvColumn::NbPlays)?,
+            themes: if layout.has(CsvColumn::Themes) { text(CsvColumn::Themes) } else { "imported_csv".to_string() },
+            game_url: text(CsvColumn::GameUrl),
+            opening_tags: text(CsvColumn::OpeningTags),
+            comment: text(CsvColumn::Comment),
+            has_setup_move,
+        };
+
+        let schedule = if layout.has(CsvColumn::CardType) {
+            Some(CardSchedule {
+                card_type: parse_column(layout, record, CsvColumn::CardType)?,
+                due_in_days: parse_column(layout, record, CsvColumn::DueInDays)?,
+                interval: parse_column(layout, record, CsvColumn::Interval)?,
+                ease_factor: parse_column(layout, record, CsvColumn::EaseFactor)?,
+                reps: parse_column(layout, record, CsvColumn::Reps)?,
+                lapses: parse_column(layout, record, CsvColumn::Lapses)?,
+            })
+        } else {
+            None
+        };
+
+        Ok((puzzle, schedule))
+    }
+
+    pub fn import_from_pgn(
+        col_arc: &Arc<Mutex<Collection>>,
+        journal: &Mutex<UndoJournal>,
+        payload: PgnImportPayload,
+        job: &JobHandle,
+    ) -> Result<ImportReport, AnkiChessError> {
+        let deck_id = DeckId(payload.deck_id);
+        let (nt, mut duplicates) = Self::prepare_import(col_arc, deck_id)?;
+
+        let games = pgn::parse_pgn(&payload.pgn_content)?;
+
+        const BATCH_SIZE: usize = 500;
+
+        let mut report = ImportReport::new("pgn", payload.deck_id);
+        report.processed = games.len();
+
+        //like the csv import, the whole file is checked before anything is written
+        let mut puzzles: Vec<(Option<u64>, ChessPuzzle, DuplicateAction)> = Vec::new();
+        for (index, game) in games.iter().enumerate() {
+            let game_number = Some(index as u64 + 1);
+            let positions = match game.puzzle_positions() {
+                Ok(positions) => positions,
+                Err(e) => {
+                    report.skip(game_number, SkipReason::from(&e), None, e.to_string());
+                    continue;
+                }
+            };
+
+            if positions.is_empty() {
+                report.skip(game_number, SkipReason::NoPuzzle, None, "No setup position, annotated variation or puzzle marker");
+                continue;
+            }
+
+            let game_url = game.header("Site")
+                .filter(|site| site.starts_with("http"))
+                .unwrap_or_default()
+                .to_string();
+
+            for position in positions {
+                let puzzle = ChessPuzzle {
+                    puzzle_id: format!("pgn_{}", Uuid::new_v4().simple()),
+                    fen: position.fen,
+                    moves: position.moves.iter().map(|mv| mv.uci()).collect::<Vec<_>>().join(" "),
+                    themes: "imported_pgn".to_string(),
+                    game_url: game_url.clone(),
+                    comment: position.comment,
+                    ..Default::default()
+                };
+                //pgn puzzles get fresh ids, so only the position can match an existing puzzle
+                let action = duplicates.resolve(&puzzle, payload.duplicates);
+                puzzles.push((game_number, puzzle, action));
+            }
+        }
+
+        let total = puzzles.len();
+        let mut pending = PendingWrites::default();
+        let mut puzzles = puzzles.into_iter().peekable();
+        while let Some((game_number, puzzle, action)) = puzzles.next() {
+            pending.push(action, puzzle, game_number, &mut report);
+            if pending.len() < BATCH_SIZE && puzzles.peek().is_some() {
+                continue;
+            }
+
+            job.check_cancelled()?;
+            Self::write_pending(&mut col_arc.lock()?, &mut journal.lock()?, &nt, deck_id, &mut pending, &mut report)?;
+
+            job.progress(format!("Processing PGN... ({})", report.imported), Self::counts(&report, Some(total)));
+        }
+
+        job.progress(format!("PGN Done! {}", report.summary()), Self::counts(&report, None));
+
+        Self::save_report(col_arc, report)
+    }
+
+    /// Imports an Anki package and links every note that holds a chess puzzle. Cards keep
+    /// the scheduling and review history of the package; notes without a valid FEN and
+    /// solution, or of a notetype with several card templates, stay plain Anki notes.
+    pub fn import_from_apkg(
+        col_arc: &Arc<Mutex<Collection>>,
+        journal: &Mutex<UndoJournal>,
+        payload: ApkgImportPayload,
+        job: &JobHandle,
+    ) -> Result<ImportReport, AnkiChessError> {
+        //anki imports the package in one operation, so the lock is held throughout
+        let mut guard = col_arc.lock()?;
+        let col: &mut Collection = &mut guard;
+        PuzzleRepository::init_tables(col.storage.db())?;
+
+        let path = PathBuf::from(&payload.file_path);
+        let is_colpkg = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("colpkg"));
+
+        //importing a .colpkg would replace the whole collection, so its cards are repackaged first
+        let work_dir = if is_colpkg { Some(Self::repackage_colpkg(&path)?) } else { None };
+        let apkg_path = match &work_dir {
+            Some(dir) => dir.path().join("collection.apkg"),
+            None => path.clone(),
+        };
+
+        job.check_cancelled()?;
+        job.message("Importing package...");
+
+        let options = ImportAnkiPackageOptions {
+            with_scheduling: true,
+            with_deck_configs: true,
+            ..Default::default()
+        };
+        let result = col.import_apkg(&apkg_path, options);
+        drop(work_dir);
+        let log = result?.output;
+
+        let nids: Vec<i64> = log.new.iter()
+            .chain(log.updated.iter())
+            .filter_map(|note| note.id.as_ref().map(|id| id.nid))
+            .collect();
+        let already_linked = PuzzleRepository::get_batch_by_nids(col.storage.db(), &nids)?;
+        let mapping = payload.field_mapping.unwrap_or_default();
+
+        //a package brings its own decks, so the report isn't tied to one
+        let mut report = ImportReport::new("apkg", 0);
+        report.processed = nids.len();
+        let mut notetypes: HashMap<i64, Option<Arc<Notetype>>> = HashMap::new();
+        let mut puzzles: Vec<ChessPuzzle> = Vec::new();
+        let mut links: Vec<(i64, String)> = Vec::new();
+
+        for (index, nid) in nids.into_iter().enumerate() {
+            let note_number = Some(index as u64 + 1);
+            if let Some(linked) = already_linked.get(&nid) {
+                report.skip(note_number, SkipReason::DuplicateId, Some(&linked.puzzle_id), format!("Note {} is already linked", nid));
+                continue;
+            }
+
+            let note = NotesService::get_note(col, to_proto_note_id(nid))?;
+            if !notetypes.contains_key(&note.notetype_id) {
+                notetypes.insert(note.notetype_id, col.get_notetype(NotetypeId(note.notetype_id))?);
+            }
+            let notetype = notetypes[&note.notetype_id].as_ref();
+
+            //linked notes are converted to the chess notetype, which would drop all but the first card
+            if let Some(nt) = notetype.filter(|nt| nt.templates.len() > 1) {
+                report.skip(
+                    note_number,
+                    SkipReason::MultipleTemplates,
+                    None,
+                    format!("Note {} uses '{}', which has {} card templates", nid, nt.name, nt.templates.len()),
+                );
+                continue;
+            }
+
+            let field_names: Vec<String> = notetype
+                .map(|nt| nt.fields.iter().map(|f| f.name.clone()).collect())
+                .unwrap_or_default();
+            match Self::puzzle_from_note(&field_names, &note.fields, &note.tags, &mapping) {
+                Some(puzzle) => {
+                    links.push((nid, puzzle.puzzle_id.clone()));
+                    puzzles.push(puzzle);
+                }
+                None => report.skip(note_number, SkipReason::NoPuzzle, None, format!("Note {} has no valid FEN and solution", nid)),
+            }
+        }
+
+        col.storage.db().execute("BEGIN TRANSACTION", [])?;
+        PuzzleRepository::save_batch_puzzles(col.storage.db(), &puzzles)?;
+        PuzzleRepository::save_batch_links(col.storage.db(), &links)?;
+        col.storage.db().execute("COMMIT", [])?;
+
+        //the links belong to the package import step, the notetype changes below are steps of their own
+        report.imported = puzzles.len();
+        journal.lock()?.record(col, vec![
+            SideTableChange::PuzzlesAdded { puzzles },
+            SideTableChange::LinksAdded { links },
+        ]);
+
+        NotetypeService::migrate_linked_notes(col)?;
+
+        job.progress(format!("Package Done! Linked {} puzzles.", report.imported), Self::counts(&report, None));
+
+        drop(guard);
+        Self::save_report(col_arc, report)
+    }
+
+    /// Reads a puzzle out of the fields of an imported note, or `None` when the
+    /// note has no parsable FEN and solution.
+    fn puzzle_from_note(
+        names: &[String],
+        fields: &[String],
+        tags: &[String],
+        mapping: &ApkgFieldMapping,
+    ) -> Option<ChessPuzzle> {
+        let index_of = |name: &str| names.iter().position(|n| n.eq_ignore_ascii_case(name));
+        let find = |configured: &Option<String>, candidates: &[&str]| match configured {
+            Some(name) => index_of(name),
+            None => candidates.iter().find_map(|c| index_of(c)),
+        };
+        let text = |index: Option<usize>| {
+            index
+                .and_then(|i| fields.get(i))
+                .map(|f| html_to_text_line(f, false).trim().to_string())
+                .unwrap_or_default()
+        };
+
+        let mut fen_index = find(&mapping.fen_field, &["FEN", "Position", "Board"]);
+        if fen_index.is_none() && mapping.fen_field.is_none() {
+            fen_index = (0..fields.len()).find(|i| Position::from_fen(&text(Some(*i))).is_ok());
+        }
+        let solution_index = find(&mapping.solution_field, &["Moves", "Solution", "Answer", "Line"])?;
+        let comment_index = find(&mapping.comment_field, &["Comment", "Notes", "Extra"]);
+
+        let fen = Position::from_fen(&text(fen_index)).ok()?.to_fen();
+        let moves = chess::parse_solution(&fen, &text(Some(solution_index))).ok()?;
+
+        //notes exported by ankichess show the board after the opponent's setup move in Position
+        let mut has_setup_move = false;
+        if let Some(i) = index_of(FIELD_POSITION).filter(|i| Some(*i) != fen_index) {
+            let position = text(Some(i));
+            if let Ok((mut pos, line)) = chess::parse_line(&fen, &moves) {
+                has_setup_move = line.len() > 1 && pos.play(&line[0]) && pos.to_fen() == position;
+            }
+        }
+
+        let themes = text(index_of(FIELD_THEMES));
+        let themes = if !themes.is_empty() {
+            themes
+        } else if !tags.is_empty() {
+            tags.join(" ")
+        } else {
+            "imported_apkg".to_string()
+        };
+
+        Some(ChessPuzzle {
+            puzzle_id: format!("apkg_{}", Uuid::new_v4().simple()),
+            fen,
+            moves,
+            rating: text(index_of(FIELD_RATING)).parse().unwrap_or(0),
+            themes,
+            game_url: text(index_of(FIELD_GAME_URL)),
+            opening_tags: text(index_of(FIELD_OPENING_TAGS)),
+            comment: text(comment_index),
+            has_setup_move,
+            ..Default::default()
+        })
+    }
+
+    /// Unpacks the collection inside a `.colpkg` into a temporary directory and exports
+    /// all of its cards, with scheduling, as `collection.apkg` next to it. The directory
+    /// is removed when the returned guard is dropped.
+    fn repackage_colpkg(path: &Path) -> Result<TempDir, AnkiChessError> {
+        let invalid = |e: zip::result::ZipError| AnkiChessError::InvalidInput(format!("Invalid .colpkg: {}", e));
+        let mut archive = zip::ZipArchive::new(File::open(path)?).map_err(invalid)?;
+
+        let work_dir = tempfile::Builder::new().prefix("ankichess_colpkg_").tempdir()?;
+        let col_path = work_dir.path().join("collection.anki2");
+
+        //newer packages hold a zstd compressed collection.anki21b next to a placeholder collection.anki2
+        let name = ["collection.anki21b", "collection.anki21", "collection.anki2"]
+            .into_iter()
+            .find(|name| archive.file_names().any(|n| n == *name))
+            .ok_or_else(|| AnkiChessError::InvalidInput("No collection found in .colpkg".into()))?;
+        {
+            let mut entry = archive.by_name(name).map_err(invalid)?;
+            let mut out = File::create(&col_path)?;
+            if name.ends_with("anki21b") {
+                zstd::stream::copy_decode(&mut entry, out)?;
+            } else {
+                std::io::copy(&mut entry, &mut out)?;
+            }
+        }
+
+        let col_path = col_path.to_str().ok_or_else(|| {
+            AnkiChessError::InvalidInput(format!("Temporary path {} is not valid UTF-8", col_path.display()))
+        })?;
+        let mut source = CollectionBuilder::default()
+            .set_collection_path(col_path)
+            .build()?;
+        let options = ExportAnkiPackageOptions {
+            with_scheduling: true,
+            with_deck_configs: true,
+            with_media: false,
+            legacy: false,
+        };
+        source.export_apkg(work_dir.path().join("collection.apkg"), options, "deck:*", None)?;
+
+        Ok(work_dir)
+    }
+
+    /// Restores the exported review state on freshly imported cards. Cards that were
+    /// new or still in learning are left as new cards.
+    fn apply_schedules(
+        col: &mut Collection,
+        links: &[(i64, String)],
+        schedules: &HashMap<String, CardSchedule>,
+    ) -> Result<(), AnkiChessError> {
+        let nids: Vec<i64> = links.iter()
+            .filter(|(_, puzzle_id)| schedules.get(puzzle_id).map(|s| s.card_type >= 2).unwrap_or(false))
+            .map(|(nid, _)| *nid)
+            .collect();
+        if nids.is_empty() {
+            return Ok(());
+        }
+
+        let card_ids = PuzzleRepository::get_card_ids_by_nids(col.storage.db(), &nids)?;
+        let today = col.timing_today()?.days_elapsed as i32;
+
+        let mut cards = Vec::with_capacity(card_ids.len());
+        for (nid, puzzle_id) in links {
+            let (Some(cid), Some(schedule)) = (card_ids.get(nid), schedules.get(puzzle_id)) else { continue };
+            if schedule.card_type < 2 {
+                continue;
+            }
+
+            let mut card = col.get_card(to_proto_card_id(*cid))?;
+            card.ctype = 2;
+            card.queue = 2;
+            card.due = today + schedule.due_in_days.max(0);
+            card.interval = schedule.interval.max(1);
+            card.ease_factor = if schedule.ease_factor > 0 { schedule.ease_factor } else { 2500 };
+            card.reps = schedule.reps;
+            card.lapses = schedule.lapses;
+            cards.push(card);
+        }
+
+        col.update_cards(UpdateCardsRequest { cards, skip_undo_entry: true })?;
+        Ok(())
+    }
+
+    
+    
+    
+    fn counts(report: &ImportReport, total: Option<usize>) -> JobCounts {
+        JobCounts {
+            processed: report.processed,
+            imported: report.imported + report.linked + report.moved,
+            skipped: report.skipped,
+            total,
+        }
+    }
+
+    //the latest reports are kept so a source file can still be fixed after a few more imports
+    fn save_report(col_arc: &Arc<Mutex<Collection>>, mut report: ImportReport) -> Result<ImportReport, AnkiChessError> {
+        const KEPT_REPORTS: usize = 50;
+        report.created_at = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_millis() as u64;
+        let col = col_arc.lock()?;
+        report.id = PuzzleRepository::save_import_report(col.storage.db(), &report, KEPT_REPORTS)?;
+        Ok(report)
+    }
+
+    //fails early when the deck is gone, before anything is parsed
+    fn prepare_import(
+        col_arc: &Arc<Mutex<Collection>>,
+        deck_id: DeckId,
+    ) -> Result<(Arc<Notetype>, DuplicateIndex), AnkiChessError> {
+        let mut col = col_arc.lock()?;
+        PuzzleRepository::init_tables(col.storage.db())?;
+        get_deck_name(&mut col, deck_id)?;
+        let nt = NotetypeService::ensure_chess_notetype(&mut col)?;
+        let duplicates = Self::load_duplicates(&col, deck_id.0)?;
+        Ok((nt, duplicates))
+    }
+
+    /// Indexes the chess notes of the collection for the duplicate check. Position keys
+    /// of puzzles saved before they existed are computed here once.
+    fn load_duplicates(col: &Collection, deck_id: i64) -> Result<DuplicateIndex, AnkiChessError> {
+        let db = col.storage.db();
+        let keys: Vec<(String, String)> = PuzzleRepository::get_puzzles_without_fen_key(db)?
+            .into_iter()
+            .filter_map(|p| chess::fen_key(&p.fen, &p.moves, p.has_setup_move).ok().map(|key| (p.puzzle_id, key)))
+            .collect();
+        if !keys.is_empty() {
+            db.execute("BEGIN TRANSACTION", [])?;
+            PuzzleRepository::save_fen_keys(db, &keys)?;
+            db.execute("COMMIT", [])?;
+        }
+
+        Ok(DuplicateIndex::new(deck_id, PuzzleRepository::get_linked_puzzles(db)?))
+    }
+
+    /// Writes a batch: new puzzles get a note, shared puzzles get another note linked to the
+    /// existing puzzle and moved notes change deck. `pending.links` keeps the notes added.
+    /// Every added note is its own undo step, so each is journaled with its side-table rows.
+    fn write_pending(
+        col: &mut Collection,
+        journal: &mut UndoJournal,
+        nt: &Notetype,
+        deck_id: DeckId,
+        pending: &mut PendingWrites,
+        report: &mut ImportReport,
+    ) -> Result<(), AnkiChessError> {
+        pending.links.clear();
+        let shared = PuzzleRepository::get_by_puzzle_ids(col.storage.db(), &pending.shared)?;
+
+        col.storage.db().execute("BEGIN TRANSACTION", [])?;
+
+        PuzzleRepository::save_batch_puzzles(col.storage.db(), &pending.puzzles)?;
+
+        //the sort field only holds the puzzle id, so notes of the same puzzle match across decks
+        for (index, p) in pending.puzzles.iter().chain(shared.iter()).enumerate() {
+            let mut note = NotetypeService::new_chess_note(nt, p, &format_anki_sfld(&p.puzzle_id))?;
+            col.add_note(&mut note, deck_id)?;
+            pending.links.push((note.id.0, p.puzzle_id.clone()));
+
+            let mut changes = Vec::new();
+            if index < pending.puzzles.len() {
+                changes.push(SideTableChange::PuzzlesAdded { puzzles: vec![p.clone()] });
+            }
+            changes.push(SideTableChange::LinksAdded { links: vec![(note.id.0, p.puzzle_id.clone())] });
+            journal.record(col, changes);
+        }
+
+        PuzzleRepository::save_batch_links(col.storage.db(), &pending.links)?;
+
+        col.storage.db().execute("COMMIT", [])?;
+
+        if !pending.moved.is_empty() {
+            let card_ids: Vec<CardId> = PuzzleRepository::get_card_ids_by_nids(col.storage.db(), &pending.moved)?
+                .into_values()
+                .map(CardId)
+                .collect();
+            col.set_deck(&card_ids, deck_id)?;
+        }
+
+        report.imported += pending.puzzles.len();
+        report.linked += shared.len();
+        report.moved += pending.moved.len();
+        pending.puzzles.clear();
+        pending.shared.clear();
+        pending.moved.clear();
+        Ok(())
+    }
+}
+
+/// What the next batch of an import writes, sorted by what the duplicate check decided.
+#[derive(Default)]
+struct PendingWrites {
+    puzzles: Vec<ChessPuzzle>,
+    //ids of existing puzzles that get a note in the deck
+    shared: Vec<String>,
+    moved: Vec<i64>,
+    links: Vec<(i64, String)>,
+}
+
+impl PendingWrites {
+    fn len(&self) -> usize {
+        self.puzzles.len() + self.shared.len() + self.moved.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn push(&mut self, action: DuplicateAction, puzzle: ChessPuzzle, line: Option<u64>, report: &mut ImportReport) {
+        match action {
+            DuplicateAction::Import => self.puzzles.push(puzzle),
+            DuplicateAction::Skip(reason, detail) => report.skip(line, reason, Some(&puzzle.puzzle_id), detail),
+            DuplicateAction::Link(puzzle_id) => self.shared.push(puzzle_id),
+            DuplicateAction::Move(nids) => self.moved.extend(nids),
+        }
+    }
+}
+
+fn parse_column<T: FromStr + Default>(
+    layout: &CsvLayout,
+    record: &csv::StringRecord,
+    column: CsvColumn,
+) -> Result<T, (SkipReason, String)> {
+    match layout.get(record, column) {
+        None | Some("") => Ok(T::default()),
+        Some(value) => value.parse::<T>().map_err(|_| (
+            SkipReason::InvalidValue,
+            format!("Invalid {} '{}'", column.header(), value),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::csv_format::{PUZZLE_COLUMNS, SCHEDULING_COLUMNS};
+
+    #[test]
+    fn exported_csv_rows_read_back_unchanged() {
+        let puzzle = ChessPuzzle {
+            puzzle_id: "deck_00042".to_string(),
+            fen: "r6k/pp2r2p/4Rp1Q/3p4/8/1N1P2R1/PqP2bPP/7K b - - 0 24".to_string(),
+            moves: "f2g3 e6e7 b2b1 b3c1 b1c1 h6c1".to_string(),
+            rating: 1850,
+            rating_deviation: 76,
+            popularity: 94,
+            nb_plays: 6230,
+            themes: "crushing hangingPiece long middlegame".to_string(),
+            game_url: "https://lichess.org/787zsVup/black#48".to_string(),
+            opening_tags: "Sicilian_Defense Sicilian_Defense_Najdorf_Variation".to_string(),
+            comment: "Don't take the rook, \"Qb1+\" first,\nthen Qxc1".to_string(),
+            has_setup_move: true,
+        };
+        let schedule = CardSchedule { card_type: 2, due_in_days: 12, interval: 30, ease_factor: 2350, reps: 7, lapses: 1 };
+
+        //the header and record as DeckService::export_deck_csv writes them
+        let mut header: Vec<&str> = PUZZLE_COLUMNS.iter().map(|c| c.header()).collect();
+        header.extend(SCHEDULING_COLUMNS.iter().map(|c| c.header()));
+        let record = [
+            puzzle.puzzle_id.clone(),
+            puzzle.fen.clone(),
+            puzzle.moves.clone(),
+            puzzle.rating.to_string(),
+            puzzle.rating_deviation.to_string(),
+            puzzle.popularity.to_string(),
+            puzzle.nb_plays.to_string(),
+            puzzle.themes.clone(),
+            puzzle.game_url.clone(),
+            puzzle.opening_tags.clone(),
+            puzzle.comment.clone(),
+            puzzle.has_setup_move.to_string(),
+            schedule.card_type.to_string(),
+            schedule.due_in_days.to_string(),
+            schedule.interval.to_string(),
+            schedule.ease_factor.to_string(),
+            schedule.reps.to_string(),
+            schedule.lapses.to_string(),
+        ];
+        let mut wtr = csv::WriterBuilder::new().has_headers(false).from_writer(Vec::new());
+        wtr.write_record(&header).unwrap();
+        wtr.write_record(&record).unwrap();
+        let csv_content = wtr.into_inner().unwrap();
+
+        let records: Vec<csv::StringRecord> = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(csv_content.as_slice())
+            .records()
+            .map(|r| r.unwrap())
+            .collect();
+        let layout = CsvLayout::detect(&records[0]);
+        assert!(layout.has_header);
+        assert!(!layout.is_lichess_format());
+
+        let (read, read_schedule) = ImportService::puzzle_from_csv_row(&layout, &records[1]).unwrap();
+        assert_eq!(read.puzzle_id, puzzle.puzzle_id);
+        assert_eq!(read.fen, puzzle.fen);
+        assert_eq!(read.moves, puzzle.moves);
+        assert_eq!(read.rating, puzzle.rating);
+        assert_eq!(read.rating_deviation, puzzle.rating_deviation);
+        assert_eq!(read.popularity, puzzle.popularity);
+        assert_eq!(read.nb_plays, puzzle.nb_plays);
+        assert_eq!(read.themes, puzzle.themes);
+        assert_eq!(read.game_url, puzzle.game_url);
+        assert_eq!(read.opening_tags, puzzle.opening_tags);
+        assert_eq!(read.comment, puzzle.comment);
+        assert_eq!(read.has_setup_move, puzzle.has_setup_move);
+        assert_eq!(read_schedule, Some(schedule));
+    }
+}
@@ -0,0 +1,259 @@
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::AnkiChessError;
+use crate::models::job::{JobCounts, JobInfo, JobKind, JobStatus};
+use crate::models::lichessdb::{DownloadProgress, IndexingProgress};
+
+//finished jobs are kept so the ui can still show how they ended
+const MAX_FINISHED_JOBS: usize = 50;
+
+/// Hears about every change of a job, and about the finer grained progress of downloads
+/// and indexing runs. The app forwards these as events to the ui, scripts can print them.
+pub trait JobObserver: Send + Sync {
+    fn job_updated(&self, _info: &JobInfo) {}
+    fn download_progress(&self, _progress: &DownloadProgress) {}
+    fn indexing_progress(&self, _progress: &IndexingProgress) {}
+}
+
+struct NoObserver;
+
+impl JobObserver for NoObserver {}
+
+struct JobEntry {
+    info: JobInfo,
+    cancel: Arc<AtomicBool>,
+}
+
+/// Registry of running and recently finished imports, downloads and indexing runs.
+pub struct JobManager {
+    next_id: AtomicU64,
+    jobs: Mutex<Vec<JobEntry>>,
+    observer: Arc<dyn JobObserver>,
+}
+
+impl Default for JobManager {
+    fn default() -> Self {
+        Self::new(Arc::new(NoObserver))
+    }
+}
+
+impl JobManager {
+    pub fn new(observer: Arc<dyn JobObserver>) -> Self {
+        Self { next_id: AtomicU64::new(0), jobs: Mutex::new(Vec::new()), observer }
+    }
+
+    pub fn start(self: &Arc<Self>, kind: JobKind, label: &str) -> JobHandle {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let cancel = Arc::new(AtomicBool::new(false));
+        let info = JobInfo {
+            id,
+            kind,
+            label: label.to_string(),
+            status: JobStatus::Running,
+            message: "Starting...".to_string(),
+            counts: JobCounts::default(),
+            error: None,
+            report_id: None,
+            started_at: now_ms(),
+            finished_at: None,
+        };
+
+        let mut jobs = self.entries();
+        jobs.push(JobEntry { info: info.clone(), cancel: cancel.clone() });
+        //drop the oldest finished jobs, running ones are never dropped
+        let finished = jobs.iter().filter(|job| job.info.status != JobStatus::Running).count();
+        let mut excess = finished.saturating_sub(MAX_FINISHED_JOBS);
+        jobs.retain(|job| {
+            if excess > 0 && job.info.status != JobStatus::Running {
+                excess -= 1;
+                return false;
+            }
+            true
+        });
+        drop(jobs);
+
+        self.observer.job_updated(&info);
+        JobHandle { id, cancel, manager: self.clone() }
+    }
+
+    /// Runs `work` on the blocking pool as a new job and returns its id right away.
+    /// The message returned by `work` becomes the final message of the job, a panic fails it.
+    pub fn spawn_blocking<F>(self: &Arc<Self>, kind: JobKind, label: &str, work: F) -> u64
+    where
+        F: FnOnce(&JobHandle) -> Result<String, AnkiChessError> + Send + 'static,
+    {
+        let job = self.start(kind, label);
+        let id = job.id();
+        tokio::task::spawn_blocking(move || {
+            //without this a panicking import would stay "running" forever
+            let result = panic::catch_unwind(AssertUnwindSafe(|| work(&job)))
+                .unwrap_or_else(|payload| Err(AnkiChessError::JoinError(format!("Job panicked: {}", panic_message(&*payload)))));
+            job.finish(result);
+        });
+        id
+    }
+
+    pub fn list(&self) -> Vec<JobInfo> {
+        self.entries().iter().map(|job| job.info.clone()).collect()
+    }
+
+    pub fn get(&self, id: u64) -> Option<JobInfo> {
+        self.entries().iter().find(|job| job.info.id == id).map(|job| job.info.clone())
+    }
+
+    /// Asks a running job to stop. Returns false when no such job is running.
+    pub fn cancel(&self, id: u64) -> bool {
+        match self.entries().iter().find(|job| job.info.id == id && job.info.status == JobStatus::Running) {
+            Some(job) => {
+                job.cancel.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn cancel_kind(&self, kind: JobKind) -> usize {
+        let jobs = self.entries();
+        let running: Vec<&JobEntry> = jobs
+            .iter()
+            .filter(|job| job.info.kind == kind && job.info.status == JobStatus::Running)
+            .collect();
+        for job in &running {
+            job.cancel.store(true, Ordering::Relaxed);
+        }
+        running.len()
+    }
+
+    fn update(&self, id: u64, f: impl FnOnce(&mut JobInfo)) -> Option<JobInfo> {
+        let mut jobs = self.entries();
+        let job = jobs.iter_mut().find(|job| job.info.id == id)?;
+        f(&mut job.info);
+        Some(job.info.clone())
+    }
+
+    //a panic while holding the lock shouldn't take the whole registry down
+    fn entries(&self) -> MutexGuard<'_, Vec<JobEntry>> {
+        self.jobs.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Given to the code doing the work, to report progress and to notice a cancel request.
+#[derive(Clone)]
+pub struct JobHandle {
+    id: u64,
+    cancel: Arc<AtomicBool>,
+    manager: Arc<JobManager>,
+}
+
+impl JobHandle {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    pub fn cancel_flag(&self) -> &AtomicBool {
+        &self.cancel
+    }
+
+    pub fn check_cancelled(&self) -> Result<(), AnkiChessError> {
+        if self.is_cancelled() {
+            return Err(AnkiChessError::Cancelled(format!("Job {} was cancelled", self.id)));
+        }
+        Ok(())
+    }
+
+    pub fn progress(&self, message: impl Into<String>, counts: JobCounts) {
+        let message = message.into();
+        self.emit(|info| {
+            info.message = message;
+            info.counts = counts;
+        });
+    }
+
+    pub fn message(&self, message: impl Into<String>) {
+        let message = message.into();
+        self.emit(|info| info.message = message);
+    }
+
+    pub fn download_progress(&self, progress: DownloadProgress) {
+        self.manager.observer.download_progress(&progress);
+    }
+
+    pub fn indexing_progress(&self, progress: IndexingProgress) {
+        self.manager.observer.indexing_progress(&progress);
+    }
+
+    //sent along with the next progress or finish event
+    pub fn attach_report(&self, report_id: i64) {
+        self.manager.update(self.id, |info| info.report_id = Some(report_id));
+    }
+
+    /// Marks the job as done. A `Cancelled` error ends it as cancelled rather than failed.
+    pub fn finish(&self, result: Result<String, AnkiChessError>) {
+        self.emit(|info| {
+            info.finished_at = Some(now_ms());
+            match result {
+                Ok(message) => {
+                    info.status = JobStatus::Completed;
+                    info.message = message;
+                }
+                Err(AnkiChessError::Cancelled(message)) => {
+                    info.status = JobStatus::Cancelled;
+                    info.message = message;
+                }
+                Err(e) => {
+                    info.status = JobStatus::Failed;
+                    info.message = e.to_string();
+                    info.error = Some(e.to_string());
+                }
+            }
+        });
+    }
+
+    fn emit(&self, f: impl FnOnce(&mut JobInfo)) {
+        if let Some(info) = self.manager.update(self.id, f) {
+            self.manager.observer.job_updated(&info);
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(|s| s.as_str()))
+        .unwrap_or("unknown cause")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn panicking_work_fails_the_job() {
+        let jobs = Arc::new(JobManager::default());
+        let id = jobs.spawn_blocking(JobKind::Import, "CSV import", |_| panic!("bad row"));
+
+        let mut info = jobs.get(id).unwrap();
+        for _ in 0..100 {
+            if info.status != JobStatus::Running {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            info = jobs.get(id).unwrap();
+        }
+        assert_eq!(info.status, JobStatus::Failed);
+        assert!(info.error.unwrap().contains("bad row"));
+    }
+}
@@ -0,0 +1,366 @@
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use anki::collection::Collection;
+use rusqlite::{params, Connection};
+use reqwest::{self, header};
+
+use crate::config::{CoreConfig, PUZZLE_DUMP_FILE_NAME};
+use crate::error::AnkiChessError;
+use crate::models::import_report::{SkipReason, SkippedRow, MAX_REPORTED_ROWS};
+use crate::models::job::JobCounts;
+use crate::models::lichessdb::{DbStatus, DownloadProgress, IndexingProgress, PuzzleDbChangeReport};
+use crate::models::puzzle::{PuzzleRecord};
+use crate::repository::lichessdb_repo::LichessdbRepository;
+use crate::repository::puzzle_repo::PuzzleRepository;
+use crate::services::job_service::JobHandle;
+use crate::services::notetype_service::NotetypeService;
+use crate::shared::download::{self, DownloadOptions};
+
+pub const DEFAULT_PUZZLE_DB_BASE_URL: &str = "https://database.lichess.org";
+const CHANGE_REPORT_META_KEY: &str = "change_report";
+
+//validators of the dump that was last indexed, compared against a HEAD of the dump url
+#[derive(Debug)]
+struct RemoteDumpInfo {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    content_length: Option<u64>,
+}
+
+impl RemoteDumpInfo {
+    fn differs_from(&self, local: &RemoteDumpInfo) -> bool {
+        if let (Some(remote), Some(local)) = (&self.etag, &local.etag) {
+            return remote != local;
+        }
+        if let (Some(remote), Some(local)) = (&self.last_modified, &local.last_modified) {
+            return remote != local;
+        }
+        if let (Some(remote), Some(local)) = (self.content_length, local.content_length) {
+            return remote != local;
+        }
+        //nothing to compare against, e.g. a database indexed by an older version
+        true
+    }
+}
+
+pub struct LichessdbService;
+
+impl LichessdbService {
+    
+    
+    pub async fn download_and_index(
+        config: CoreConfig,
+        base_url: String,
+        job: JobHandle,
+        expected_sha256: Option<String>,
+    ) -> Result<PuzzleDbChangeReport, AnkiChessError> {
+        config.ensure_data_dir()?;
+        let zst_path = config.puzzle_dump_path();
+        let url = Self::dump_url(&base_url);
+        let remote = Self::fetch_remote_info(&url).await?;
+
+        
+        let zst_path_download = zst_path.clone();
+        let download_job = job.clone();
+
+        //a .part file left by an earlier run is resumed instead of starting over
+        let options = DownloadOptions {
+            if_range: remote.etag.clone().or_else(|| remote.last_modified.clone()),
+            expected_size: remote.content_length,
+            expected_sha256,
+            ..Default::default()
+        };
+        job.message("Downloading...");
+        let sha256 = tokio::task::spawn_blocking(move || -> Result<String, AnkiChessError> {
+            let mut last_percent = None;
+            let sha256 = download::download_resumable(&url, &zst_path_download, &options, download_job.cancel_flag(), |downloaded, total| {
+                if let Some(total) = total {
+                    download_job.download_progress(DownloadProgress { downloaded, total });
+                    //the registry only hears about whole percents
+                    let percent = downloaded * 100 / total.max(1);
+                    if last_percent != Some(percent) {
+                        last_percent = Some(percent);
+                        download_job.progress(format!("Downloading... {}%", percent), JobCounts {
+                            processed: downloaded as usize, total: Some(total as usize), ..Default::default()
+                        });
+                    }
+                }
+            }, |attempt, e| {
+                download_job.message(format!("Download attempt {} failed, resuming: {}", attempt, e));
+            })?;
+            download::check_zstd_archive(&zst_path_download)?;
+            Ok(sha256)
+        }).await??;
+
+        let db_path = config.puzzle_db_path();
+        let report = Self::index_dump(job.clone(), zst_path.clone(), db_path.clone()).await?;
+
+        let conn = Connection::open(&db_path)?;
+        Self::set_meta(&conn, "dump_sha256", Some(&sha256))?;
+        Self::set_meta(&conn, "remote_etag", remote.etag.as_deref())?;
+        Self::set_meta(&conn, "remote_last_modified", remote.last_modified.as_deref())?;
+        Self::set_meta(&conn, "remote_content_length", remote.content_length.map(|len| len.to_string()).as_deref())?;
+
+        
+        let _ = fs::remove_file(zst_path);
+        
+        job.indexing_progress(IndexingProgress { status: "finished".to_string(), processed_count: report.indexed });
+
+        Ok(report)
+    }
+
+    /// Indexes a dump that is already on disk, e.g. copied from a shared drive. The file is left in place.
+    pub async fn index_local_dump(
+        config: CoreConfig,
+        job: JobHandle,
+        dump_path: PathBuf,
+    ) -> Result<PuzzleDbChangeReport, AnkiChessError> {
+        if !dump_path.is_file() {
+            return Err(AnkiChessError::NotFound(format!("File not found: {}", dump_path.display())));
+        }
+
+        let check_path = dump_path.clone();
+        tokio::task::spawn_blocking(move || -> Result<(), AnkiChessError> {
+            if check_path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("zst")) {
+                download::check_zstd_archive(&check_path)?;
+            }
+            Ok(())
+        }).await??;
+
+        config.ensure_data_dir()?;
+        let db_path = config.puzzle_db_path();
+        let report = Self::index_dump(job.clone(), dump_path, db_path.clone()).await?;
+
+        //the headers of an earlier download don't describe this dump, so the update check has nothing to compare
+        let conn = Connection::open(&db_path)?;
+        for key in ["dump_sha256", "remote_etag", "remote_last_modified", "remote_content_length"] {
+            Self::set_meta(&conn, key, None)?;
+        }
+
+        job.indexing_progress(IndexingProgress { status: "finished".to_string(), processed_count: report.indexed });
+
+        Ok(report)
+    }
+
+    /// Loads a Lichess puzzle dump, either `.csv.zst` or plain `.csv`, into the puzzle database.
+    /// The dump is staged first and diffed against the current rows, so a re-index only
+    /// rewrites puzzles that changed and leaves a report of what did.
+    /// Cancelling the job rolls the staged rows back and leaves the current database as it was.
+    async fn index_dump(
+        job: JobHandle,
+        dump_path: PathBuf,
+        db_path: PathBuf,
+    ) -> Result<PuzzleDbChangeReport, AnkiChessError> {
+        job.indexing_progress(IndexingProgress { status: "starting".to_string(), processed_count: 0 });
+        job.message("Indexing...");
+
+        tokio::task::spawn_blocking(move || -> Result<PuzzleDbChangeReport, AnkiChessError> {
+            let mut conn = Connection::open(&db_path)?;
+            LichessdbRepository::init_tables(&conn)?;
+            LichessdbRepository::create_staging(&conn)?;
+
+            let tx = conn.transaction()?;
+            let mut processed_count = 0;
+            let mut skipped = 0;
+            let mut skipped_rows = Vec::new();
+
+            {
+                let file = File::open(&dump_path)?;
+                let is_zst = dump_path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("zst"));
+                let reader: Box<dyn Read> = if is_zst {
+                    Box::new(zstd::stream::read::Decoder::new(file)?)
+                } else {
+                    Box::new(BufReader::new(file))
+                };
+                let mut rdr = csv::Reader::from_reader(reader);
+
+                let mut stmt = LichessdbRepository::prepare_staging_insert(&tx)?;
+
+                for (index, result) in rdr.deserialize().enumerate() {
+                    let record: PuzzleRecord = match result {
+                        Ok(r) => r,
+                        //a read error means the file itself is broken, stop instead of indexing half of it
+                        Err(e) if e.is_io_error() => return Err(e.into()),
+                        Err(e) => {
+                            skipped += 1;
+                            if skipped_rows.len() < MAX_REPORTED_ROWS {
+                                let reason = match e.kind() {
+                                    csv::ErrorKind::UnequalLengths { .. } => SkipReason::MissingColumn,
+                                    _ => SkipReason::InvalidValue,
+                                };
+                                let line = e.position().map(|p| p.line());
+                                skipped_rows.push(SkippedRow { line, reason, puzzle_id: None, detail: e.to_string() });
+                            }
+                            continue;
+                        }
+                    };
+
+                    LichessdbRepository::insert_staging(&mut stmt, &record)?;
+
+                    processed_count += 1;
+                    if index % 10000 == 0 {
+                        job.check_cancelled()?;
+                        job.indexing_progress(IndexingProgress { status: "indexing".to_string(), processed_count });
+                        job.progress(format!("Indexing... ({} puzzles)", processed_count), JobCounts { processed: processed_count, ..Default::default() });
+                    }
+                }
+            }
+            job.check_cancelled()?;
+            tx.commit()?;
+
+            job.indexing_progress(IndexingProgress { status: "diffing".to_string(), processed_count });
+            job.message("Comparing with the current database...");
+
+            let tx = conn.transaction()?;
+            let mut report = LichessdbRepository::apply_staging(&tx)?;
+            report.skipped = skipped;
+            report.skipped_rows = skipped_rows;
+            let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_millis() as u64;
+            Self::set_meta(&tx, "last_updated", Some(&now.to_string()))?;
+            Self::set_meta(&tx, CHANGE_REPORT_META_KEY, Some(&serde_json::to_string(&report)?))?;
+            tx.commit()?;
+
+            LichessdbRepository::drop_staging(&conn)?;
+
+            Ok(report)
+        }).await?
+    }
+
+    /// Summary of the last re-index with up to `limit` of the changed puzzles.
+    pub fn get_change_report(config: &CoreConfig, limit: usize) -> Result<Option<PuzzleDbChangeReport>, AnkiChessError> {
+        let db_path = config.puzzle_db_path();
+        if !db_path.exists() {
+            return Ok(None);
+        }
+
+        let conn = Connection::open(db_path)?;
+        LichessdbRepository::init_tables(&conn)?;
+        let Some(json) = Self::get_meta(&conn, CHANGE_REPORT_META_KEY) else {
+            return Ok(None);
+        };
+
+        let mut report: PuzzleDbChangeReport = serde_json::from_str(&json)?;
+        report.changes = LichessdbRepository::get_changes(&conn, limit)?;
+        Ok(Some(report))
+    }
+
+    /// Copies rating, popularity, themes and opening tags from the puzzle database into the
+    /// puzzles already imported into the collection and refreshes the fields of their notes.
+    /// Cards and their scheduling are not touched. Returns the number of puzzles updated.
+    pub fn propagate_metadata(col: &mut Collection, db_path: &Path) -> Result<usize, AnkiChessError> {
+        if !db_path.exists() {
+            return Err(AnkiChessError::NotFound("Puzzle database has not been indexed yet".into()));
+        }
+        let puzzle_db = Connection::open(db_path)?;
+
+        //puzzles removed from the dump keep their last known metadata
+        let mut updates = Vec::new();
+        for puzzle_id in PuzzleRepository::get_all_puzzle_ids(col.storage.db())? {
+            if let Some(metadata) = LichessdbRepository::get_metadata(&puzzle_db, &puzzle_id)? {
+                updates.push(metadata);
+            }
+        }
+
+        let db = col.storage.db();
+        let mut changed = Vec::new();
+        db.execute("BEGIN TRANSACTION", [])?;
+        for metadata in updates {
+            if PuzzleRepository::update_metadata(db, &metadata)? {
+                changed.push(metadata.puzzle_id);
+            }
+        }
+        db.execute("COMMIT", [])?;
+
+        let mut nids = Vec::new();
+        for chunk in changed.chunks(500) {
+            nids.extend(PuzzleRepository::get_nids_by_puzzle_ids(col.storage.db(), chunk)?);
+        }
+        NotetypeService::refresh_notes(col, &nids, true)?;
+
+        Ok(changed.len())
+    }
+
+    
+    
+    pub fn get_status(config: &CoreConfig) -> Result<DbStatus, AnkiChessError> {
+        let db_path = config.puzzle_db_path();
+
+        if !db_path.exists() {
+            return Ok(DbStatus::default());
+        }
+
+        let conn = Connection::open(db_path)?;
+
+        let puzzle_count = conn.query_row("SELECT COUNT(*) FROM puzzles", [], |row| row.get(0)).unwrap_or(0);
+        
+        let last_updated = conn.query_row("SELECT value FROM meta WHERE key = 'last_updated'", [], |row| {
+            Ok(row.get::<_, String>(0).ok().and_then(|s| s.parse::<u64>().ok()))
+        }).unwrap_or(None);
+
+        Ok(DbStatus {
+            db_exists: true,
+            last_updated,
+            puzzle_count,
+        })
+    }
+
+    /// Compares the ETag, Last-Modified or Content-Length of the dump at `base_url` with the
+    /// ones recorded when the local database was indexed.
+    pub async fn check_for_update(config: &CoreConfig, base_url: &str) -> Result<bool, AnkiChessError> {
+        let db_path = config.puzzle_db_path();
+
+        if !db_path.exists() {
+            return Ok(true);
+        }
+
+        let remote = Self::fetch_remote_info(&Self::dump_url(base_url)).await?;
+
+        let conn = Connection::open(db_path)?;
+        let local = RemoteDumpInfo {
+            etag: Self::get_meta(&conn, "remote_etag"),
+            last_modified: Self::get_meta(&conn, "remote_last_modified"),
+            content_length: Self::get_meta(&conn, "remote_content_length").and_then(|v| v.parse().ok()),
+        };
+
+        Ok(remote.differs_from(&local))
+    }
+
+    pub fn dump_url(base_url: &str) -> String {
+        format!("{}/{}", base_url.trim_end_matches('/'), PUZZLE_DUMP_FILE_NAME)
+    }
+
+    async fn fetch_remote_info(url: &str) -> Result<RemoteDumpInfo, AnkiChessError> {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!("ankichess/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+
+        let response = client.head(url).send().await?;
+        if !response.status().is_success() {
+            return Err(AnkiChessError::HttpError(format!("HTTP Error: {} for {}", response.status(), url)));
+        }
+
+        let value_of = |name: header::HeaderName| {
+            response.headers().get(name).and_then(|v| v.to_str().ok()).map(|v| v.to_string())
+        };
+
+        //read the header directly, the body length of a HEAD response is always 0
+        Ok(RemoteDumpInfo {
+            etag: value_of(header::ETAG),
+            last_modified: value_of(header::LAST_MODIFIED),
+            content_length: value_of(header::CONTENT_LENGTH).and_then(|v| v.parse().ok()),
+        })
+    }
+
+    fn get_meta(conn: &Connection, key: &str) -> Option<String> {
+        conn.query_row("SELECT value FROM meta WHERE key = ?1", params![key], |row| row.get(0)).ok()
+    }
+
+    fn set_meta(conn: &Connection, key: &str, value: Option<&str>) -> Result<(), AnkiChessError> {
+        match value {
+            Some(value) => conn.execute("INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)", params![key, value])?,
+            None => conn.execute("DELETE FROM meta WHERE key = ?1", params![key])?,
+        };
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,7 @@
+pub mod note_service;
+pub mod deck_service;
+pub mod import_service;
+pub mod job_service;
+pub mod lichessdb_service;
+pub mod notetype_service;
+pub mod undo_service;
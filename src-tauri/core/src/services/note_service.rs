@@ -0,0 +1,609 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{chess, error::AnkiChessError, models::{card::{AddNotePayload, BrowseCardInfo, BrowseOptions, NoteFields, PaginatedBrowseResult, PuzzleAttempt, PuzzleReviewStats, StudyCard, UpdateNotePayload}, puzzle::ChessPuzzle}, shared::{grading, utils::{format_anki_sfld, get_deck_name, to_proto_card_id, to_proto_note_id}}};
+use anki::{collection::Collection, prelude::*, scheduler::answering::{CardAnswer, Rating}, scheduler::states::{CardState, FilteredState, LearnState, NormalState, RelearnState, ReviewState}, search::SortMode, services::{CardsService, NotesService}};
+use crate::repository::puzzle_repo::PuzzleRepository;
+use crate::services::notetype_service::NotetypeService;
+use crate::services::undo_service::{PuzzleEdit, SideTableChange, UndoJournal};
+
+pub struct NoteService;
+
+impl NoteService {
+    
+    pub fn create_note(
+        col: &mut Collection,
+        journal: &mut UndoJournal,
+        payload: AddNotePayload,
+    ) -> Result<i64, AnkiChessError> {
+        
+        chess::validate_puzzle(&payload.fen, &payload.solution)?;
+
+        let deck_id = DeckId(payload.deck_id);
+        
+        get_deck_name(col, deck_id)?;
+        
+        let puzzle: ChessPuzzle = payload.into();
+        
+        let clean_id = puzzle.puzzle_id.clone();
+        let anki_sfld = format_anki_sfld(&clean_id);
+
+        PuzzleRepository::save(col.storage.db(), &puzzle)?;
+
+        let nt = NotetypeService::ensure_chess_notetype(col)?;
+        let mut note = NotetypeService::new_chess_note(&nt, &puzzle, &anki_sfld)?;
+        
+        col.add_note(&mut note, deck_id)?;
+        let nid = note.id.0;
+
+        PuzzleRepository::create_link(col.storage.db(), nid, &clean_id)?;
+        journal.record(col, vec![
+            SideTableChange::PuzzlesAdded { puzzles: vec![puzzle] },
+            SideTableChange::LinksAdded { links: vec![(nid, clean_id)] },
+        ]);
+
+        Ok(nid)
+    }
+
+    
+    pub fn update_note(
+        col: &mut Collection,
+        journal: &mut UndoJournal,
+        payload: UpdateNotePayload,
+    ) -> Result<(), AnkiChessError> {
+        
+        
+        chess::validate_puzzle(&payload.fen, &payload.solution)?;
+
+        let before = PuzzleRepository::get_by_nid(col.storage.db(), payload.note_id)?;
+
+        
+        
+        
+        let success = PuzzleRepository::update_fields_by_nid(
+            col.storage.db(),
+            payload.note_id,
+            &payload.fen,
+            &payload.solution,
+            &payload.comment
+        )?;
+
+        if !success {
+            return Err(AnkiChessError::NotFound(
+                format!("No puzzle found linked to note id {}", payload.note_id)
+            ));
+        }
+
+        //notes in other decks can share the puzzle, all of them change in the one undo step
+        let puzzle_ids: Vec<String> = before.iter().map(|p| p.puzzle_id.clone()).collect();
+        let nids = PuzzleRepository::get_nids_by_puzzle_ids(col.storage.db(), &puzzle_ids)?;
+        let updated = NotetypeService::refresh_notes(col, &nids, false)?;
+
+        if let Some(p) = before.filter(|_| updated > 0) {
+            let before = PuzzleEdit { fen: p.fen, moves: p.moves, comment: p.comment };
+            let after = PuzzleEdit { fen: payload.fen, moves: payload.solution, comment: payload.comment };
+            journal.record(col, vec![SideTableChange::PuzzleEdited { nid: payload.note_id, before, after }]);
+        }
+
+        Ok(())
+    }
+
+    pub fn get_card_details(col: &mut Collection, card_id: i64) -> Result<BrowseCardInfo, AnkiChessError> {
+        
+        let proto_cid = to_proto_card_id(card_id);
+        let card = col.get_card(proto_cid)?; 
+        let nid = card.note_id;      
+
+        
+        
+        let puzzle = PuzzleRepository::get_by_nid(col.storage.db(), nid)?
+            .ok_or_else(|| AnkiChessError::NotFound(format!("No puzzle data found for note id {}", nid)))?;
+
+        
+        
+        Ok(BrowseCardInfo {
+            card_id: card.id,
+            note_id: nid,
+            fen: puzzle.fen,
+            solution: puzzle.moves, 
+            comment: puzzle.comment,
+            puzzle_id: puzzle.puzzle_id,
+            rating: puzzle.rating,
+            rating_deviation: puzzle.rating_deviation,
+            popularity: puzzle.popularity,
+            nb_plays: puzzle.nb_plays,
+            themes: puzzle.themes,
+            game_url: puzzle.game_url,
+            opening_tags: puzzle.opening_tags,
+            has_setup_move: puzzle.has_setup_move,
+        })
+    }
+
+    pub fn delete_notes(
+        col: &mut Collection,
+        journal: &mut UndoJournal,
+        note_ids: Vec<i64>,
+    ) -> Result<(), AnkiChessError> {
+        
+        
+        
+        
+        
+        
+        let removed_links: Vec<(i64, String)> = PuzzleRepository::get_batch_by_nids(col.storage.db(), &note_ids)?
+            .into_iter()
+            .map(|(nid, puzzle)| (nid, puzzle.puzzle_id))
+            .collect();
+
+        col.storage.db().execute("BEGIN TRANSACTION", [])?;
+        PuzzleRepository::delete_links(col.storage.db(), &note_ids)?;
+        col.storage.db().execute("COMMIT", [])?;
+
+        
+        let nids: Vec<NoteId> = note_ids.into_iter().map(NoteId).collect();
+
+        
+        col.remove_notes(&nids)?;
+        journal.record(col, vec![SideTableChange::LinksRemoved { links: removed_links }]);
+
+        Ok(())
+    }
+
+    /// Deletes the puzzles no note links to anymore, e.g. after decks were deleted.
+    pub fn cleanup_unused_puzzles(col: &Collection) -> Result<usize, AnkiChessError> {
+        let db = col.storage.db();
+        db.execute("BEGIN TRANSACTION", [])?;
+
+        match PuzzleRepository::delete_unused_puzzles(db) {
+            Ok(count) => {
+                db.execute("COMMIT", [])?;
+                Ok(count)
+            }
+            Err(e) => {
+                db.execute("ROLLBACK", []).ok();
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Ids of the notes matching an Anki search such as `deck:Tactics`.
+    pub fn find_notes(col: &mut Collection, query: &str) -> Result<Vec<i64>, AnkiChessError> {
+        Ok(col.search_notes_unordered(query)?.into_iter().map(|nid| nid.0).collect())
+    }
+
+    pub fn find_cards(col: &mut Collection, query: &str) -> Result<Vec<i64>, AnkiChessError> {
+        Ok(col.search_cards(query, SortMode::NoOrder)?.into_iter().map(|cid| cid.0).collect())
+    }
+
+    /// The fields of each note, `None` for ids that don't exist.
+    pub fn get_note_fields(col: &mut Collection, note_ids: &[i64]) -> Result<Vec<Option<NoteFields>>, AnkiChessError> {
+        let chess_nt = NotetypeService::ensure_chess_notetype(col)?;
+        let puzzles = PuzzleRepository::get_batch_by_nids(col.storage.db(), note_ids)?;
+        let mut notetypes = HashMap::new();
+
+        let mut result = Vec::with_capacity(note_ids.len());
+        for &nid in note_ids {
+            let Ok(note) = NotesService::get_note(col, to_proto_note_id(nid)) else {
+                result.push(None);
+                continue;
+            };
+
+            let nt = match notetypes.get(&note.notetype_id) {
+                Some(nt) => Arc::clone(nt),
+                None => {
+                    let Some(nt) = col.get_notetype(NotetypeId(note.notetype_id))? else {
+                        result.push(None);
+                        continue;
+                    };
+                    notetypes.insert(note.notetype_id, nt.clone());
+                    nt
+                }
+            };
+
+            let values = match puzzles.get(&nid) {
+                Some(puzzle) if nt.id == chess_nt.id => NotetypeService::field_values(&nt, &note.fields, puzzle, None),
+                _ => note.fields.clone(),
+            };
+            let card_ids = Self::find_cards(col, &format!("nid:{}", nid))?;
+
+            result.push(Some(NoteFields {
+                note_id: nid,
+                notetype: nt.name.clone(),
+                tags: note.tags.clone(),
+                fields: nt.fields.iter().map(|f| f.name.clone()).zip(values).collect(),
+                card_ids,
+                modified_secs: note.mtime_secs as i64,
+            }));
+        }
+        Ok(result)
+    }
+
+    
+    pub fn get_next_study_card(
+        col: &mut Collection, 
+        deck_id: i64
+    ) -> Result<Option<StudyCard>, AnkiChessError> {
+        
+        
+        col.set_current_deck(DeckId(deck_id))?;
+
+        
+        
+        let queued_card = match col.get_next_card()? {
+            Some(card) => card,
+            None => return Ok(None), 
+        };
+
+        
+        let states = col.get_scheduling_states(queued_card.card.id())?;
+        
+        let again_secs = Self::get_interval_secs(states.again);
+        let hard_secs = Self::get_interval_secs(states.hard);
+        let good_secs = Self::get_interval_secs(states.good);
+        let easy_secs = Self::get_interval_secs(states.easy);
+
+        
+        let nid = queued_card.card.note_id().0;
+        
+        
+        let puzzle = PuzzleRepository::get_by_nid(col.storage.db(), nid)?
+            .ok_or_else(|| AnkiChessError::NotFound(
+                format!("Data consistency error: Anki note {} exists but has no linked puzzle in app_chess_puzzles", nid)
+            ))?;
+
+        
+        
+        let review_stats = PuzzleRepository::get_review_stats(col.storage.db(), queued_card.card.id().0)?;
+        let auto_grade = PuzzleRepository::get_deck_grading(col.storage.db(), deck_id)?.auto_grade;
+
+        let solution_vec: Vec<String> = puzzle.moves
+            .split_whitespace()
+            .map(|s| s.to_string())
+            .collect();
+
+        Ok(Some(StudyCard {
+            
+            card_id: queued_card.card.id().0,
+            note_id: nid,
+            deck_id,
+            again_secs,
+            hard_secs,
+            good_secs,
+            easy_secs,
+
+            
+            puzzle_id: puzzle.puzzle_id,
+            fen: puzzle.fen,
+            moves: solution_vec,
+            rating: puzzle.rating,
+            rating_deviation: puzzle.rating_deviation,
+            popularity: puzzle.popularity,
+            nb_plays: puzzle.nb_plays,
+            themes: puzzle.themes,
+            game_url: puzzle.game_url,
+            opening_tags: puzzle.opening_tags,
+            comment: puzzle.comment,
+            has_setup_move: puzzle.has_setup_move,
+            review_stats,
+            auto_grade,
+        }))
+    }
+
+    /// Answers a card with the given 1-4 rating, or derives the rating from the attempt
+    /// and the deck's grading settings when none is given. Returns the rating applied.
+    /// The solving time goes into the revlog, mistakes and hints into the card's
+    /// custom data and `app_chess_reviews`.
+    pub fn answer_card(
+        col: &mut Collection,
+        journal: &mut UndoJournal,
+        card_id: i64,
+        rating: Option<u8>,
+        attempt: PuzzleAttempt,
+    ) -> Result<u8, AnkiChessError> {
+        let card = col.get_card(to_proto_card_id(card_id))?;
+
+        let rating = match rating {
+            Some(rating) => rating,
+            None => {
+                //cards in a filtered deck are graded with the settings of their home deck
+                let deck_id = if card.original_deck_id != 0 { card.original_deck_id } else { card.deck_id };
+                let grading = PuzzleRepository::get_deck_grading(col.storage.db(), deck_id)?;
+                grading::derive_rating(&attempt, &grading)
+            }
+        };
+
+        let states = col.get_scheduling_states(CardId(card_id))?;
+
+        let (rating_enum, new_state) = match rating {
+            1 => (Rating::Again, states.again),
+            2 => (Rating::Hard, states.hard),
+            3 => (Rating::Good, states.good),
+            4 => (Rating::Easy, states.easy),
+            _ => {
+                return Err(AnkiChessError::InvalidInput(
+                    "Invalid rating. Use 1 (Again), 2 (Hard), 3 (Good), or 4 (Easy).".to_string(),
+                ))
+            }
+        };
+
+        let custom_data = Self::merge_custom_data(&card.custom_data, &attempt)?;
+
+        let mut answer = CardAnswer {
+            card_id: CardId(card_id),
+            current_state: states.current,
+            new_state,
+            rating: rating_enum,
+            answered_at: TimestampMillis::now(),
+            milliseconds_taken: attempt.milliseconds_taken,
+            custom_data: Some(custom_data),
+            from_queue: true,
+        };
+
+        col.answer_card(&mut answer)?;
+
+        if let Some(revlog_id) = PuzzleRepository::get_last_revlog_id(col.storage.db(), card_id)? {
+            PuzzleRepository::save_review(col.storage.db(), revlog_id, card_id, rating, &attempt)?;
+            journal.record(col, vec![SideTableChange::ReviewAdded { revlog_id, cid: card_id, rating, attempt }]);
+        }
+        Ok(rating)
+    }
+
+    pub fn get_review_stats(col: &mut Collection, card_id: i64) -> Result<PuzzleReviewStats, AnkiChessError> {
+        Ok(PuzzleRepository::get_review_stats(col.storage.db(), card_id)?)
+    }
+
+    //anki limits custom data to short keys and 100 bytes, so only running totals are kept there
+    fn merge_custom_data(existing: &str, attempt: &PuzzleAttempt) -> Result<String, AnkiChessError> {
+        let mut data: serde_json::Map<String, serde_json::Value> = if existing.trim().is_empty() {
+            serde_json::Map::new()
+        } else {
+            serde_json::from_str(existing).unwrap_or_default()
+        };
+
+        let total = |data: &serde_json::Map<String, serde_json::Value>, key: &str| {
+            data.get(key).and_then(|v| v.as_u64()).unwrap_or(0)
+        };
+        let wrong = total(&data, "wa") + attempt.wrong_attempts as u64;
+        let hints = total(&data, "hu") + attempt.hints_used as u64;
+        data.insert("wa".to_string(), wrong.into());
+        data.insert("hu".to_string(), hints.into());
+
+        Ok(serde_json::to_string(&data)?)
+    }
+
+    pub fn browse_cards(col: &mut Collection, options: BrowseOptions) -> Result<PaginatedBrowseResult, AnkiChessError> {
+        let deck_id = options.deck_id;
+        let page_size = options.page_size;
+        let offset = if options.page > 0 { (options.page - 1) * page_size } else { 0 };
+
+        let filter = options.filter_text.unwrap_or_default().trim().to_string();
+        let sort = options.sort_order.unwrap_or_else(|| "default".to_string());
+
+        let mut sql_base = String::from(
+            "FROM cards c
+             JOIN app_chess_note_links l ON c.nid = l.nid
+             JOIN app_chess_puzzles p ON l.puzzle_id = p.puzzle_id
+             WHERE c.did = ?"
+        );
+
+        let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(deck_id)];
+
+        if !filter.is_empty() {
+            
+            let is_range = filter.contains('-');
+            let range_parts: Vec<&str> = if is_range { filter.split('-').collect() } else { vec![] };
+            
+            
+            if is_range && range_parts.len() == 2 {
+                let min = range_parts[0].trim().parse::<i32>();
+                let max = range_parts[1].trim().parse::<i32>();
+                
+                if let (Ok(min_val), Ok(max_val)) = (min, max) {
+                    sql_base.push_str(" AND (p.rating BETWEEN ? AND ?)");
+                    params.push(Box::new(min_val));
+                    params.push(Box::new(max_val));
+                } else {
+                    
+                    apply_text_search(&mut sql_base, &mut params, &filter);
+                }
+            } 
+            
+            else if let Ok(rating_val) = filter.parse::<i32>() {
+                sql_base.push_str(" AND (p.rating BETWEEN ? AND ?)");
+                params.push(Box::new(rating_val - 50));
+                params.push(Box::new(rating_val + 50));
+            } 
+            
+            else if filter.starts_with('>') {
+                if let Ok(val) = filter[1..].trim().parse::<i32>() {
+                    sql_base.push_str(" AND p.rating > ?");
+                    params.push(Box::new(val));
+                } else {
+                    apply_text_search(&mut sql_base, &mut params, &filter);
+                }
+            }
+            
+            else if filter.starts_with('<') {
+                if let Ok(val) = filter[1..].trim().parse::<i32>() {
+                    sql_base.push_str(" AND p.rating < ?");
+                    params.push(Box::new(val));
+                } else {
+                    apply_text_search(&mut sql_base, &mut params, &filter);
+                }
+            }
+            
+            else {
+                apply_text_search(&mut sql_base, &mut params, &filter);
+            }
+        }
+
+        let conn = col.storage.db();
+
+        
+        let count_sql = format!("SELECT COUNT(*) {}", sql_base);
+        let params_sql = rusqlite::params_from_iter(params.iter().map(|p| p.as_ref()));
+        let total_cards: usize = conn.query_row(&count_sql, params_sql, |row| row.get(0)).unwrap_or(0);
+
+        if total_cards == 0 {
+             return Ok(PaginatedBrowseResult { cards: vec![], total_cards: 0 });
+        }
+
+        
+        let order_clause = match sort.as_str() {
+            "rating-desc" => "ORDER BY p.rating DESC",
+            "rating-asc" => "ORDER BY p.rating ASC",
+            "popularity" => "ORDER BY p.popularity DESC",
+            _ => "ORDER BY c.id DESC", 
+        };
+
+        
+        let select_sql = format!(
+            "SELECT c.id, c.nid, p.* {} {} LIMIT ? OFFSET ?",
+            sql_base, order_clause
+        );
+
+        params.push(Box::new(page_size as i64));
+        params.push(Box::new(offset as i64));
+
+        let params_sql_select = rusqlite::params_from_iter(params.iter().map(|p| p.as_ref()));
+        let mut stmt = conn.prepare(&select_sql)?;
+        
+        let cards_iter = stmt.query_map(params_sql_select, |row| {
+             Ok(BrowseCardInfo {
+                card_id: row.get(0)?,
+                note_id: row.get(1)?,
+                puzzle_id: row.get("puzzle_id")?,
+                fen: row.get("fen")?,
+                solution: row.get("moves")?,
+                rating: row.get("rating")?,
+                rating_deviation: row.get("rating_deviation")?,
+                popularity: row.get("popularity")?,
+                nb_plays: row.get("nb_plays")?,
+                themes: row.get("themes")?,
+                game_url: row.get("game_url")?,
+                opening_tags: row.get("opening_tags")?,
+                comment: row.get("comment")?,
+                has_setup_move: row.get::<_, i32>("has_setup_move")? != 0,
+            })
+        })?;
+
+        let mut cards = Vec::new();
+        for card_res in cards_iter {
+            cards.push(card_res?);
+        }
+
+        Ok(PaginatedBrowseResult {
+            cards,
+            total_cards,
+        })
+    }
+
+    fn get_interval_secs(state: CardState) -> u32 {
+    match state {
+        CardState::Normal(normal_state) => match normal_state {
+            NormalState::New(_) => 0,
+            NormalState::Learning(LearnState { scheduled_secs, .. }) => scheduled_secs,
+            NormalState::Relearning(RelearnState { learning, .. }) => learning.scheduled_secs,
+            NormalState::Review(ReviewState { scheduled_days, .. }) => scheduled_days * 86_400,
+        },
+        CardState::Filtered(filtered_state) => match filtered_state {
+            FilteredState::Preview(preview_state) => preview_state.scheduled_secs,
+            FilteredState::Rescheduling(rescheduling_state) => {
+                Self::get_interval_secs(CardState::Normal(rescheduling_state.original_state))
+            }
+        },
+    }
+}
+
+
+    
+    
+    fn _card_type_to_string(ctype: u32) -> String {
+        
+        
+        match ctype {
+            0 => "New".to_string(),
+            1 => "Learning".to_string(),
+            2 => "Review".to_string(),
+            3 => "Relearning".to_string(),
+            _ => "Unknown".to_string(),
+        }
+    }
+
+}
+
+fn apply_text_search(sql: &mut String, params: &mut Vec<Box<dyn rusqlite::ToSql>>, filter: &str) {
+    
+    sql.push_str(" AND (p.puzzle_id LIKE ? OR p.themes LIKE ? OR p.comment LIKE ? OR p.opening_tags LIKE ?)");
+    
+    let pattern = format!("%{}%", filter);
+    
+    
+    params.push(Box::new(pattern.clone())); // For puzzle_id
+    params.push(Box::new(pattern.clone())); // For themes
+    params.push(Box::new(pattern.clone())); // For comment
+    params.push(Box::new(pattern.clone())); // For opening_tags
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anki::collection::CollectionBuilder;
+    use anki::search::SortMode;
+    use crate::models::deck::DeckGradingPayload;
+    use crate::services::deck_service::DeckService;
+
+    const FEN: &str = "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4";
+
+    fn auto_graded_card(dir: &tempfile::TempDir, journal: &mut UndoJournal) -> (Collection, i64) {
+        let mut col = CollectionBuilder::new(dir.path().join("collection.ankichess")).build().unwrap();
+        PuzzleRepository::init_tables(col.storage.db()).unwrap();
+
+        let deck_id = DeckService::create_deck(&mut col, "Tactics").unwrap();
+        DeckService::set_deck_grading(&mut col, deck_id, DeckGradingPayload { auto_grade: true, target_seconds: 30 }).unwrap();
+
+        let payload = AddNotePayload {
+            deck_id,
+            fen: FEN.to_string(),
+            solution: "h5f7".to_string(),
+            comment: None,
+            rating: None,
+            themes: None,
+            game_url: None,
+            opening_tags: None,
+        };
+        let nid = NoteService::create_note(&mut col, journal, payload).unwrap();
+        let card_id = col.search_cards(&format!("nid:{}", nid), SortMode::NoOrder).unwrap()[0].0;
+        (col, card_id)
+    }
+
+    fn fast_solve(hints_used: u32) -> PuzzleAttempt {
+        PuzzleAttempt { milliseconds_taken: 5_000, wrong_attempts: 0, hints_used }
+    }
+
+    #[test]
+    fn fast_solves_without_hints_are_easy() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut journal = UndoJournal::default();
+        let (mut col, card_id) = auto_graded_card(&dir, &mut journal);
+
+        let rating = NoteService::answer_card(&mut col, &mut journal, card_id, None, fast_solve(0)).unwrap();
+        assert_eq!(rating, 4);
+    }
+
+    #[test]
+    fn hinted_solves_are_graded_down() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut journal = UndoJournal::default();
+        let (mut col, card_id) = auto_graded_card(&dir, &mut journal);
+
+        let rating = NoteService::answer_card(&mut col, &mut journal, card_id, None, fast_solve(1)).unwrap();
+        assert_eq!(rating, 2);
+        let rating = NoteService::answer_card(&mut col, &mut journal, card_id, None, fast_solve(2)).unwrap();
+        assert_eq!(rating, 1);
+
+        let stats = NoteService::get_review_stats(&mut col, card_id).unwrap();
+        assert_eq!(stats.reviews, 2);
+        assert_eq!(stats.hints_used, 3);
+        assert_eq!(stats.clean_solves, 0);
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anki::{collection::Collection, prelude::*, services::NotesService};
+use anki_proto::notes::UpdateNotesRequest;
+
+use crate::chess;
+use crate::error::AnkiChessError;
+use crate::models::puzzle::ChessPuzzle;
+use crate::repository::puzzle_repo::PuzzleRepository;
+use crate::shared::utils::{format_anki_sfld, to_proto_note_id};
+
+pub const CHESS_NOTETYPE_NAME: &str = "AnkiChess Puzzle";
+
+//bump whenever fields, templates or css change so existing collections get migrated
+const CHESS_NOTETYPE_VERSION: &str = "1";
+const META_NOTETYPE_ID: &str = "chess_notetype_id";
+const META_NOTETYPE_VERSION: &str = "chess_notetype_version";
+const META_SORT_FIELD_FORMAT: &str = "sort_field_format";
+
+pub const FIELD_PUZZLE: &str = "Puzzle";
+pub const FIELD_FEN: &str = "FEN";
+pub const FIELD_MOVES: &str = "Moves";
+pub const FIELD_POSITION: &str = "Position";
+pub const FIELD_SOLUTION: &str = "Solution";
+pub const FIELD_COMMENT: &str = "Comment";
+pub const FIELD_THEMES: &str = "Themes";
+pub const FIELD_RATING: &str = "Rating";
+pub const FIELD_GAME_URL: &str = "GameUrl";
+pub const FIELD_OPENING_TAGS: &str = "OpeningTags";
+
+const CHESS_FIELDS: [&str; 10] = [
+    FIELD_PUZZLE,
+    FIELD_FEN,
+    FIELD_MOVES,
+    FIELD_POSITION,
+    FIELD_SOLUTION,
+    FIELD_COMMENT,
+    FIELD_THEMES,
+    FIELD_RATING,
+    FIELD_GAME_URL,
+    FIELD_OPENING_TAGS,
+];
+
+const TEMPLATE_NAME: &str = "Puzzle";
+
+const FRONT_TEMPLATE: &str = r#"<div class="ankichess-board" data-fen="{{text:Position}}"></div>
+<div class="ankichess-turn"></div>
+<script>
+(function () {
+  var glyphs = { k: '♚', q: '♛', r: '♜', b: '♝', n: '♞', p: '♟' };
+  document.querySelectorAll('.ankichess-board').forEach(function (el) {
+    var parts = (el.getAttribute('data-fen') || '').trim().split(/\s+/);
+    var rows = (parts[0] || '').split('/');
+    if (rows.length !== 8) { el.textContent = parts.join(' '); return; }
+    var flip = parts[1] === 'b';
+    var squares = '', pieces = '';
+    rows.forEach(function (row, r) {
+      var f = 0;
+      row.split('').forEach(function (c) {
+        if (/[1-8]/.test(c)) { f += parseInt(c, 10); return; }
+        var x = flip ? 7 - f : f, y = flip ? 7 - r : r;
+        var white = c === c.toUpperCase();
+        pieces += '<text x="' + (x + 0.5) + '" y="' + (y + 0.55) + '" class="' + (white ? 'w' : 'b') + '">' + (glyphs[c.toLowerCase()] || '') + '</text>';
+        f += 1;
+      });
+    });
+    for (var r = 0; r < 8; r++) {
+      for (var f = 0; f < 8; f++) {
+        squares += '<rect x="' + f + '" y="' + r + '" width="1" height="1" class="' + ((r + f) % 2 ? 'dark' : 'light') + '"/>';
+      }
+    }
+    el.innerHTML = '<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 8 8">' + squares + pieces + '</svg>';
+    var turn = el.parentNode.querySelector('.ankichess-turn');
+    if (turn) { turn.textContent = flip ? 'Black to move' : 'White to move'; }
+  });
+})();
+</script>"#;
+
+const BACK_TEMPLATE: &str = r#"{{FrontSide}}
+<hr id="answer">
+<div class="ankichess-solution">{{Solution}}</div>
+{{#Comment}}<div class="ankichess-comment">{{Comment}}</div>{{/Comment}}
+{{#GameUrl}}<div class="ankichess-link"><a href="{{text:GameUrl}}">{{text:GameUrl}}</a></div>{{/GameUrl}}"#;
+
+const CSS: &str = r#".card { font-family: sans-serif; font-size: 18px; text-align: center; }
+.ankichess-board { width: min(90vw, 480px); margin: 0 auto; }
+.ankichess-board svg { width: 100%; display: block; }
+.ankichess-board rect.light { fill: #f0d9b5; }
+.ankichess-board rect.dark { fill: #b58863; }
+.ankichess-board text { font-size: 0.8px; text-anchor: middle; dominant-baseline: middle; }
+.ankichess-board text.w { fill: #fff; stroke: #000; stroke-width: 0.03px; }
+.ankichess-board text.b { fill: #000; }
+.ankichess-turn { margin-top: 8px; color: #888; }
+.ankichess-solution { font-weight: bold; }
+.ankichess-comment { margin-top: 8px; font-style: italic; }"#;
+
+pub struct NotetypeService;
+
+impl NotetypeService {
+    /// Returns the notetype used for chess notes, creating it on first use and
+    /// bringing fields, templates and css up to date on older collections.
+    /// The id is remembered in `app_chess_meta`, so renaming the notetype in Anki is harmless.
+    pub fn ensure_chess_notetype(col: &mut Collection) -> Result<Arc<Notetype>, AnkiChessError> {
+        let stored_id = PuzzleRepository::get_meta(col.storage.db(), META_NOTETYPE_ID)?
+            .and_then(|id| id.parse::<i64>().ok());
+
+        let existing = match stored_id {
+            Some(id) => col.get_notetype(NotetypeId(id))?,
+            None => None,
+        };
+        let existing = match existing {
+            Some(nt) => Some(nt),
+            None => col.get_notetype_by_name(CHESS_NOTETYPE_NAME)?,
+        };
+
+        let nt = match existing {
+            Some(nt) => {
+                let version = PuzzleRepository::get_meta(col.storage.db(), META_NOTETYPE_VERSION)?;
+                let missing_field = CHESS_FIELDS.iter().any(|name| !nt.fields.iter().any(|f| f.name == *name));
+
+                if version.as_deref() != Some(CHESS_NOTETYPE_VERSION) || missing_field {
+                    let mut updated = (*nt).clone();
+                    Self::apply_layout(&mut updated);
+                    col.update_notetype(&mut updated, false)?;
+                }
+                col.get_notetype(nt.id)?
+                    .ok_or_else(|| AnkiChessError::NotFound(format!("Notetype {} not found", nt.id.0)))?
+            }
+            None => {
+                let mut nt = Notetype {
+                    name: CHESS_NOTETYPE_NAME.to_string(),
+                    ..Default::default()
+                };
+                Self::apply_layout(&mut nt);
+                col.add_notetype(&mut nt, false)?;
+                col.get_notetype(nt.id)?
+                    .ok_or_else(|| AnkiChessError::NotFound(format!("Notetype {} not found", nt.id.0)))?
+            }
+        };
+
+        PuzzleRepository::set_meta(col.storage.db(), META_NOTETYPE_ID, &nt.id.0.to_string())?;
+        PuzzleRepository::set_meta(col.storage.db(), META_NOTETYPE_VERSION, CHESS_NOTETYPE_VERSION)?;
+        Ok(nt)
+    }
+
+    //adds missing fields and resets the first template and css, fields added by the user are kept
+    fn apply_layout(nt: &mut Notetype) {
+        for name in CHESS_FIELDS {
+            if !nt.fields.iter().any(|f| f.name == name) {
+                nt.add_field(name);
+            }
+        }
+
+        match nt.templates.first_mut() {
+            Some(template) => {
+                template.config.q_format = FRONT_TEMPLATE.to_string();
+                template.config.a_format = BACK_TEMPLATE.to_string();
+            }
+            None => nt.add_template(TEMPLATE_NAME, FRONT_TEMPLATE, BACK_TEMPLATE),
+        }
+        nt.config.css = CSS.to_string();
+    }
+
+    /// Computes the field contents of a chess note. Fields the notetype doesn't know
+    /// are left untouched, `sort_field` only replaces the Puzzle field when given.
+    pub fn field_values(
+        nt: &Notetype,
+        existing: &[String],
+        puzzle: &ChessPuzzle,
+        sort_field: Option<&str>,
+    ) -> Vec<String> {
+        let (position, solution) = Self::display_line(puzzle);
+
+        let mut values: HashMap<&str, String> = HashMap::from([
+            (FIELD_FEN, puzzle.fen.clone()),
+            (FIELD_MOVES, puzzle.moves.clone()),
+            (FIELD_POSITION, position),
+            (FIELD_SOLUTION, solution),
+            (FIELD_COMMENT, puzzle.comment.clone()),
+            (FIELD_THEMES, puzzle.themes.clone()),
+            (FIELD_RATING, if puzzle.rating > 0 { puzzle.rating.to_string() } else { String::new() }),
+            (FIELD_GAME_URL, puzzle.game_url.clone()),
+            (FIELD_OPENING_TAGS, puzzle.opening_tags.clone()),
+        ]);
+        if let Some(sfld) = sort_field {
+            values.insert(FIELD_PUZZLE, sfld.to_string());
+        }
+
+        nt.fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| match values.remove(field.name.as_str()) {
+                Some(value) => value,
+                None => existing.get(i).cloned().unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    /// Position shown to the player and the SAN solution from there. For Lichess
+    /// puzzles the first move is the opponent's, so the board starts after it.
+    fn display_line(puzzle: &ChessPuzzle) -> (String, String) {
+        let Ok((mut pos, moves)) = chess::parse_line(&puzzle.fen, &puzzle.moves) else {
+            return (puzzle.fen.clone(), puzzle.moves.clone());
+        };
+
+        let mut moves = moves.as_slice();
+        if puzzle.has_setup_move && moves.len() > 1 {
+            pos.play(&moves[0]);
+            moves = &moves[1..];
+        }
+
+        let display_fen = pos.to_fen();
+        let mut san = Vec::with_capacity(moves.len());
+        for mv in moves {
+            san.push(pos.san(mv));
+            pos.play(mv);
+        }
+        (display_fen, san.join(" "))
+    }
+
+    pub fn new_chess_note(nt: &Notetype, puzzle: &ChessPuzzle, sort_field: &str) -> Result<Note, AnkiChessError> {
+        let mut note = nt.new_note();
+        for (i, value) in Self::field_values(nt, &[], puzzle, Some(sort_field)).iter().enumerate() {
+            note.set_field(i, value)?;
+        }
+        Ok(note)
+    }
+
+    /// Rewrites the fields of all given chess notes in one update, keeping their sort field.
+    /// Notes without a linked puzzle or of another notetype are left alone. With
+    /// `skip_undo_entry` the update can't be undone and clears anki's redo stack.
+    pub fn refresh_notes(col: &mut Collection, nids: &[i64], skip_undo_entry: bool) -> Result<usize, AnkiChessError> {
+        let nt = Self::ensure_chess_notetype(col)?;
+        let puzzles = PuzzleRepository::get_batch_by_nids(col.storage.db(), nids)?;
+
+        let mut notes = Vec::with_capacity(puzzles.len());
+        for (nid, puzzle) in &puzzles {
+            let mut note = NotesService::get_note(col, to_proto_note_id(*nid))?;
+            if note.notetype_id != nt.id.0 {
+                continue;
+            }
+            let fields = Self::field_values(&nt, &note.fields, puzzle, None);
+            if fields != note.fields {
+                note.fields = fields;
+                notes.push(note);
+            }
+        }
+
+        let updated = notes.len();
+        if updated > 0 {
+            NotesService::update_notes(col, UpdateNotesRequest { notes, skip_undo_entry })?;
+        }
+        Ok(updated)
+    }
+
+    /// Moves linked notes that still use another notetype (e.g. `Basic` from older
+    /// versions) to the chess notetype and fills their fields. Scheduling is kept.
+    /// Notetypes with several card templates are left alone, the chess notetype has one
+    /// template and converting them would delete the other cards with their reviews.
+    pub fn migrate_linked_notes(col: &mut Collection) -> Result<usize, AnkiChessError> {
+        let nt = Self::ensure_chess_notetype(col)?;
+
+        let by_notetype = PuzzleRepository::get_linked_nids_not_of_notetype(col.storage.db(), nt.id.0)?;
+        let mut migrated = 0;
+
+        for (old_ntid, nids) in by_notetype {
+            let templates = col.get_notetype(NotetypeId(old_ntid))?.map_or(0, |old| old.templates.len());
+            if templates > 1 {
+                continue;
+            }
+
+            let mut info = col.notetype_change_info(NotetypeId(old_ntid), nt.id)?;
+            info.input.note_ids = nids.iter().map(|nid| NoteId(*nid)).collect();
+            col.change_notetype_of_notes(info.input)?;
+
+            let mut notes = Vec::with_capacity(nids.len());
+            for nid in &nids {
+                let Some(puzzle) = PuzzleRepository::get_by_nid(col.storage.db(), *nid)? else { continue };
+                let mut note = NotesService::get_note(col, to_proto_note_id(*nid))?;
+                note.fields = Self::field_values(&nt, &note.fields, &puzzle, Some(&format_anki_sfld(&puzzle.puzzle_id)));
+                notes.push(note);
+            }
+            NotesService::update_notes(col, UpdateNotesRequest { notes, skip_undo_entry: true })?;
+            migrated += nids.len();
+        }
+        Ok(migrated)
+    }
+
+    /// Sets the Puzzle field of every chess note to its bare puzzle id. Older versions added
+    /// the deck name, which went stale when cards changed deck. Only runs once per collection.
+    pub fn normalize_sort_fields(col: &mut Collection) -> Result<usize, AnkiChessError> {
+        if PuzzleRepository::get_meta(col.storage.db(), META_SORT_FIELD_FORMAT)?.is_some() {
+            return Ok(0);
+        }
+        let nt = Self::ensure_chess_notetype(col)?;
+        let Some(index) = nt.fields.iter().position(|f| f.name == FIELD_PUZZLE) else { return Ok(0) };
+
+        let mut notes = Vec::new();
+        for linked in PuzzleRepository::get_linked_puzzles(col.storage.db())? {
+            let mut note = NotesService::get_note(col, to_proto_note_id(linked.nid))?;
+            let sfld = format_anki_sfld(&linked.puzzle_id);
+            if note.notetype_id != nt.id.0 || note.fields.get(index) == Some(&sfld) {
+                continue;
+            }
+            if let Some(field) = note.fields.get_mut(index) {
+                *field = sfld;
+                notes.push(note);
+            }
+        }
+
+        let updated = notes.len();
+        if updated > 0 {
+            NotesService::update_notes(col, UpdateNotesRequest { notes, skip_undo_entry: true })?;
+        }
+        PuzzleRepository::set_meta(col.storage.db(), META_SORT_FIELD_FORMAT, "1")?;
+        Ok(updated)
+    }
+}
@@ -0,0 +1,133 @@
+use anki::collection::Collection;
+use serde::Serialize;
+
+use crate::error::AnkiChessError;
+use crate::models::card::PuzzleAttempt;
+use crate::models::puzzle::ChessPuzzle;
+use crate::repository::puzzle_repo::PuzzleRepository;
+
+//anki keeps a limited number of undo steps, older journal entries could never be reached
+const MAX_JOURNAL_STEPS: usize = 30;
+
+/// A write to the side tables made together with an undoable collection operation.
+#[derive(Debug, Clone)]
+pub enum SideTableChange {
+    PuzzlesAdded { puzzles: Vec<ChessPuzzle> },
+    LinksAdded { links: Vec<(i64, String)> },
+    LinksRemoved { links: Vec<(i64, String)> },
+    PuzzleEdited { nid: i64, before: PuzzleEdit, after: PuzzleEdit },
+    ReviewAdded { revlog_id: i64, cid: i64, rating: u8, attempt: PuzzleAttempt },
+}
+
+#[derive(Debug, Clone)]
+pub struct PuzzleEdit {
+    pub fen: String,
+    pub moves: String,
+    pub comment: String,
+}
+
+/// Side-table changes keyed by the anki undo step they belong to, so undoing or
+/// redoing that step can replay them.
+#[derive(Debug, Default)]
+pub struct UndoJournal {
+    undo: Vec<(usize, Vec<SideTableChange>)>,
+    redo: Vec<(usize, Vec<SideTableChange>)>,
+}
+
+impl UndoJournal {
+    /// Attaches `changes` to the collection's most recent undo step.
+    pub fn record(&mut self, col: &Collection, changes: Vec<SideTableChange>) {
+        let step = col.undo_status().last_step;
+        self.undo.push((step, changes));
+        if self.undo.len() > MAX_JOURNAL_STEPS {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UndoStatusPayload {
+    pub reverted: Option<String>,
+    pub undo_label: Option<String>,
+    pub redo_label: Option<String>,
+}
+
+pub struct UndoService;
+
+impl UndoService {
+    pub fn get_status(col: &mut Collection) -> UndoStatusPayload {
+        Self::status(col, None)
+    }
+
+    pub fn undo(col: &mut Collection, journal: &mut UndoJournal) -> Result<UndoStatusPayload, AnkiChessError> {
+        let out = col.undo()?.output;
+
+        if let Some(pos) = journal.undo.iter().rposition(|(step, _)| *step == out.counter) {
+            let (_, changes) = journal.undo.remove(pos);
+            Self::apply(col, &changes, true)?;
+            //the reverted step now sits on anki's redo stack under a new counter
+            journal.redo.push((col.undo_status().last_step, changes));
+        }
+
+        let description = out.undone_op.describe(&col.tr);
+        Ok(Self::status(col, Some(description)))
+    }
+
+    pub fn redo(col: &mut Collection, journal: &mut UndoJournal) -> Result<UndoStatusPayload, AnkiChessError> {
+        let out = col.redo()?.output;
+
+        if let Some(pos) = journal.redo.iter().rposition(|(step, _)| *step == out.counter) {
+            let (_, changes) = journal.redo.remove(pos);
+            Self::apply(col, &changes, false)?;
+            journal.undo.push((col.undo_status().last_step, changes));
+        }
+
+        let description = out.undone_op.describe(&col.tr);
+        Ok(Self::status(col, Some(description)))
+    }
+
+    fn apply(col: &mut Collection, changes: &[SideTableChange], undo: bool) -> Result<(), AnkiChessError> {
+        let conn = col.storage.db();
+        conn.execute("BEGIN TRANSACTION", [])?;
+
+        let ordered: Vec<&SideTableChange> = if undo { changes.iter().rev().collect() } else { changes.iter().collect() };
+        for change in ordered {
+            match (change, undo) {
+                (SideTableChange::PuzzlesAdded { puzzles }, true) => {
+                    let ids: Vec<String> = puzzles.iter().map(|p| p.puzzle_id.clone()).collect();
+                    PuzzleRepository::delete_unlinked_puzzles(conn, &ids)?
+                }
+                (SideTableChange::PuzzlesAdded { puzzles }, false) => PuzzleRepository::save_batch_puzzles(conn, puzzles)?,
+                (SideTableChange::LinksAdded { links }, false) | (SideTableChange::LinksRemoved { links }, true) => {
+                    PuzzleRepository::save_batch_links(conn, links)?
+                }
+                (SideTableChange::LinksAdded { links }, true) | (SideTableChange::LinksRemoved { links }, false) => {
+                    let nids: Vec<i64> = links.iter().map(|(nid, _)| *nid).collect();
+                    PuzzleRepository::delete_links(conn, &nids)?
+                }
+                (SideTableChange::PuzzleEdited { nid, before, after }, _) => {
+                    let edit = if undo { before } else { after };
+                    PuzzleRepository::update_fields_by_nid(conn, *nid, &edit.fen, &edit.moves, &edit.comment)?;
+                }
+                (SideTableChange::ReviewAdded { revlog_id, .. }, true) => PuzzleRepository::delete_review(conn, *revlog_id)?,
+                (SideTableChange::ReviewAdded { revlog_id, cid, rating, attempt }, false) => {
+                    PuzzleRepository::save_review(conn, *revlog_id, *cid, *rating, attempt)?
+                }
+            }
+        }
+
+        conn.execute("COMMIT", [])?;
+        Ok(())
+    }
+
+    fn status(col: &Collection, reverted: Option<String>) -> UndoStatusPayload {
+        let status = col.undo_status();
+        UndoStatusPayload {
+            reverted,
+            undo_label: status.undo.map(|op| op.describe(&col.tr)),
+            redo_label: status.redo.map(|op| op.describe(&col.tr)),
+        }
+    }
+}
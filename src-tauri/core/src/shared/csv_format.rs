@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+
+use csv::StringRecord;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CsvColumn {
+    PuzzleId,
+    Fen,
+    Moves,
+    Rating,
+    RatingDeviation,
+    Popularity,
+    NbPlays,
+    Themes,
+    GameUrl,
+    OpeningTags,
+    Comment,
+    HasSetupMove,
+    CardType,
+    DueInDays,
+    Interval,
+    EaseFactor,
+    Reps,
+    Lapses,
+}
+
+/// Columns written by the export, in order. The first ten match the Lichess puzzle dump.
+pub const PUZZLE_COLUMNS: [CsvColumn; 12] = [
+    CsvColumn::PuzzleId,
+    CsvColumn::Fen,
+    CsvColumn::Moves,
+    CsvColumn::Rating,
+    CsvColumn::RatingDeviation,
+    CsvColumn::Popularity,
+    CsvColumn::NbPlays,
+    CsvColumn::Themes,
+    CsvColumn::GameUrl,
+    CsvColumn::OpeningTags,
+    CsvColumn::Comment,
+    CsvColumn::HasSetupMove,
+];
+
+pub const SCHEDULING_COLUMNS: [CsvColumn; 6] = [
+    CsvColumn::CardType,
+    CsvColumn::DueInDays,
+    CsvColumn::Interval,
+    CsvColumn::EaseFactor,
+    CsvColumn::Reps,
+    CsvColumn::Lapses,
+];
+
+impl CsvColumn {
+    pub fn header(self) -> &'static str {
+        match self {
+            CsvColumn::PuzzleId => "PuzzleId",
+            CsvColumn::Fen => "FEN",
+            CsvColumn::Moves => "Moves",
+            CsvColumn::Rating => "Rating",
+            CsvColumn::RatingDeviation => "RatingDeviation",
+            CsvColumn::Popularity => "Popularity",
+            CsvColumn::NbPlays => "NbPlays",
+            CsvColumn::Themes => "Themes",
+            CsvColumn::GameUrl => "GameUrl",
+            CsvColumn::OpeningTags => "OpeningTags",
+            CsvColumn::Comment => "Comment",
+            CsvColumn::HasSetupMove => "HasSetupMove",
+            CsvColumn::CardType => "CardType",
+            CsvColumn::DueInDays => "DueInDays",
+            CsvColumn::Interval => "Interval",
+            CsvColumn::EaseFactor => "EaseFactor",
+            CsvColumn::Reps => "Reps",
+            CsvColumn::Lapses => "Lapses",
+        }
+    }
+
+    //case and punctuation insensitive, so "puzzle_id" or "Game URL" are recognised too
+    fn from_header(name: &str) -> Option<CsvColumn> {
+        let key: String = name
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        let column = match key.as_str() {
+            "puzzleid" | "id" => CsvColumn::PuzzleId,
+            "fen" => CsvColumn::Fen,
+            "moves" | "solution" => CsvColumn::Moves,
+            "rating" => CsvColumn::Rating,
+            "ratingdeviation" => CsvColumn::RatingDeviation,
+            "popularity" => CsvColumn::Popularity,
+            "nbplays" => CsvColumn::NbPlays,
+            "themes" => CsvColumn::Themes,
+            "gameurl" => CsvColumn::GameUrl,
+            "openingtags" => CsvColumn::OpeningTags,
+            "comment" => CsvColumn::Comment,
+            "hassetupmove" => CsvColumn::HasSetupMove,
+            "cardtype" => CsvColumn::CardType,
+            "dueindays" => CsvColumn::DueInDays,
+            "interval" => CsvColumn::Interval,
+            "easefactor" => CsvColumn::EaseFactor,
+            "reps" => CsvColumn::Reps,
+            "lapses" => CsvColumn::Lapses,
+            _ => return None,
+        };
+        Some(column)
+    }
+}
+
+/// Maps columns to their position in a CSV record.
+pub struct CsvLayout {
+    columns: HashMap<CsvColumn, usize>,
+    pub has_header: bool,
+}
+
+impl CsvLayout {
+    /// Uses the first record as header when it names both a FEN and a moves column,
+    /// otherwise falls back to the headerless `fen,moves,comment,id` layout.
+    pub fn detect(first_record: &StringRecord) -> CsvLayout {
+        let mut columns = HashMap::new();
+        for (i, field) in first_record.iter().enumerate() {
+            if let Some(column) = CsvColumn::from_header(field.trim()) {
+                columns.entry(column).or_insert(i);
+            }
+        }
+
+        if columns.contains_key(&CsvColumn::Fen) && columns.contains_key(&CsvColumn::Moves) {
+            return CsvLayout { columns, has_header: true };
+        }
+
+        let legacy = [CsvColumn::Fen, CsvColumn::Moves, CsvColumn::Comment, CsvColumn::PuzzleId];
+        CsvLayout {
+            columns: legacy.iter().enumerate().map(|(i, c)| (*c, i)).collect(),
+            has_header: false,
+        }
+    }
+
+    pub fn has(&self, column: CsvColumn) -> bool {
+        self.columns.contains_key(&column)
+    }
+
+    pub fn get<'a>(&self, record: &'a StringRecord, column: CsvColumn) -> Option<&'a str> {
+        self.columns.get(&column).and_then(|i| record.get(*i)).map(|v| v.trim())
+    }
+
+    /// Lichess dumps store the opponent's last move as first move of the solution.
+    pub fn is_lichess_format(&self) -> bool {
+        self.has_header
+            && self.has(CsvColumn::PuzzleId)
+            && self.has(CsvColumn::GameUrl)
+            && !self.has(CsvColumn::HasSetupMove)
+    }
+}
@@ -0,0 +1,348 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+use reqwest::header::{CONTENT_RANGE, IF_RANGE, RANGE};
+use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
+
+use crate::error::AnkiChessError;
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct DownloadOptions {
+    /// ETag or Last-Modified of the file; a part file from an older version is then restarted.
+    pub if_range: Option<String>,
+    pub expected_size: Option<u64>,
+    pub expected_sha256: Option<String>,
+    pub max_attempts: u32,
+    pub retry_delay: Duration,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            if_range: None,
+            expected_size: None,
+            expected_sha256: None,
+            max_attempts: 5,
+            retry_delay: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Where partial data for `dest` is kept between attempts.
+pub fn part_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    dest.with_file_name(name)
+}
+
+/// Downloads `url` into `dest`. Data goes to `<dest>.part` first and a broken transfer is
+/// resumed with a range request, both on retry and on the next call. The file is only
+/// moved to `dest` once its size and, if given, its sha256 check out. `on_retry` hears
+/// about every failed attempt that is retried. Returns the sha256 of the finished file.
+pub fn download_resumable(
+    url: &str,
+    dest: &Path,
+    options: &DownloadOptions,
+    cancel: &AtomicBool,
+    mut on_progress: impl FnMut(u64, Option<u64>),
+    mut on_retry: impl FnMut(u32, &AnkiChessError),
+) -> Result<String, AnkiChessError> {
+    let part = part_path(dest);
+    let client = Client::builder()
+        .connect_timeout(Duration::from_secs(30))
+        .timeout(None)
+        .build()?;
+
+    let attempts = options.max_attempts.max(1);
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        match fetch_into_part(&client, url, &part, options.if_range.as_deref(), cancel, &mut on_progress) {
+            Ok(()) => break,
+            Err(e @ AnkiChessError::Cancelled(_)) => return Err(e),
+            Err(e) if attempt >= attempts => return Err(e),
+            Err(e) => {
+                on_retry(attempt, &e);
+                std::thread::sleep(options.retry_delay);
+            }
+        }
+    }
+
+    let size = fs::metadata(&part)?.len();
+    if let Some(expected) = options.expected_size {
+        if size != expected {
+            let _ = fs::remove_file(&part);
+            return Err(AnkiChessError::IoError(format!(
+                "Downloaded file has {} bytes, expected {}", size, expected
+            )));
+        }
+    }
+
+    let sha256 = sha256_file(&part)?;
+    if let Some(expected) = &options.expected_sha256 {
+        if !sha256.eq_ignore_ascii_case(expected.trim()) {
+            let _ = fs::remove_file(&part);
+            return Err(AnkiChessError::IoError(format!(
+                "Checksum mismatch: got {}, expected {}", sha256, expected
+            )));
+        }
+    }
+
+    fs::rename(&part, dest)?;
+    Ok(sha256)
+}
+
+fn fetch_into_part(
+    client: &Client,
+    url: &str,
+    part: &Path,
+    if_range: Option<&str>,
+    cancel: &AtomicBool,
+    on_progress: &mut impl FnMut(u64, Option<u64>),
+) -> Result<(), AnkiChessError> {
+    let offset = fs::metadata(part).map(|m| m.len()).unwrap_or(0);
+
+    let mut request = client.get(url);
+    if offset > 0 {
+        request = request.header(RANGE, format!("bytes={}-", offset));
+        if let Some(validator) = if_range {
+            request = request.header(IF_RANGE, validator);
+        }
+    }
+    let mut response = request.send()?;
+
+    let (mut file, mut downloaded, total) = match response.status() {
+        StatusCode::PARTIAL_CONTENT => {
+            let total = content_range_total(&response)
+                .or_else(|| response.content_length().map(|len| len + offset));
+            (OpenOptions::new().append(true).open(part)?, offset, total)
+        }
+        //the part file already holds everything the server has
+        StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => return Ok(()),
+        status if status.is_success() => (File::create(part)?, 0, response.content_length()),
+        status => return Err(AnkiChessError::HttpError(format!("HTTP Error: {}", status))),
+    };
+
+    let mut buffer = vec![0; BUFFER_SIZE];
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(AnkiChessError::Cancelled("Download cancelled".into()));
+        }
+
+        let bytes_read = match response.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                file.flush()?;
+                return Err(e.into());
+            }
+        };
+        file.write_all(&buffer[..bytes_read])?;
+        downloaded += bytes_read as u64;
+        on_progress(downloaded, total);
+    }
+    file.flush()?;
+
+    match total {
+        Some(total) if downloaded < total => Err(AnkiChessError::IoError(format!(
+            "Download truncated at {} of {} bytes", downloaded, total
+        ))),
+        _ => Ok(()),
+    }
+}
+
+//"bytes 100-199/200" -> 200
+fn content_range_total(response: &reqwest::blocking::Response) -> Option<u64> {
+    let value = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    value.rsplit('/').next()?.trim().parse().ok()
+}
+
+pub fn sha256_file(path: &Path) -> Result<String, AnkiChessError> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; BUFFER_SIZE];
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// Decodes a whole `.zst` file without keeping the output, so a truncated or corrupt
+/// archive is rejected before indexing starts.
+pub fn check_zstd_archive(path: &Path) -> Result<(), AnkiChessError> {
+    let file = File::open(path)?;
+    zstd::stream::copy_decode(file, io::sink()).map_err(|e| {
+        AnkiChessError::InvalidInput(format!("{} is truncated or corrupt: {}", path.display(), e))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    //minimal http stand-in: honours "Range: bytes=N-" and can cut the first response short
+    fn serve(payload: Vec<u8>, cut_first_at: Option<usize>) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/lichess_db_puzzle.csv.zst", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut start = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some(range) = line.to_ascii_lowercase().strip_prefix("range: bytes=") {
+                        start = range.trim().trim_end_matches('-').parse().unwrap();
+                    }
+                }
+
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                let body = &payload[start..];
+                let head = if start > 0 {
+                    format!(
+                        "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\nConnection: close\r\n\r\n",
+                        body.len(), start, payload.len() - 1, payload.len()
+                    )
+                } else {
+                    format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len())
+                };
+                stream.write_all(head.as_bytes()).unwrap();
+                let body = match cut_first_at {
+                    Some(cut) if n == 0 => &body[..cut],
+                    _ => body,
+                };
+                let _ = stream.write_all(body);
+            }
+        });
+
+        (url, requests)
+    }
+
+    fn payload() -> Vec<u8> {
+        (0..300_000u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn fast_retry() -> DownloadOptions {
+        DownloadOptions { retry_delay: Duration::from_millis(10), ..Default::default() }
+    }
+
+    #[test]
+    fn resumes_a_truncated_download() {
+        let data = payload();
+        let (url, requests) = serve(data.clone(), Some(100_000));
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("dump.zst");
+
+        let mut retries = Vec::new();
+        download_resumable(&url, &dest, &fast_retry(), &AtomicBool::new(false), |_, _| {}, |attempt, _| retries.push(attempt))
+            .unwrap();
+
+        assert_eq!(fs::read(&dest).unwrap(), data);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        assert_eq!(retries, [1]);
+        assert!(!part_path(&dest).exists());
+    }
+
+    #[test]
+    fn continues_from_an_existing_part_file() {
+        let data = payload();
+        let (url, requests) = serve(data.clone(), None);
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("dump.zst");
+        fs::write(part_path(&dest), &data[..1234]).unwrap();
+
+        let mut first_progress = None;
+        download_resumable(&url, &dest, &fast_retry(), &AtomicBool::new(false), |done, total| {
+            first_progress.get_or_insert((done, total));
+        }, |_, _| {})
+        .unwrap();
+
+        assert_eq!(fs::read(&dest).unwrap(), data);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        assert_eq!(first_progress.unwrap().1, Some(data.len() as u64));
+    }
+
+    #[test]
+    fn rejects_a_checksum_mismatch() {
+        let (url, _) = serve(payload(), None);
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("dump.zst");
+        let options = DownloadOptions { expected_sha256: Some("00".repeat(32)), ..fast_retry() };
+
+        let result = download_resumable(&url, &dest, &options, &AtomicBool::new(false), |_, _| {}, |_, _| {});
+
+        assert!(result.is_err());
+        assert!(!dest.exists());
+        assert!(!part_path(&dest).exists());
+    }
+
+    #[test]
+    fn verifies_the_expected_checksum() {
+        let data = payload();
+        let (url, _) = serve(data.clone(), None);
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("dump.zst");
+        let reference = dir.path().join("reference");
+        fs::write(&reference, &data).unwrap();
+        let options = DownloadOptions {
+            expected_sha256: Some(sha256_file(&reference).unwrap()),
+            expected_size: Some(data.len() as u64),
+            ..fast_retry()
+        };
+
+        download_resumable(&url, &dest, &options, &AtomicBool::new(false), |_, _| {}, |_, _| {}).unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), data);
+    }
+
+    #[test]
+    fn stops_when_cancelled() {
+        let (url, _) = serve(payload(), None);
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("dump.zst");
+
+        let result = download_resumable(&url, &dest, &fast_retry(), &AtomicBool::new(true), |_, _| {}, |_, _| {});
+
+        assert!(matches!(result, Err(AnkiChessError::Cancelled(_))));
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn detects_a_truncated_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dump.zst");
+        let compressed = zstd::encode_all(&payload()[..], 3).unwrap();
+
+        fs::write(&path, &compressed).unwrap();
+        check_zstd_archive(&path).unwrap();
+
+        fs::write(&path, &compressed[..compressed.len() / 2]).unwrap();
+        assert!(check_zstd_archive(&path).is_err());
+    }
+}
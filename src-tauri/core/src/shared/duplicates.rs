@@ -0,0 +1,157 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::chess;
+use crate::models::import_report::SkipReason;
+use crate::models::puzzle::{ChessPuzzle, DuplicatePolicy, LinkedPuzzle};
+
+//notes added by the running import don't exist yet
+const PENDING_NID: i64 = 0;
+
+#[derive(Debug, PartialEq)]
+pub enum DuplicateAction {
+    Import,
+    Skip(SkipReason, String),
+    /// Add a note to the deck for this puzzle of the collection.
+    Link(String),
+    /// Move these notes to the deck.
+    Move(Vec<i64>),
+}
+
+/// The chess notes of a collection by puzzle id and by position, to decide what an import
+/// does with each incoming puzzle. Every decision is remembered, so a puzzle that appears
+/// twice in the same import is only imported once.
+pub struct DuplicateIndex {
+    deck_id: i64,
+    //puzzle id -> (nid, deck id) of each of its notes
+    notes: HashMap<String, Vec<(i64, i64)>>,
+    //position key -> first puzzle id with that position
+    positions: HashMap<String, String>,
+}
+
+impl DuplicateIndex {
+    pub fn new(deck_id: i64, linked: Vec<LinkedPuzzle>) -> Self {
+        let mut index = Self { deck_id, notes: HashMap::new(), positions: HashMap::new() };
+        for puzzle in linked {
+            if let Some(key) = puzzle.fen_key {
+                index.positions.entry(key).or_insert_with(|| puzzle.puzzle_id.clone());
+            }
+            index.notes.entry(puzzle.puzzle_id).or_default().push((puzzle.nid, puzzle.deck_id));
+        }
+        index
+    }
+
+    pub fn ids_in_deck(&self) -> HashSet<String> {
+        self.notes
+            .iter()
+            .filter(|(_, notes)| notes.iter().any(|(_, deck_id)| *deck_id == self.deck_id))
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    /// Ids that will be skipped whatever the position: those of the deck, and with the
+    /// skip policy those of the whole collection.
+    pub fn excluded_ids(&self, policy: DuplicatePolicy) -> HashSet<String> {
+        match policy {
+            DuplicatePolicy::Skip => self.notes.keys().cloned().collect(),
+            DuplicatePolicy::Link | DuplicatePolicy::Move => self.ids_in_deck(),
+        }
+    }
+
+    pub fn resolve(&mut self, puzzle: &ChessPuzzle, policy: DuplicatePolicy) -> DuplicateAction {
+        let key = chess::fen_key(&puzzle.fen, &puzzle.moves, puzzle.has_setup_move).ok();
+        let (existing_id, reason) = if self.notes.contains_key(&puzzle.puzzle_id) {
+            (Some(puzzle.puzzle_id.clone()), SkipReason::DuplicateId)
+        } else {
+            (key.as_ref().and_then(|key| self.positions.get(key)).cloned(), SkipReason::DuplicatePosition)
+        };
+
+        let Some(existing_id) = existing_id else {
+            self.notes.entry(puzzle.puzzle_id.clone()).or_default().push((PENDING_NID, self.deck_id));
+            if let Some(key) = key {
+                self.positions.insert(key, puzzle.puzzle_id.clone());
+            }
+            return DuplicateAction::Import;
+        };
+
+        let deck_id = self.deck_id;
+        let notes = self.notes.entry(existing_id.clone()).or_default();
+        let detail = |place: &str| match reason {
+            SkipReason::DuplicatePosition => format!("Same position as puzzle {}, {}", existing_id, place),
+            _ => format!("Already {}", place),
+        };
+
+        let mut in_deck = notes.iter().filter(|(_, did)| *did == deck_id).peekable();
+        if in_deck.peek().is_some() {
+            let place = if in_deck.all(|(nid, _)| *nid == PENDING_NID) { "earlier in this import" } else { "in the deck" };
+            return DuplicateAction::Skip(reason, detail(place));
+        }
+
+        match policy {
+            DuplicatePolicy::Skip => DuplicateAction::Skip(reason, detail("in another deck")),
+            DuplicatePolicy::Link => {
+                notes.push((PENDING_NID, deck_id));
+                DuplicateAction::Link(existing_id)
+            }
+            DuplicatePolicy::Move => {
+                for note in notes.iter_mut() {
+                    note.1 = deck_id;
+                }
+                DuplicateAction::Move(notes.iter().map(|(nid, _)| *nid).collect())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET_DECK: i64 = 1;
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    const SCHOLAR: &str = "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4";
+
+    fn puzzle(id: &str, fen: &str) -> ChessPuzzle {
+        ChessPuzzle { puzzle_id: id.to_string(), fen: fen.to_string(), ..Default::default() }
+    }
+
+    //"deck" has a note in the target deck, "shared" has notes in two other decks
+    fn index() -> DuplicateIndex {
+        let linked = |nid: i64, deck_id: i64, id: &str, fen: &str| LinkedPuzzle {
+            nid,
+            deck_id,
+            puzzle_id: id.to_string(),
+            fen_key: chess::fen_key(fen, "", false).ok(),
+        };
+        DuplicateIndex::new(
+            TARGET_DECK,
+            vec![linked(10, TARGET_DECK, "deck", START), linked(20, 2, "shared", SCHOLAR), linked(21, 3, "shared", SCHOLAR)],
+        )
+    }
+
+    #[test]
+    fn resolve_follows_the_policy_and_remembers_its_decisions() {
+        let mut duplicates = index();
+
+        assert_eq!(
+            duplicates.resolve(&puzzle("deck", START), DuplicatePolicy::Link),
+            DuplicateAction::Skip(SkipReason::DuplicateId, "Already in the deck".to_string())
+        );
+        assert_eq!(
+            duplicates.resolve(&puzzle("copy", SCHOLAR), DuplicatePolicy::Skip),
+            DuplicateAction::Skip(SkipReason::DuplicatePosition, "Same position as puzzle shared, in another deck".to_string())
+        );
+
+        assert_eq!(duplicates.resolve(&puzzle("shared", SCHOLAR), DuplicatePolicy::Link), DuplicateAction::Link("shared".to_string()));
+        assert_eq!(
+            duplicates.resolve(&puzzle("shared", SCHOLAR), DuplicatePolicy::Link),
+            DuplicateAction::Skip(SkipReason::DuplicateId, "Already earlier in this import".to_string())
+        );
+
+        let mut duplicates = index();
+        assert_eq!(duplicates.resolve(&puzzle("shared", SCHOLAR), DuplicatePolicy::Move), DuplicateAction::Move(vec![20, 21]));
+        assert_eq!(
+            duplicates.resolve(&puzzle("copy", SCHOLAR), DuplicatePolicy::Move),
+            DuplicateAction::Skip(SkipReason::DuplicatePosition, "Same position as puzzle shared, in the deck".to_string())
+        );
+    }
+}
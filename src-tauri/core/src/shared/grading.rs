@@ -0,0 +1,26 @@
+use crate::models::card::PuzzleAttempt;
+use crate::models::deck::DeckGradingPayload;
+
+/// Derives the 1-4 Anki rating from how a puzzle went. Any wrong move is Again,
+/// a hint or going over the target time is Hard (two or more hints are Again),
+/// solving within half the target time is Easy and anything else is Good.
+pub fn derive_rating(attempt: &PuzzleAttempt, grading: &DeckGradingPayload) -> u8 {
+    if attempt.wrong_attempts > 0 || attempt.hints_used > 1 {
+        return 1;
+    }
+    if attempt.hints_used == 1 {
+        return 2;
+    }
+
+    let target_ms = grading.target_seconds as u64 * 1000;
+    let taken_ms = attempt.milliseconds_taken as u64;
+    if target_ms == 0 {
+        3
+    } else if taken_ms > target_ms {
+        2
+    } else if taken_ms * 2 <= target_ms {
+        4
+    } else {
+        3
+    }
+}
@@ -0,0 +1,6 @@
+pub mod csv_format;
+pub mod download;
+pub mod duplicates;
+pub mod grading;
+pub mod sampling;
+pub mod utils;
\ No newline at end of file
@@ -0,0 +1,139 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap, HashSet};
+
+/// Position of a puzzle in the shuffled order for `seed`. It only depends on the id, so the
+/// same seed picks the same puzzles whatever order the database returns them in.
+pub fn sample_key(id: &str, seed: u64) -> u64 {
+    //fnv-1a, then splitmix64 to spread the bits
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in id.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+
+    let mut z = hash ^ seed.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+struct Candidate<T> {
+    key: u64,
+    id: String,
+    item: T,
+}
+
+impl<T> PartialEq for Candidate<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key && self.id == other.id
+    }
+}
+
+impl<T> Eq for Candidate<T> {}
+
+impl<T> PartialOrd for Candidate<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Candidate<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key).then_with(|| self.id.cmp(&other.id))
+    }
+}
+
+/// Picks `limit` items spread evenly over strata (rating buckets, themes, or a single
+/// stratum for a plain random sample). Each stratum only keeps its `limit` lowest keys,
+/// so memory stays bounded however many rows are offered.
+pub struct StratifiedSampler<K: Ord, T> {
+    limit: usize,
+    seed: u64,
+    strata: BTreeMap<K, BinaryHeap<Candidate<T>>>,
+}
+
+impl<K: Ord, T> StratifiedSampler<K, T> {
+    pub fn new(limit: usize, seed: u64) -> Self {
+        Self { limit, seed, strata: BTreeMap::new() }
+    }
+
+    pub fn offer(&mut self, stratum: K, id: &str, item: T) {
+        if self.limit == 0 {
+            return;
+        }
+        let candidate = Candidate { key: sample_key(id, self.seed), id: id.to_string(), item };
+        let heap = self.strata.entry(stratum).or_default();
+        if heap.len() < self.limit {
+            heap.push(candidate);
+        } else if heap.peek().is_some_and(|largest| candidate < *largest) {
+            heap.pop();
+            heap.push(candidate);
+        }
+    }
+
+    /// Takes one item per stratum in turn until `limit` is reached, so a stratum with few
+    /// matches leaves its share to the others. An id offered to several strata is used once.
+    pub fn into_sample(self) -> Vec<T> {
+        let mut queues: Vec<std::vec::IntoIter<Candidate<T>>> = self
+            .strata
+            .into_values()
+            .map(|heap| heap.into_sorted_vec().into_iter())
+            .collect();
+
+        let mut taken = HashSet::new();
+        let mut sample = Vec::new();
+        while sample.len() < self.limit && !queues.is_empty() {
+            queues.retain_mut(|queue| {
+                if sample.len() >= self.limit {
+                    return true;
+                }
+                for candidate in queue.by_ref() {
+                    if taken.insert(candidate.id) {
+                        sample.push(candidate.item);
+                        return true;
+                    }
+                }
+                false
+            });
+        }
+        sample
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(ids: impl Iterator<Item = String>, limit: usize, seed: u64) -> Vec<String> {
+        let mut sampler = StratifiedSampler::new(limit, seed);
+        for id in ids {
+            sampler.offer((), &id, id.clone());
+        }
+        let mut picked = sampler.into_sample();
+        picked.sort();
+        picked
+    }
+
+    #[test]
+    fn seed_picks_the_same_puzzles_in_any_order_and_strata_share_the_limit() {
+        let ids: Vec<String> = (0..10_000).map(|i| format!("p{:05}", i)).collect();
+
+        let forward = sample(ids.iter().cloned(), 50, 7);
+        assert_eq!(forward.len(), 50);
+        assert_eq!(forward, sample(ids.iter().rev().cloned(), 50, 7));
+        assert_ne!(forward, sample(ids.iter().cloned(), 50, 8));
+        assert_ne!(forward, ids[..50].to_vec());
+
+        //bucket 0 only has 3 matches, the others take over its share
+        let mut sampler = StratifiedSampler::new(20, 7);
+        for (i, id) in ids.iter().enumerate() {
+            let bucket = if i < 3 { 0 } else { 1 + i % 4 };
+            sampler.offer(bucket, id, bucket);
+        }
+        let mut per_bucket = [0; 5];
+        for bucket in sampler.into_sample() {
+            per_bucket[bucket] += 1;
+        }
+        assert_eq!(per_bucket, [3, 5, 4, 4, 4]);
+    }
+}
@@ -7,12 +7,13 @@ pub fn to_proto_card_id(card_id: i64) -> ProtoCardId {
     ProtoCardId { cid: card_id }
 }
 
-pub fn _to_proto_note_id(note_id: i64) -> ProtoNoteId {
+pub fn to_proto_note_id(note_id: i64) -> ProtoNoteId {
     ProtoNoteId { nid: note_id }
 }
 
-pub fn format_anki_sfld(canonical_id: &str, deck_name: &str) -> String {
-    format!("{} ({})", canonical_id, deck_name)
+//the deck is not part of it, a note keeps its sort field when its cards change deck
+pub fn format_anki_sfld(canonical_id: &str) -> String {
+    canonical_id.to_string()
 }
 
 
@@ -0,0 +1,56 @@
+//! Imports into a throwaway collection, the way scripts use the core without the app.
+
+use std::sync::{Arc, Mutex};
+
+use anki::collection::Collection;
+use ankichess_core::config::CoreConfig;
+use ankichess_core::models::import_report::{ImportReport, SkipReason};
+use ankichess_core::models::job::JobKind;
+use ankichess_core::models::puzzle::{CsvImportPayload, DuplicatePolicy};
+use ankichess_core::services::deck_service::DeckService;
+use ankichess_core::services::import_service::ImportService;
+use ankichess_core::services::job_service::JobManager;
+use ankichess_core::services::undo_service::UndoJournal;
+
+const CSV: &str = "\
+r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4,h5f7,Scholar's mate,scholar
+not a fen,e2e4,,broken
+";
+
+fn import_csv(col: &Arc<Mutex<Collection>>, deck_id: i64, duplicates: DuplicatePolicy) -> ImportReport {
+    let jobs = Arc::new(JobManager::default());
+    let job = jobs.start(JobKind::Import, "CSV import");
+    let payload = CsvImportPayload { deck_id, csv_content: CSV.to_string(), duplicates };
+    ImportService::import_from_csv(col, &Mutex::new(UndoJournal::default()), payload, &job).unwrap()
+}
+
+#[test]
+fn csv_import_skips_bad_rows_and_duplicates() {
+    let dir = tempfile::tempdir().unwrap();
+    let col = Arc::new(Mutex::new(CoreConfig::new(dir.path()).open_collection().unwrap().0));
+    let deck_id = DeckService::create_deck(&mut col.lock().unwrap(), "Tactics").unwrap();
+
+    let first = import_csv(&col, deck_id, DuplicatePolicy::Skip);
+    assert_eq!(first.imported, 1);
+    assert_eq!(first.skipped, 1);
+    assert_eq!(first.skipped_rows[0].line, Some(2));
+    assert_eq!(first.skipped_rows[0].reason, SkipReason::MalformedFen);
+
+    let second = import_csv(&col, deck_id, DuplicatePolicy::Skip);
+    assert_eq!(second.imported, 0);
+    assert_eq!(second.skipped, 2);
+    assert!(second.skipped_rows.iter().any(|row| row.reason == SkipReason::DuplicateId && row.line == Some(1)));
+}
+
+#[test]
+fn csv_import_links_puzzles_of_another_deck() {
+    let dir = tempfile::tempdir().unwrap();
+    let col = Arc::new(Mutex::new(CoreConfig::new(dir.path()).open_collection().unwrap().0));
+    let tactics = DeckService::create_deck(&mut col.lock().unwrap(), "Tactics").unwrap();
+    let review = DeckService::create_deck(&mut col.lock().unwrap(), "Review").unwrap();
+
+    import_csv(&col, tactics, DuplicatePolicy::Skip);
+    let linked = import_csv(&col, review, DuplicatePolicy::Link);
+    assert_eq!(linked.imported, 0);
+    assert_eq!(linked.linked, 1);
+}
@@ -0,0 +1,168 @@
+//! Upgrades the chess tables of collections written by earlier versions of ankichess.
+
+use ankichess_core::models::import_report::ImportReport;
+use ankichess_core::repository::migrations::{SchemaMigrations, META_SCHEMA_VERSION, SCHEMA_VERSION};
+use ankichess_core::repository::puzzle_repo::PuzzleRepository;
+use rusqlite::Connection;
+
+//the tables as each schema version left them, before the version was stored
+const SCHEMA_1: &str = "
+    CREATE TABLE app_chess_puzzles (
+        puzzle_id TEXT PRIMARY KEY,
+        fen TEXT NOT NULL,
+        moves TEXT NOT NULL,
+        rating INTEGER,
+        rating_deviation INTEGER,
+        popularity INTEGER,
+        nb_plays INTEGER,
+        themes TEXT,
+        game_url TEXT,
+        opening_tags TEXT,
+        comment TEXT,
+        has_setup_move INTEGER DEFAULT 0
+    );
+    CREATE TABLE app_chess_note_links (
+        nid INTEGER PRIMARY KEY,
+        puzzle_id TEXT NOT NULL,
+        FOREIGN KEY(puzzle_id) REFERENCES app_chess_puzzles(puzzle_id)
+    );
+    CREATE INDEX idx_link_puzzle_id ON app_chess_note_links(puzzle_id);
+    INSERT INTO app_chess_puzzles VALUES ('00sHx', 'q3k1nr/1pp1nQpp/3p4/1P2p3/4P3/B1PP1b2/B5PP/5K2 b k - 0 17',
+        'e8d7 a2e6 d7d8 f7f8', 1760, 80, 83, 72, 'mate mateIn2 middlegame short', 'https://lichess.org/yyznGmXs/black#34',
+        'Italian_Game Italian_Game_Classical_Variation', '', 1);
+    INSERT INTO app_chess_note_links (nid, puzzle_id) VALUES (1700000000000, '00sHx');";
+
+//the meta table came with the chess notetype, then the review stats
+const SCHEMA_2: &str = "
+    CREATE TABLE app_chess_meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE app_chess_reviews (
+        revlog_id INTEGER PRIMARY KEY,
+        cid INTEGER NOT NULL,
+        rating INTEGER NOT NULL,
+        ms_taken INTEGER NOT NULL DEFAULT 0,
+        wrong_attempts INTEGER NOT NULL DEFAULT 0,
+        hints_used INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX idx_reviews_cid ON app_chess_reviews(cid);
+    INSERT INTO app_chess_reviews (revlog_id, cid, rating, ms_taken) VALUES (1700000000001, 1700000000002, 3, 12000);";
+
+const SCHEMA_3: &str = "
+    CREATE TABLE app_chess_deck_grading (
+        deck_id INTEGER PRIMARY KEY,
+        auto_grade INTEGER NOT NULL DEFAULT 0,
+        target_seconds INTEGER NOT NULL DEFAULT 0
+    );
+    INSERT INTO app_chess_deck_grading (deck_id, auto_grade, target_seconds) VALUES (1, 1, 30);";
+
+const SCHEMA_4: &str = "
+    CREATE TABLE app_chess_import_reports (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        deck_id INTEGER NOT NULL,
+        source TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        processed INTEGER NOT NULL,
+        imported INTEGER NOT NULL,
+        skipped INTEGER NOT NULL
+    );
+    CREATE TABLE app_chess_import_skips (
+        report_id INTEGER NOT NULL,
+        line INTEGER,
+        reason TEXT NOT NULL,
+        puzzle_id TEXT,
+        detail TEXT NOT NULL
+    );
+    CREATE INDEX idx_import_skips_report ON app_chess_import_skips(report_id);
+    INSERT INTO app_chess_import_reports (deck_id, source, created_at, processed, imported, skipped)
+        VALUES (1, 'CSV import', 1700000000, 2, 1, 1);
+    INSERT INTO app_chess_import_skips (report_id, line, reason, detail) VALUES (1, 2, 'malformed_fen', 'not a fen');";
+
+const FIXTURES: [&str; 4] = [SCHEMA_1, SCHEMA_2, SCHEMA_3, SCHEMA_4];
+
+fn fixture(version: usize) -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    for schema in &FIXTURES[..version] {
+        conn.execute_batch(schema).unwrap();
+    }
+    conn
+}
+
+fn report_count(conn: &Connection) -> i64 {
+    conn.query_row("SELECT COUNT(*) FROM app_chess_import_reports", [], |row| row.get(0)).unwrap()
+}
+
+#[test]
+fn new_collections_get_the_current_schema() {
+    let conn = fixture(0);
+    assert_eq!(SchemaMigrations::migrate(&conn).unwrap(), 0);
+    assert_eq!(SchemaMigrations::version(&conn).unwrap(), SCHEMA_VERSION);
+
+    let mut report = ImportReport::new("CSV import", 1);
+    report.linked = 2;
+    let id = PuzzleRepository::save_import_report(&conn, &report, 10).unwrap();
+    assert_eq!(PuzzleRepository::get_import_report(&conn, id).unwrap().unwrap().linked, 2);
+}
+
+#[test]
+fn upgrades_every_earlier_schema() {
+    for version in 1..=FIXTURES.len() {
+        let conn = fixture(version);
+        let reports_before = if version >= 4 { report_count(&conn) } else { 0 };
+
+        //unversioned collections start from the first migration
+        assert_eq!(SchemaMigrations::migrate(&conn).unwrap(), 0, "schema {}", version);
+        assert_eq!(SchemaMigrations::version(&conn).unwrap(), SCHEMA_VERSION, "schema {}", version);
+
+        let puzzle = PuzzleRepository::get_by_nid(&conn, 1700000000000).unwrap().expect("linked puzzle kept");
+        assert_eq!(puzzle.puzzle_id, "00sHx");
+        assert_eq!(puzzle.rating, 1760);
+        //position keys are computed by the next import
+        assert_eq!(PuzzleRepository::get_puzzles_without_fen_key(&conn).unwrap().len(), 1);
+
+        if version >= 2 {
+            let ms: i64 = conn.query_row("SELECT ms_taken FROM app_chess_reviews", [], |row| row.get(0)).unwrap();
+            assert_eq!(ms, 12000, "schema {}", version);
+        }
+        if version >= 3 {
+            assert!(PuzzleRepository::get_deck_grading(&conn, 1).unwrap().auto_grade, "schema {}", version);
+        }
+
+        //reports of before the duplicate policies count nothing as linked or moved
+        assert_eq!(report_count(&conn), reports_before);
+        for report in PuzzleRepository::list_import_reports(&conn, None).unwrap() {
+            assert_eq!((report.linked, report.moved), (0, 0));
+        }
+        PuzzleRepository::save_import_report(&conn, &ImportReport::new("PGN import", 1), 10).unwrap();
+    }
+}
+
+#[test]
+fn migrating_again_changes_nothing() {
+    let conn = fixture(FIXTURES.len());
+    SchemaMigrations::migrate(&conn).unwrap();
+    assert_eq!(SchemaMigrations::migrate(&conn).unwrap(), SCHEMA_VERSION);
+    assert_eq!(report_count(&conn), 1);
+}
+
+#[test]
+fn versioned_collections_only_run_newer_migrations() {
+    let conn = fixture(FIXTURES.len());
+    conn.execute_batch(&format!("INSERT INTO app_chess_meta (key, value) VALUES ('{}', '4')", META_SCHEMA_VERSION))
+        .unwrap();
+
+    assert_eq!(SchemaMigrations::migrate(&conn).unwrap(), 4);
+    assert_eq!(SchemaMigrations::version(&conn).unwrap(), SCHEMA_VERSION);
+    assert_eq!(PuzzleRepository::list_import_reports(&conn, Some(1)).unwrap().len(), 1);
+}
+
+#[test]
+fn newer_schemas_are_refused() {
+    let conn = fixture(0);
+    SchemaMigrations::migrate(&conn).unwrap();
+    PuzzleRepository::set_meta(&conn, META_SCHEMA_VERSION, &(SCHEMA_VERSION + 1).to_string()).unwrap();
+
+    assert!(SchemaMigrations::migrate(&conn).is_err());
+    assert_eq!(SchemaMigrations::version(&conn).unwrap(), SCHEMA_VERSION + 1);
+}
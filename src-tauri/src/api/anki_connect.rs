@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+
+use anki::collection::Collection;
+use ankichess_core::chess;
+use ankichess_core::error::AnkiChessError;
+use ankichess_core::models::card::{AddNotePayload, NoteFields, UpdateNotePayload};
+use ankichess_core::services::deck_service::DeckService;
+use ankichess_core::services::note_service::NoteService;
+use ankichess_core::services::notetype_service::{
+    NotetypeService, CHESS_NOTETYPE_NAME, FIELD_COMMENT, FIELD_FEN, FIELD_GAME_URL, FIELD_MOVES, FIELD_OPENING_TAGS,
+    FIELD_POSITION, FIELD_RATING, FIELD_SOLUTION, FIELD_THEMES,
+};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use super::token_matches;
+use crate::state::AppState;
+
+//the protocol version of the AnkiConnect add-on this endpoint follows
+const ANKI_CONNECT_VERSION: u64 = 6;
+
+#[derive(Deserialize)]
+struct Request {
+    action: String,
+    #[serde(default)]
+    version: Option<u64>,
+    #[serde(default)]
+    params: Value,
+    #[serde(default)]
+    key: Option<String>,
+}
+
+/// Handles one AnkiConnect request. Only chess notes can be added, their fields are
+/// those of the chess notetype and read from the linked puzzle.
+pub fn handle(state: &AppState, token: &str, body: &[u8]) -> Value {
+    let request: Request = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(e) => return reply(Some(ANKI_CONNECT_VERSION), Err(e.to_string())),
+    };
+
+    //like the add-on, asking for permission is the one action that works without the key
+    let authorized = request.key.as_deref().is_some_and(|key| token_matches(key, token));
+    if !authorized && request.action != "requestPermission" {
+        return reply(request.version, Err("valid api key must be provided".into()));
+    }
+
+    let result = perform(state, &request.action, request.params);
+    reply(request.version, result)
+}
+
+//versions before 5 answer with the bare result
+fn reply(version: Option<u64>, result: Result<Value, String>) -> Value {
+    match (version.unwrap_or(4) >= 5, result) {
+        (true, Ok(value)) => json!({ "result": value, "error": null }),
+        (true, Err(e)) | (false, Err(e)) => json!({ "result": null, "error": e }),
+        (false, Ok(value)) => value,
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Note {
+    deck_name: String,
+    model_name: String,
+    fields: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct NoteUpdate {
+    id: i64,
+    fields: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct MultiAction {
+    action: String,
+    #[serde(default)]
+    version: Option<u64>,
+    #[serde(default)]
+    params: Value,
+}
+
+fn perform(state: &AppState, action: &str, params: Value) -> Result<Value, String> {
+    match action {
+        "version" => Ok(json!(ANKI_CONNECT_VERSION)),
+        "requestPermission" => Ok(json!({
+            "permission": "granted",
+            "requireApiKey": true,
+            "version": ANKI_CONNECT_VERSION,
+        })),
+        "multi" => {
+            let actions: Vec<MultiAction> = param(&params, "actions")?;
+            let results = actions
+                .into_iter()
+                .map(|a| {
+                    let result = perform(state, &a.action, a.params);
+                    reply(a.version.or(Some(ANKI_CONNECT_VERSION)), result)
+                })
+                .collect();
+            Ok(Value::Array(results))
+        }
+        //decks
+        "deckNames" => {
+            let decks = DeckService::get_all_decks(&mut lock(state)?).map_err(message)?;
+            Ok(json!(decks.into_iter().map(|d| d.name).collect::<Vec<_>>()))
+        }
+        "deckNamesAndIds" => {
+            let decks = DeckService::get_all_decks(&mut lock(state)?).map_err(message)?;
+            Ok(Value::Object(decks.into_iter().map(|d| (d.name, json!(d.id.0))).collect()))
+        }
+        "createDeck" => {
+            let name: String = param(&params, "deck")?;
+            Ok(json!(DeckService::create_deck(&mut lock(state)?, &name).map_err(message)?))
+        }
+        //models, only the chess notetype is offered
+        "modelNames" => Ok(json!([CHESS_NOTETYPE_NAME])),
+        "modelNamesAndIds" => {
+            let nt = NotetypeService::ensure_chess_notetype(&mut lock(state)?).map_err(message)?;
+            Ok(json!({ CHESS_NOTETYPE_NAME: nt.id.0 }))
+        }
+        "modelFieldNames" => {
+            let model: String = param(&params, "modelName")?;
+            check_model(&model)?;
+            let nt = NotetypeService::ensure_chess_notetype(&mut lock(state)?).map_err(message)?;
+            Ok(json!(nt.fields.iter().map(|f| f.name.clone()).collect::<Vec<_>>()))
+        }
+        //notes
+        "addNote" => {
+            let note: Note = param(&params, "note")?;
+            Ok(json!(add_note(state, note)?))
+        }
+        "addNotes" => {
+            let notes: Vec<Note> = param(&params, "notes")?;
+            Ok(json!(notes.into_iter().map(|note| add_note(state, note).ok()).collect::<Vec<_>>()))
+        }
+        "canAddNotes" => {
+            let notes: Vec<Note> = param(&params, "notes")?;
+            let mut col = lock(state)?;
+            let decks = deck_ids(&mut col)?;
+            Ok(json!(notes.iter().map(|note| to_payload(note, &decks).is_ok()).collect::<Vec<_>>()))
+        }
+        "updateNoteFields" => {
+            let update: NoteUpdate = param(&params, "note")?;
+            update_note(state, update)?;
+            Ok(Value::Null)
+        }
+        "deleteNotes" => {
+            let note_ids: Vec<i64> = param(&params, "notes")?;
+            let mut col = lock(state)?;
+            let mut journal = state.undo_journal.lock().map_err(|e| e.to_string())?;
+            NoteService::delete_notes(&mut col, &mut journal, note_ids).map_err(message)?;
+            Ok(Value::Null)
+        }
+        "findNotes" => {
+            let query: String = param(&params, "query")?;
+            Ok(json!(NoteService::find_notes(&mut lock(state)?, &query).map_err(message)?))
+        }
+        "findCards" => {
+            let query: String = param(&params, "query")?;
+            Ok(json!(NoteService::find_cards(&mut lock(state)?, &query).map_err(message)?))
+        }
+        "notesInfo" => {
+            let note_ids: Vec<i64> = param(&params, "notes")?;
+            let notes = NoteService::get_note_fields(&mut lock(state)?, &note_ids).map_err(message)?;
+            Ok(json!(notes.iter().map(|note| note.as_ref().map_or(json!({}), note_info)).collect::<Vec<_>>()))
+        }
+        //the card on the study screen
+        "guiCurrentCard" => current_card(state),
+        _ => Err(format!("unsupported action: {}", action)),
+    }
+}
+
+fn lock(state: &AppState) -> Result<std::sync::MutexGuard<'_, Collection>, String> {
+    state.col.lock().map_err(|e| e.to_string())
+}
+
+fn message(e: AnkiChessError) -> String {
+    e.to_string()
+}
+
+fn param<T: DeserializeOwned>(params: &Value, name: &str) -> Result<T, String> {
+    let value = params.get(name).cloned().unwrap_or(Value::Null);
+    serde_json::from_value(value).map_err(|e| format!("invalid parameter '{}': {}", name, e))
+}
+
+fn check_model(name: &str) -> Result<(), String> {
+    if name != CHESS_NOTETYPE_NAME {
+        return Err(format!("model was not found: {}", name));
+    }
+    Ok(())
+}
+
+fn deck_ids(col: &mut Collection) -> Result<HashMap<String, i64>, String> {
+    let decks = DeckService::get_all_decks(col).map_err(message)?;
+    Ok(decks.into_iter().map(|d| (d.name, d.id.0)).collect())
+}
+
+//the solution is read from Moves (uci), Solution is accepted as well since tools tend to fill it
+fn to_payload(note: &Note, decks: &HashMap<String, i64>) -> Result<AddNotePayload, String> {
+    check_model(&note.model_name)?;
+    let deck_id = *decks
+        .get(&note.deck_name)
+        .ok_or_else(|| format!("deck was not found: {}", note.deck_name))?;
+
+    let field = |name: &str| note.fields.get(name).map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+    let fen = field(FIELD_FEN).ok_or("cannot create note because it is empty")?;
+    let solution = field(FIELD_MOVES)
+        .or_else(|| field(FIELD_SOLUTION))
+        .ok_or_else(|| format!("the {} field is empty", FIELD_MOVES))?;
+    chess::validate_puzzle(&fen, &solution).map_err(|e| e.to_string())?;
+
+    Ok(AddNotePayload {
+        deck_id,
+        fen,
+        solution,
+        comment: field(FIELD_COMMENT),
+        rating: field(FIELD_RATING).and_then(|r| r.parse().ok()),
+        themes: field(FIELD_THEMES),
+        game_url: field(FIELD_GAME_URL),
+        opening_tags: field(FIELD_OPENING_TAGS),
+    })
+}
+
+//the tags of the request are not kept, puzzles are organised by themes instead
+fn add_note(state: &AppState, note: Note) -> Result<i64, String> {
+    let mut col = lock(state)?;
+    let payload = to_payload(&note, &deck_ids(&mut col)?)?;
+    let mut journal = state.undo_journal.lock().map_err(|e| e.to_string())?;
+    NoteService::create_note(&mut col, &mut journal, payload).map_err(message)
+}
+
+//only the fields a chess note can edit, the others come from the puzzle
+fn update_note(state: &AppState, update: NoteUpdate) -> Result<(), String> {
+    let mut col = lock(state)?;
+    let current = NoteService::get_note_fields(&mut col, &[update.id])
+        .map_err(message)?
+        .pop()
+        .flatten()
+        .ok_or_else(|| format!("note was not found: {}", update.id))?;
+
+    let value = |name: &str| {
+        update
+            .fields
+            .get(name)
+            .cloned()
+            .or_else(|| current.fields.iter().find(|(n, _)| n == name).map(|(_, v)| v.clone()))
+            .unwrap_or_default()
+    };
+    let payload = UpdateNotePayload {
+        note_id: update.id,
+        fen: value(FIELD_FEN),
+        solution: value(FIELD_MOVES),
+        comment: value(FIELD_COMMENT),
+    };
+
+    let mut journal = state.undo_journal.lock().map_err(|e| e.to_string())?;
+    NoteService::update_note(&mut col, &mut journal, payload).map_err(message)
+}
+
+fn fields_object(note: &NoteFields) -> Value {
+    let fields: Map<String, Value> = note
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(order, (name, value))| (name.clone(), json!({ "value": value, "order": order })))
+        .collect();
+    Value::Object(fields)
+}
+
+fn note_info(note: &NoteFields) -> Value {
+    json!({
+        "noteId": note.note_id,
+        "modelName": note.notetype,
+        "tags": note.tags,
+        "fields": fields_object(note),
+        "cards": note.card_ids,
+        "mod": note.modified_secs,
+    })
+}
+
+fn current_card(state: &AppState) -> Result<Value, String> {
+    let Some(card) = state.study_card.lock().map_err(|e| e.to_string())?.clone() else {
+        return Ok(Value::Null);
+    };
+
+    let mut col = lock(state)?;
+    let Some(note) = NoteService::get_note_fields(&mut col, &[card.note_id]).map_err(message)?.pop().flatten() else {
+        return Ok(Value::Null);
+    };
+    let deck_name = DeckService::get_all_decks(&mut col)
+        .map_err(message)?
+        .into_iter()
+        .find(|d| d.id.0 == card.deck_id)
+        .map(|d| d.name)
+        .unwrap_or_default();
+    let field = |name: &str| note.fields.iter().find(|(n, _)| n == name).map(|(_, v)| v.clone()).unwrap_or_default();
+
+    Ok(json!({
+        "cardId": card.card_id,
+        "note": card.note_id,
+        "deckName": deck_name,
+        "modelName": note.notetype,
+        "fieldOrder": 0,
+        "fields": fields_object(&note),
+        "template": "Puzzle",
+        "question": field(FIELD_POSITION),
+        "answer": field(FIELD_SOLUTION),
+        "buttons": [1, 2, 3, 4],
+    }))
+}
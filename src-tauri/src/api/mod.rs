@@ -0,0 +1,195 @@
+use std::net::{Ipv4Addr, TcpListener};
+use std::sync::{Arc, Mutex, RwLock};
+
+use anki::collection::Collection;
+use ankichess_core::error::AnkiChessError;
+use ankichess_core::repository::puzzle_repo::PuzzleRepository;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde::Serialize;
+use tokio::sync::oneshot;
+use tower_http::cors::CorsLayer;
+
+use crate::state::AppState;
+
+mod anki_connect;
+mod rpc;
+
+const META_API_ENABLED: &str = "api_enabled";
+const META_API_PORT: &str = "api_port";
+const META_API_TOKEN: &str = "api_token";
+const META_ANKI_CONNECT_ENABLED: &str = "anki_connect_enabled";
+const META_ANKI_CONNECT_PORT: &str = "anki_connect_port";
+const DEFAULT_API_PORT: u16 = 8766;
+//the port of the AnkiConnect add-on, so tools find it without configuration
+const DEFAULT_ANKI_CONNECT_PORT: u16 = 8765;
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiSettings {
+    pub enabled: bool,
+    pub port: u16,
+    pub anki_connect_enabled: bool,
+    pub anki_connect_port: u16,
+    /// Shared by both servers, AnkiConnect clients send it as their api key.
+    pub token: String,
+}
+
+impl ApiSettings {
+    //kept in the collection, so the api comes back up with the next start
+    pub fn load(col: &Collection) -> Result<Self, AnkiChessError> {
+        let db = col.storage.db();
+        let flag = |key| -> Result<bool, AnkiChessError> { Ok(PuzzleRepository::get_meta(db, key)?.as_deref() == Some("1")) };
+        let port = |key, default| -> Result<u16, AnkiChessError> {
+            Ok(PuzzleRepository::get_meta(db, key)?.and_then(|p| p.parse().ok()).unwrap_or(default))
+        };
+
+        Ok(Self {
+            enabled: flag(META_API_ENABLED)?,
+            port: port(META_API_PORT, DEFAULT_API_PORT)?,
+            anki_connect_enabled: flag(META_ANKI_CONNECT_ENABLED)?,
+            anki_connect_port: port(META_ANKI_CONNECT_PORT, DEFAULT_ANKI_CONNECT_PORT)?,
+            token: PuzzleRepository::get_meta(db, META_API_TOKEN)?.unwrap_or_default(),
+        })
+    }
+
+    pub fn save(&self, col: &Collection) -> Result<(), AnkiChessError> {
+        let db = col.storage.db();
+        let flag = |enabled| if enabled { "1" } else { "0" };
+        PuzzleRepository::set_meta(db, META_API_ENABLED, flag(self.enabled))?;
+        PuzzleRepository::set_meta(db, META_API_PORT, &self.port.to_string())?;
+        PuzzleRepository::set_meta(db, META_ANKI_CONNECT_ENABLED, flag(self.anki_connect_enabled))?;
+        PuzzleRepository::set_meta(db, META_ANKI_CONNECT_PORT, &self.anki_connect_port.to_string())?;
+        PuzzleRepository::set_meta(db, META_API_TOKEN, &self.token)?;
+        Ok(())
+    }
+
+    pub fn new_token() -> String {
+        uuid::Uuid::new_v4().simple().to_string()
+    }
+}
+
+/// The opt-in servers on localhost: the JSON-RPC api and the AnkiConnect endpoint.
+/// At most one instance of each runs.
+#[derive(Default)]
+pub struct ApiServer {
+    token: Arc<RwLock<String>>,
+    rpc: Mutex<Option<RunningServer>>,
+    anki_connect: Mutex<Option<RunningServer>>,
+}
+
+struct RunningServer {
+    port: u16,
+    shutdown: oneshot::Sender<()>,
+}
+
+#[derive(Clone)]
+struct ApiContext {
+    state: AppState,
+    token: Arc<RwLock<String>>,
+}
+
+impl ApiServer {
+    /// Starts, stops or updates the servers to match `settings`.
+    pub fn apply(&self, state: AppState, settings: &ApiSettings) -> Result<(), AnkiChessError> {
+        if (settings.enabled || settings.anki_connect_enabled) && settings.token.is_empty() {
+            return Err(AnkiChessError::InvalidInput("The API needs a token".into()));
+        }
+        //read on every request, a new token doesn't need a restart
+        *self.token.write()? = settings.token.clone();
+
+        let context = ApiContext { state, token: self.token.clone() };
+        let rpc = Router::new().route("/", post(handle_rpc)).with_state(context.clone());
+        Self::apply_server(&self.rpc, settings.enabled, settings.port, rpc)?;
+
+        let anki_connect = Router::new().route("/", post(handle_anki_connect)).with_state(context);
+        Self::apply_server(&self.anki_connect, settings.anki_connect_enabled, settings.anki_connect_port, anki_connect)
+    }
+
+    fn apply_server(
+        slot: &Mutex<Option<RunningServer>>,
+        enabled: bool,
+        port: u16,
+        router: Router,
+    ) -> Result<(), AnkiChessError> {
+        let mut running = slot.lock()?;
+        //keeps the port from being rebound while the old server is still shutting down
+        if enabled && running.as_ref().is_some_and(|server| server.port == port) {
+            return Ok(());
+        }
+        if let Some(server) = running.take() {
+            server.shutdown.send(()).ok();
+        }
+        if !enabled {
+            return Ok(());
+        }
+
+        //bound here so a port in use is reported to the caller
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        listener.set_nonblocking(true)?;
+        let router = router.layer(CorsLayer::permissive());
+
+        let (shutdown, rx) = oneshot::channel::<()>();
+        tauri::async_runtime::spawn(async move {
+            let result = match tokio::net::TcpListener::from_std(listener) {
+                Ok(listener) => axum::serve(listener, router)
+                    .with_graceful_shutdown(async {
+                        rx.await.ok();
+                    })
+                    .await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                eprintln!("Local API on port {} stopped: {}", port, e);
+            }
+        });
+
+        *running = Some(RunningServer { port, shutdown });
+        Ok(())
+    }
+}
+
+async fn handle_rpc(State(context): State<ApiContext>, headers: HeaderMap, body: Bytes) -> Response {
+    let Some(expected) = current_token(&context) else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let authorized = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| token_matches(token, &expected));
+    if !authorized {
+        return (StatusCode::UNAUTHORIZED, "Missing or wrong API token").into_response();
+    }
+
+    let response = match tokio::task::spawn_blocking(move || rpc::handle(&context.state, &body)).await {
+        Ok(response) => response,
+        Err(e) => rpc::RpcResponse::internal_error(e.to_string()),
+    };
+    Json(response).into_response()
+}
+
+//AnkiConnect sends the key inside the request, so it's checked there
+async fn handle_anki_connect(State(context): State<ApiContext>, body: Bytes) -> Response {
+    let Some(expected) = current_token(&context) else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    match tokio::task::spawn_blocking(move || anki_connect::handle(&context.state, &expected, &body)).await {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+fn current_token(context: &ApiContext) -> Option<String> {
+    context.token.read().ok().map(|token| token.clone())
+}
+
+//compares every byte, so the time taken doesn't tell how much of a guess was right
+fn token_matches(given: &str, expected: &str) -> bool {
+    let (a, b) = (given.as_bytes(), expected.as_bytes());
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,283 @@
+use ankichess_core::error::AnkiChessError;
+use ankichess_core::models::card::{AddNotePayload, BrowseOptions, PuzzleAttempt, UpdateNotePayload};
+use ankichess_core::models::puzzle::{CsvImportPayload, ImportOptions, PgnImportPayload};
+use ankichess_core::repository::puzzle_repo::PuzzleRepository;
+use ankichess_core::services::deck_service::DeckService;
+use ankichess_core::services::import_service::ImportService;
+use ankichess_core::services::note_service::NoteService;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::commands::import::{start_csv_import, start_db_import, start_pgn_import};
+use crate::state::AppState;
+
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+const INTERNAL_ERROR: i32 = -32603;
+//errors of the operation itself, `data` holds the serialized AnkiChessError
+const OPERATION_FAILED: i32 = -32000;
+
+#[derive(Deserialize)]
+struct RpcRequest {
+    jsonrpc: String,
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize)]
+pub struct RpcResponse {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+#[derive(Serialize)]
+pub struct RpcError {
+    code: i32,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i32, message: impl Into<String>) -> Self {
+        Self { code, message: message.into(), data: None }
+    }
+}
+
+impl From<AnkiChessError> for RpcError {
+    fn from(e: AnkiChessError) -> Self {
+        Self { code: OPERATION_FAILED, message: e.to_string(), data: serde_json::to_value(&e).ok() }
+    }
+}
+
+impl<T> From<std::sync::PoisonError<T>> for RpcError {
+    fn from(e: std::sync::PoisonError<T>) -> Self {
+        AnkiChessError::from(e).into()
+    }
+}
+
+impl RpcResponse {
+    fn new(id: Value, result: Result<Value, RpcError>) -> Self {
+        match result {
+            Ok(value) => Self { jsonrpc: "2.0", id, result: Some(value), error: None },
+            Err(error) => Self { jsonrpc: "2.0", id, result: None, error: Some(error) },
+        }
+    }
+
+    pub fn internal_error(message: String) -> Self {
+        Self::new(Value::Null, Err(RpcError::new(INTERNAL_ERROR, message)))
+    }
+}
+
+/// Handles one JSON-RPC 2.0 request. The methods are named after the Tauri commands and
+/// take the same arguments as `invoke`, e.g. `{"deckId": 1}` for `get_next_card`.
+pub fn handle(state: &AppState, body: &[u8]) -> RpcResponse {
+    let request: RpcRequest = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(e) => return RpcResponse::new(Value::Null, Err(RpcError::new(PARSE_ERROR, e.to_string()))),
+    };
+    if request.jsonrpc != "2.0" {
+        return RpcResponse::new(request.id, Err(RpcError::new(INVALID_REQUEST, "jsonrpc must be \"2.0\"")));
+    }
+
+    let result = call(state, &request.method, request.params);
+    RpcResponse::new(request.id, result)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeckArgs {
+    deck_id: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateDeckArgs {
+    deck_name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CardArgs {
+    card_id: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NotesArgs {
+    note_ids: Vec<i64>,
+}
+
+#[derive(Deserialize)]
+struct PayloadArgs<T> {
+    payload: T,
+}
+
+#[derive(Deserialize)]
+struct BrowseArgs {
+    options: BrowseOptions,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AnswerArgs {
+    card_id: i64,
+    rating: Option<u8>,
+    milliseconds_taken: Option<u32>,
+    wrong_attempts: Option<u32>,
+    hints_used: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PreviewArgs {
+    payload: ImportOptions,
+    sample_size: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReportArgs {
+    report_id: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReportsArgs {
+    deck_id: Option<i64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JobArgs {
+    job_id: u64,
+}
+
+fn call(state: &AppState, method: &str, params: Value) -> Result<Value, RpcError> {
+    match method {
+        //decks
+        "get_all_decks" => to_value(DeckService::get_all_decks(&mut state.col.lock()?)?),
+        "create_deck" => {
+            let args: CreateDeckArgs = args(params)?;
+            to_value(DeckService::create_deck(&mut state.col.lock()?, &args.deck_name)?)
+        }
+        "delete_deck" => {
+            let args: DeckArgs = args(params)?;
+            DeckService::delete_deck(&mut state.col.lock()?, args.deck_id)?;
+            Ok(Value::Null)
+        }
+        //cards
+        "add_chess_note" => {
+            let args: PayloadArgs<AddNotePayload> = args(params)?;
+            let mut col = state.col.lock()?;
+            let mut journal = state.undo_journal.lock()?;
+            to_value(NoteService::create_note(&mut col, &mut journal, args.payload)?)
+        }
+        "update_chess_note" => {
+            let args: PayloadArgs<UpdateNotePayload> = args(params)?;
+            let mut col = state.col.lock()?;
+            let mut journal = state.undo_journal.lock()?;
+            NoteService::update_note(&mut col, &mut journal, args.payload)?;
+            Ok(Value::Null)
+        }
+        "delete_notes" => {
+            let args: NotesArgs = args(params)?;
+            let mut col = state.col.lock()?;
+            let mut journal = state.undo_journal.lock()?;
+            NoteService::delete_notes(&mut col, &mut journal, args.note_ids)?;
+            Ok(Value::Null)
+        }
+        "get_card_by_id" => {
+            let args: CardArgs = args(params)?;
+            to_value(NoteService::get_card_details(&mut state.col.lock()?, args.card_id)?)
+        }
+        //study
+        "get_next_card" => {
+            let args: DeckArgs = args(params)?;
+            to_value(NoteService::get_next_study_card(&mut state.col.lock()?, args.deck_id)?)
+        }
+        "answer_card" => {
+            let args: AnswerArgs = args(params)?;
+            let attempt = PuzzleAttempt {
+                milliseconds_taken: args.milliseconds_taken.unwrap_or(0),
+                wrong_attempts: args.wrong_attempts.unwrap_or(0),
+                hints_used: args.hints_used.unwrap_or(0),
+            };
+            let mut col = state.col.lock()?;
+            let mut journal = state.undo_journal.lock()?;
+            to_value(NoteService::answer_card(&mut col, &mut journal, args.card_id, args.rating, attempt)?)
+        }
+        "get_card_review_stats" => {
+            let args: CardArgs = args(params)?;
+            to_value(NoteService::get_review_stats(&mut state.col.lock()?, args.card_id)?)
+        }
+        "browse_cards_in_deck" => {
+            let args: BrowseArgs = args(params)?;
+            to_value(NoteService::browse_cards(&mut state.col.lock()?, args.options)?)
+        }
+        //imports, these return the id of the job
+        "import_puzzles_from_db" => {
+            let args: PayloadArgs<ImportOptions> = args(params)?;
+            to_value(start_db_import(state, args.payload))
+        }
+        "preview_import_from_db" => {
+            let args: PreviewArgs = args(params)?;
+            let db_path = state.config.puzzle_db_path();
+            let preview = ImportService::preview_lichess_import(
+                state.col.clone(),
+                args.payload,
+                db_path,
+                args.sample_size.unwrap_or(12),
+            )?;
+            to_value(preview)
+        }
+        "import_puzzles_from_csv" => {
+            let args: PayloadArgs<CsvImportPayload> = args(params)?;
+            to_value(start_csv_import(state, args.payload))
+        }
+        "import_puzzles_from_pgn" => {
+            let args: PayloadArgs<PgnImportPayload> = args(params)?;
+            to_value(start_pgn_import(state, args.payload))
+        }
+        "get_import_report" => {
+            let args: ReportArgs = args(params)?;
+            let col = state.col.lock()?;
+            to_value(PuzzleRepository::get_import_report(col.storage.db(), args.report_id).map_err(AnkiChessError::from)?)
+        }
+        "list_import_reports" => {
+            let args: ReportsArgs = args(params)?;
+            let col = state.col.lock()?;
+            to_value(PuzzleRepository::list_import_reports(col.storage.db(), args.deck_id).map_err(AnkiChessError::from)?)
+        }
+        //jobs
+        "list_jobs" => to_value(state.jobs.list()),
+        "get_job" => {
+            let args: JobArgs = args(params)?;
+            to_value(state.jobs.get(args.job_id))
+        }
+        "cancel_job" => {
+            let args: JobArgs = args(params)?;
+            to_value(state.jobs.cancel(args.job_id))
+        }
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("Unknown method '{}'", method))),
+    }
+}
+
+//params is an object with the arguments of the command
+fn args<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn to_value<T: Serialize>(value: T) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(|e| RpcError::new(INTERNAL_ERROR, e.to_string()))
+}
@@ -0,0 +1,45 @@
+use ankichess_core::error::AnkiChessError;
+use crate::api::ApiSettings;
+use crate::state::AppState;
+use tauri::{command, State};
+
+#[command]
+pub fn get_api_settings(state: State<AppState>) -> Result<ApiSettings, AnkiChessError> {
+    let col = state.col.lock()?;
+    ApiSettings::load(&col)
+}
+
+//leaves out settings that aren't given. a token is created the first time a server is turned on
+#[command]
+pub fn set_api_settings(
+    enabled: Option<bool>,
+    port: Option<u16>,
+    anki_connect_enabled: Option<bool>,
+    anki_connect_port: Option<u16>,
+    state: State<AppState>,
+) -> Result<ApiSettings, AnkiChessError> {
+    let mut settings = ApiSettings::load(&*state.col.lock()?)?;
+    settings.enabled = enabled.unwrap_or(settings.enabled);
+    settings.port = port.unwrap_or(settings.port);
+    settings.anki_connect_enabled = anki_connect_enabled.unwrap_or(settings.anki_connect_enabled);
+    settings.anki_connect_port = anki_connect_port.unwrap_or(settings.anki_connect_port);
+    if settings.token.is_empty() {
+        settings.token = ApiSettings::new_token();
+    }
+
+    //started before saving, so a port that is in use isn't kept
+    state.api.apply(state.inner().clone(), &settings)?;
+    settings.save(&*state.col.lock()?)?;
+    Ok(settings)
+}
+
+//clients with the old token are rejected from now on
+#[command]
+pub fn regenerate_api_token(state: State<AppState>) -> Result<ApiSettings, AnkiChessError> {
+    let mut settings = ApiSettings::load(&*state.col.lock()?)?;
+    settings.token = ApiSettings::new_token();
+
+    state.api.apply(state.inner().clone(), &settings)?;
+    settings.save(&*state.col.lock()?)?;
+    Ok(settings)
+}
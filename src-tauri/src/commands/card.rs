@@ -1,12 +1,9 @@
-use crate::{services::note_service::NoteService};
-use crate::error::AnkiChessError;
+use ankichess_core::{services::note_service::NoteService};
+use ankichess_core::error::AnkiChessError;
 use crate::state::AppState;
-use anki::card::CardId;
-use anki::prelude::TimestampMillis;
-use anki::scheduler::answering::{CardAnswer, Rating};
 use tauri::{command, State};
 
-use crate::models::card::*;
+use ankichess_core::models::card::*;
 
 #[command]
 pub fn add_chess_note(
@@ -14,13 +11,15 @@ pub fn add_chess_note(
     state: State<AppState>,
 ) -> Result<i64, AnkiChessError> {
     let mut col = state.col.lock()?;
-    NoteService::create_note(&mut col, payload)
+    let mut journal = state.undo_journal.lock()?;
+    NoteService::create_note(&mut col, &mut journal, payload)
 }
 
 #[command]
 pub fn delete_notes(note_ids: Vec<i64>, state: State<AppState>) -> Result<(), AnkiChessError> {
     let mut col = state.col.lock()?;
-    NoteService::delete_notes(&mut col, note_ids)
+    let mut journal = state.undo_journal.lock()?;
+    NoteService::delete_notes(&mut col, &mut journal, note_ids)
 }
 
 #[command]
@@ -29,7 +28,15 @@ pub fn get_next_card(
     state: State<AppState>,
 ) -> Result<Option<StudyCard>, AnkiChessError> {
     let mut col = state.col.lock()?;
-    NoteService::get_next_study_card(&mut col, deck_id)
+    let card = NoteService::get_next_study_card(&mut col, deck_id)?;
+    *state.study_card.lock()? = card.clone();
+    Ok(card)
+}
+
+#[command]
+pub fn end_study_session(state: State<AppState>) -> Result<(), AnkiChessError> {
+    *state.study_card.lock()? = None;
+    Ok(())
 }
 
 #[command]
@@ -42,35 +49,33 @@ pub fn browse_cards_in_deck(
 }
 
 #[command]
-pub fn answer_card(card_id: i64, rating: u8, state: State<AppState>) -> Result<(), AnkiChessError> {
+pub fn answer_card(
+    card_id: i64,
+    rating: Option<u8>,
+    milliseconds_taken: Option<u32>,
+    wrong_attempts: Option<u32>,
+    hints_used: Option<u32>,
+    state: State<AppState>,
+) -> Result<u8, AnkiChessError> {
     let mut col = state.col.lock()?;
-    let states = col.get_scheduling_states(CardId(card_id))?;
-
-    let (rating_enum, new_state) = match rating {
-        1 => (Rating::Again, states.again),
-        2 => (Rating::Hard, states.hard),
-        3 => (Rating::Good, states.good),
-        4 => (Rating::Easy, states.easy),
-        _ => {
-            return Err(AnkiChessError::InvalidInput(
-                "Invalid rating. Use 1 (Again), 2 (Hard), 3 (Good), or 4 (Easy).".to_string(),
-            ))
-        }
-    };
-
-    let mut answer = CardAnswer {
-        card_id: CardId(card_id),
-        current_state: states.current,
-        new_state,
-        rating: rating_enum,
-        answered_at: TimestampMillis::now(),
-        milliseconds_taken: 0,
-        custom_data: None,
-        from_queue: true,
+    let mut journal = state.undo_journal.lock()?;
+    let attempt = PuzzleAttempt {
+        milliseconds_taken: milliseconds_taken.unwrap_or(0),
+        wrong_attempts: wrong_attempts.unwrap_or(0),
+        hints_used: hints_used.unwrap_or(0),
     };
+    let rating = NoteService::answer_card(&mut col, &mut journal, card_id, rating, attempt)?;
+    *state.study_card.lock()? = None;
+    Ok(rating)
+}
 
-    col.answer_card(&mut answer)?;
-    Ok(())
+#[command]
+pub fn get_card_review_stats(
+    card_id: i64,
+    state: State<AppState>,
+) -> Result<PuzzleReviewStats, AnkiChessError> {
+    let mut col = state.col.lock()?;
+    NoteService::get_review_stats(&mut col, card_id)
 }
 
 #[command]
@@ -88,5 +93,6 @@ pub fn update_chess_note(
     state: State<AppState>,
 ) -> Result<(), AnkiChessError> {
     let mut col = state.col.lock()?;
-    NoteService::update_note(&mut col, payload)
+    let mut journal = state.undo_journal.lock()?;
+    NoteService::update_note(&mut col, &mut journal, payload)
 }
\ No newline at end of file
@@ -1,19 +1,56 @@
-use crate::{models::lichessdb::DbStatus, repository::puzzle_repo::PuzzleRepository, services::lichessdb_service::LichessdbService, state::AppState};
-use tauri::{AppHandle, Emitter, Runtime, State, Window};
+use ankichess_core::{models::lichessdb::{DbStatus, PuzzleDbChangeReport}, repository::puzzle_repo::PuzzleRepository, services::lichessdb_service::{LichessdbService, DEFAULT_PUZZLE_DB_BASE_URL}};
+use ankichess_core::config::CoreConfig;
+use ankichess_core::error::AnkiChessError;
+use ankichess_core::models::job::JobKind;
+use ankichess_core::services::job_service::JobHandle;
+use ankichess_core::services::note_service::NoteService;
+use anki::collection::Collection;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::{Emitter, Runtime, State, Window};
+use crate::state::AppState;
 use crate::AppBootstrapData;
 
 
 const DATABASE_READY_EVENT: &str = "DATABASE_READY";
 const DATABASE_ERROR_EVENT: &str = "DATABASE_ERROR";
+const PUZZLE_METADATA_SYNCED_EVENT: &str = "PUZZLE_METADATA_SYNCED";
 
 #[tauri::command]
-pub fn get_puzzle_db_status<R: Runtime>(app_handle: AppHandle<R>) -> Result<DbStatus, String> {
-    LichessdbService::get_status(&app_handle).map_err(|e| e.to_string())
+pub fn get_puzzle_db_status(state: State<AppState>) -> Result<DbStatus, String> {
+    LichessdbService::get_status(&state.config).map_err(|e| e.to_string())
 }
 
+const META_PUZZLE_DB_BASE_URL: &str = "puzzle_db_base_url";
+
+//the dump can be served from a mirror or a local server instead of database.lichess.org
+fn puzzle_db_base_url(state: &AppState) -> Result<String, String> {
+    let col = state.col.lock().map_err(|e| e.to_string())?;
+    let base_url = PuzzleRepository::get_meta(col.storage.db(), META_PUZZLE_DB_BASE_URL).map_err(|e| e.to_string())?;
+    Ok(base_url.unwrap_or_else(|| DEFAULT_PUZZLE_DB_BASE_URL.to_string()))
+}
+
+#[tauri::command]
+pub fn get_puzzle_db_base_url(state: State<AppState>) -> Result<String, String> {
+    puzzle_db_base_url(&state)
+}
+
+//none goes back to the default url
 #[tauri::command]
-pub async fn check_for_update<R: Runtime>(app_handle: AppHandle<R>) -> Result<bool, String> {
-    LichessdbService::check_for_update(&app_handle)
+pub fn set_puzzle_db_base_url(base_url: Option<String>, state: State<AppState>) -> Result<(), String> {
+    let col = state.col.lock().map_err(|e| e.to_string())?;
+    let db = col.storage.db();
+    match base_url.map(|url| url.trim().to_string()).filter(|url| !url.is_empty()) {
+        Some(url) => PuzzleRepository::set_meta(db, META_PUZZLE_DB_BASE_URL, &url),
+        None => db.execute("DELETE FROM app_chess_meta WHERE key = ?1", [META_PUZZLE_DB_BASE_URL]).map(|_| ()),
+    }
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn check_for_update(state: State<'_, AppState>) -> Result<bool, String> {
+    let base_url = puzzle_db_base_url(&state)?;
+    LichessdbService::check_for_update(&state.config, &base_url)
         .await 
         .map_err(|e| e.to_string())
 }
@@ -21,46 +58,116 @@ pub async fn check_for_update<R: Runtime>(app_handle: AppHandle<R>) -> Result<bo
 #[tauri::command]
 pub async fn start_database_download_and_index<R: Runtime>(
     window: Window<R>,
-    app_handle: AppHandle<R>,
-) -> Result<(), String> {
-    tokio::spawn(async move {
-        let result = LichessdbService::download_and_index(window.clone(), app_handle.clone()).await;
+    expected_sha256: Option<String>,
+    propagate_to_decks: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<u64, String> {
+    let base_url = puzzle_db_base_url(&state)?;
+    let config = state.config.clone();
+    let col = propagate_to_decks.unwrap_or(false).then(|| state.col.clone());
+    let job = state.jobs.start(JobKind::Download, "Puzzle database download");
+    let job_id = job.id();
 
-        if let Err(e) = result {
-            window.emit(DATABASE_ERROR_EVENT, e.to_string()).ok();
-        } else {
-            if let Ok(status) = get_puzzle_db_status(app_handle) {
-                window.emit(DATABASE_READY_EVENT, status).ok();
-            }
-        }
+    tokio::spawn(async move {
+        let result = LichessdbService::download_and_index(config.clone(), base_url, job.clone(), expected_sha256).await;
+        finish_indexing(window, config, job, result, col).await;
     });
 
-    Ok(())
+    Ok(job_id)
 }
 
 #[tauri::command]
-pub fn cleanup_unused_puzzles(state: State<AppState>) -> Result<usize, String> {
-    let col_guard = state.col.lock().map_err(|e| e.to_string())?;
-    
-    let db = col_guard.storage.db();
+pub async fn index_local_puzzle_dump<R: Runtime>(
+    window: Window<R>,
+    file_path: String,
+    propagate_to_decks: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<u64, String> {
+    let config = state.config.clone();
+    let col = propagate_to_decks.unwrap_or(false).then(|| state.col.clone());
+    let job = state.jobs.start(JobKind::Index, "Puzzle database indexing");
+    let job_id = job.id();
 
-    db.execute("BEGIN TRANSACTION", []).map_err(|e| e.to_string())?;
+    tokio::spawn(async move {
+        let result = LichessdbService::index_local_dump(config.clone(), job.clone(), PathBuf::from(file_path)).await;
+        finish_indexing(window, config, job, result, col).await;
+    });
 
-    let result = PuzzleRepository::delete_unused_puzzles(db);
+    Ok(job_id)
+}
 
-    match result {
-        Ok(count) => {
-            db.execute("COMMIT", []).map_err(|e| e.to_string())?;
-            Ok(count)
-        },
+//col is only passed when the new metadata should also be copied into the decks
+async fn finish_indexing<R: Runtime>(
+    window: Window<R>,
+    config: CoreConfig,
+    job: JobHandle,
+    result: Result<PuzzleDbChangeReport, AnkiChessError>,
+    col: Option<Arc<Mutex<Collection>>>,
+) {
+    let report = match result {
+        Ok(report) => report,
         Err(e) => {
-            
-            db.execute("ROLLBACK", []).ok();
-            Err(e.to_string())
+            window.emit(DATABASE_ERROR_EVENT, e.to_string()).ok();
+            job.finish(Err(e));
+            return;
         }
+    };
+
+    let mut message = format!(
+        "Indexed {} puzzles ({} added, {} updated, {} removed).",
+        report.indexed, report.added, report.updated, report.removed
+    );
+    if report.skipped > 0 {
+        message.push_str(&format!(" {} rows of the dump could not be read.", report.skipped));
+    }
+    if let Some(col) = col {
+        match propagate_metadata(col, config.puzzle_db_path()).await {
+            Ok(updated) => {
+                window.emit(PUZZLE_METADATA_SYNCED_EVENT, updated).ok();
+                message.push_str(&format!(" Updated {} puzzles in your decks.", updated));
+            }
+            Err(e) => { window.emit(DATABASE_ERROR_EVENT, e).ok(); }
+        }
+    }
+    job.finish(Ok(message));
+
+    if let Ok(status) = LichessdbService::get_status(&config) {
+        window.emit(DATABASE_READY_EVENT, status).ok();
     }
 }
 
+async fn propagate_metadata(col: Arc<Mutex<Collection>>, db_path: PathBuf) -> Result<usize, String> {
+    tokio::task::spawn_blocking(move || {
+        let mut col = col.lock().map_err(|e| e.to_string())?;
+        LichessdbService::propagate_metadata(&mut col, &db_path).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn get_puzzle_db_changes(limit: Option<usize>, state: State<AppState>) -> Result<Option<PuzzleDbChangeReport>, String> {
+    LichessdbService::get_change_report(&state.config, limit.unwrap_or(500)).map_err(|e| e.to_string())
+}
+
+//copies the current rating, popularity and themes of the puzzle database into imported puzzles
+#[tauri::command]
+pub async fn sync_puzzle_metadata_to_decks(state: State<'_, AppState>) -> Result<usize, String> {
+    propagate_metadata(state.col.clone(), state.config.puzzle_db_path()).await
+}
+
+//the partial download is kept, starting again resumes it
+#[tauri::command]
+pub fn cancel_database_download(state: State<AppState>) -> usize {
+    state.jobs.cancel_kind(JobKind::Download)
+}
+
+#[tauri::command]
+pub fn cleanup_unused_puzzles(state: State<AppState>) -> Result<usize, String> {
+    let col = state.col.lock().map_err(|e| e.to_string())?;
+    NoteService::cleanup_unused_puzzles(&col).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub fn get_bootstrap_data(state: tauri::State<AppState>) -> AppBootstrapData {
     (*state.bootstrap_data).clone()
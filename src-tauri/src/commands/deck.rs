@@ -1,9 +1,9 @@
-use crate::error::AnkiChessError;
-use crate::services::deck_service::DeckService;
+use ankichess_core::error::AnkiChessError;
+use ankichess_core::services::deck_service::DeckService;
 use crate::state::AppState;
 use tauri::State;
 
-use crate::models::deck::{DeckInfo, DeckLimitsPayload};
+use ankichess_core::models::deck::{DeckGradingPayload, DeckInfo, DeckLimitsPayload};
 
 
 #[tauri::command]
@@ -44,13 +44,62 @@ pub fn set_deck_limits(
     DeckService::set_deck_limits(&mut col, deck_id, limits)
 }
 
+#[tauri::command]
+pub fn get_deck_grading(
+    deck_id: i64,
+    state: State<AppState>,
+) -> Result<DeckGradingPayload, AnkiChessError> {
+    let mut col = state.col.lock()?;
+    DeckService::get_deck_grading(&mut col, deck_id)
+}
+
+#[tauri::command]
+pub fn set_deck_grading(
+    deck_id: i64,
+    grading: DeckGradingPayload,
+    state: State<AppState>,
+) -> Result<(), AnkiChessError> {
+    let mut col = state.col.lock()?;
+    DeckService::set_deck_grading(&mut col, deck_id, grading)
+}
+
 #[tauri::command]
 pub async fn export_deck_to_csv(
     deck_id: i64,
     file_path: String,
+    include_scheduling: Option<bool>,
     state: State<'_, AppState>,
 ) -> Result<usize, AnkiChessError> {
     let col_arc = state.col.clone();
-    DeckService::export_deck_csv(col_arc, deck_id, file_path).await
+    DeckService::export_deck_csv(col_arc, deck_id, file_path, include_scheduling.unwrap_or(false)).await
 }
 
+
+#[tauri::command]
+pub async fn export_deck_to_pgn(
+    deck_id: i64,
+    file_path: String,
+    state: State<'_, AppState>,
+) -> Result<usize, AnkiChessError> {
+    let col_arc = state.col.clone();
+    DeckService::export_deck_pgn(col_arc, deck_id, file_path).await
+}
+
+#[tauri::command]
+pub async fn export_deck_to_apkg(
+    deck_id: i64,
+    file_path: String,
+    include_scheduling: Option<bool>,
+    legacy_format: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<usize, AnkiChessError> {
+    let col_arc = state.col.clone();
+    DeckService::export_deck_apkg(
+        col_arc,
+        deck_id,
+        file_path,
+        include_scheduling.unwrap_or(false),
+        legacy_format.unwrap_or(true),
+    )
+    .await
+}
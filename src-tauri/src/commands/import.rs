@@ -1,82 +1,114 @@
-use crate::{error::AnkiChessError, services::lichessdb_service::LichessdbService};
-use crate::services::import_service::ImportService;
+use ankichess_core::error::AnkiChessError;
+use ankichess_core::models::import_report::ImportReport;
+use ankichess_core::models::job::JobKind;
+use ankichess_core::repository::puzzle_repo::PuzzleRepository;
+use ankichess_core::services::import_service::ImportService;
 use crate::state::AppState;
-use tauri::{AppHandle, Emitter, Runtime, State, Window};
-use crate::models::puzzle::{CsvImportPayload, ImportOptions};
+use tauri::State;
+use ankichess_core::models::puzzle::{ApkgImportPayload, CsvImportPayload, ImportOptions, ImportPreview, PgnImportPayload};
 
-pub const IMPORT_STATUS_EVENT: &str = "IMPORT_STATUS";
+//the import commands return the id of the job, progress comes through JOB_PROGRESS events
+#[tauri::command]
+pub async fn import_puzzles_from_db(
+    payload: ImportOptions,
+    state: State<'_, AppState>,
+) -> Result<u64, AnkiChessError> {
+    Ok(start_db_import(&state, payload))
+}
 
+//dry run of import_puzzles_from_db, nothing is written
 #[tauri::command]
-pub async fn import_puzzles_from_db<R: Runtime>(
-    window: Window<R>,
+pub async fn preview_import_from_db(
     payload: ImportOptions,
-    app_handle: AppHandle<R>,
+    sample_size: Option<usize>,
     state: State<'_, AppState>,
-) -> Result<(), AnkiChessError> {
+) -> Result<ImportPreview, AnkiChessError> {
     let col_arc = state.col.clone();
-    let app_handle_clone = app_handle.clone();
-
-    let db_path = LichessdbService::get_sqlite_db_path(&app_handle)?;
-
-    app_handle
-        .emit(
-            IMPORT_STATUS_EVENT,
-            "Import started... The app may be slow.", 
-        )
-        .ok();
+    let db_path = state.config.puzzle_db_path();
 
     tokio::task::spawn_blocking(move || {
-        let import_result = (|| -> Result<i64, AnkiChessError> {
-            let mut col = col_arc.lock()?;
-            ImportService::import_from_lichess_db(&mut col, payload, db_path, &window)
-    
-        })();
+        ImportService::preview_lichess_import(col_arc, payload, db_path, sample_size.unwrap_or(12))
+    })
+    .await?
+}
 
-        match import_result {
-            Ok(count) => {
-                let msg = format!("Import completed. Added {} new notes.", count); 
-                app_handle_clone.emit(IMPORT_STATUS_EVENT, msg).ok();
-            }
-            Err(e) => {
-                let error_msg = format!("Error during import: {}", e.to_string()); 
-                app_handle_clone.emit(IMPORT_STATUS_EVENT, error_msg).ok();
-            }
-        }
-    });
+#[tauri::command]
+pub async fn import_puzzles_from_csv(
+    payload: CsvImportPayload,
+    state: State<'_, AppState>,
+) -> Result<u64, AnkiChessError> {
+    Ok(start_csv_import(&state, payload))
+}
 
-    Ok(())
+#[tauri::command]
+pub async fn import_puzzles_from_pgn(
+    payload: PgnImportPayload,
+    state: State<'_, AppState>,
+) -> Result<u64, AnkiChessError> {
+    Ok(start_pgn_import(&state, payload))
 }
 
 #[tauri::command]
-pub async fn import_puzzles_from_csv<R: Runtime>(
-    window: Window<R>,
-    payload: CsvImportPayload,
-    app_handle: AppHandle<R>,
+pub async fn import_anki_package(
+    payload: ApkgImportPayload,
     state: State<'_, AppState>,
-) -> Result<(), AnkiChessError> {
+) -> Result<u64, AnkiChessError> {
     let col_arc = state.col.clone();
-    let app_handle_clone = app_handle.clone();
+    let journal = state.undo_journal.clone();
 
-    tokio::task::spawn_blocking(move || {
-        let import_result = (|| -> Result<i64, AnkiChessError> {
-            let mut col = col_arc.lock()?;
-            ImportService::import_from_csv(&mut col, payload, &window)
-        })();
+    Ok(state.jobs.spawn_blocking(JobKind::Import, "Anki package import", move |job| {
+        let report = ImportService::import_from_apkg(&col_arc, &journal, payload, job)?;
+        job.attach_report(report.id);
+        Ok(format!("Package Import completed. Linked {} chess puzzles, skipped {} notes.", report.imported, report.skipped))
+    }))
+}
 
-        match import_result {
-            Ok(count) => {
-                let msg = format!(
-                    "CSV Import completed. Added {} new notes.", 
-                    count
-                );
-                app_handle_clone.emit(IMPORT_STATUS_EVENT, msg).ok();
-            }
-            Err(e) => {
-                let error_msg = format!("Error during CSV import: {}", e.to_string()); 
-                app_handle_clone.emit(IMPORT_STATUS_EVENT, error_msg).ok();
-            }
-        }
-    });
+//the skipped rows of an import, the id comes with the finished job
+#[tauri::command]
+pub fn get_import_report(report_id: i64, state: State<AppState>) -> Result<Option<ImportReport>, AnkiChessError> {
+    let col = state.col.lock()?;
+    PuzzleRepository::init_tables(col.storage.db())?;
+    Ok(PuzzleRepository::get_import_report(col.storage.db(), report_id)?)
+}
+
+#[tauri::command]
+pub fn list_import_reports(deck_id: Option<i64>, state: State<AppState>) -> Result<Vec<ImportReport>, AnkiChessError> {
+    let col = state.col.lock()?;
+    PuzzleRepository::init_tables(col.storage.db())?;
+    Ok(PuzzleRepository::list_import_reports(col.storage.db(), deck_id)?)
+}
+
+//shared with the local api, which starts the same jobs
+pub(crate) fn start_db_import(state: &AppState, payload: ImportOptions) -> u64 {
+    let col_arc = state.col.clone();
+    let journal = state.undo_journal.clone();
+    let db_path = state.config.puzzle_db_path();
+
+    state.jobs.spawn_blocking(JobKind::Import, "Lichess database import", move |job| {
+        let report = ImportService::import_from_lichess_db(&col_arc, &journal, payload, db_path, job)?;
+        job.attach_report(report.id);
+        Ok(format!("Import completed. {}", report.summary()))
+    })
+}
+
+pub(crate) fn start_csv_import(state: &AppState, payload: CsvImportPayload) -> u64 {
+    let col_arc = state.col.clone();
+    let journal = state.undo_journal.clone();
+
+    state.jobs.spawn_blocking(JobKind::Import, "CSV import", move |job| {
+        let report = ImportService::import_from_csv(&col_arc, &journal, payload, job)?;
+        job.attach_report(report.id);
+        Ok(format!("CSV Import completed. {}", report.summary()))
+    })
+}
+
+pub(crate) fn start_pgn_import(state: &AppState, payload: PgnImportPayload) -> u64 {
+    let col_arc = state.col.clone();
+    let journal = state.undo_journal.clone();
 
-    Ok(())
+    state.jobs.spawn_blocking(JobKind::Import, "PGN import", move |job| {
+        let report = ImportService::import_from_pgn(&col_arc, &journal, payload, job)?;
+        job.attach_report(report.id);
+        Ok(format!("PGN Import completed. {}", report.summary()))
+    })
 }
@@ -0,0 +1,19 @@
+use ankichess_core::models::job::JobInfo;
+use crate::state::AppState;
+use tauri::{command, State};
+
+#[command]
+pub fn list_jobs(state: State<AppState>) -> Vec<JobInfo> {
+    state.jobs.list()
+}
+
+#[command]
+pub fn get_job(job_id: u64, state: State<AppState>) -> Option<JobInfo> {
+    state.jobs.get(job_id)
+}
+
+//the job stops at its next checkpoint, work already committed is kept
+#[command]
+pub fn cancel_job(job_id: u64, state: State<AppState>) -> bool {
+    state.jobs.cancel(job_id)
+}
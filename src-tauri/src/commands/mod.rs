@@ -1,4 +1,7 @@
+pub mod api;
 pub mod card;
 pub mod database;
 pub mod deck;
 pub mod import;
+pub mod job;
+pub mod undo;
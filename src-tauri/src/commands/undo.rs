@@ -0,0 +1,24 @@
+use ankichess_core::error::AnkiChessError;
+use ankichess_core::services::undo_service::{UndoService, UndoStatusPayload};
+use crate::state::AppState;
+use tauri::{command, State};
+
+#[command]
+pub fn get_undo_status(state: State<AppState>) -> Result<UndoStatusPayload, AnkiChessError> {
+    let mut col = state.col.lock()?;
+    Ok(UndoService::get_status(&mut col))
+}
+
+#[command]
+pub fn undo(state: State<AppState>) -> Result<UndoStatusPayload, AnkiChessError> {
+    let mut col = state.col.lock()?;
+    let mut journal = state.undo_journal.lock()?;
+    UndoService::undo(&mut col, &mut journal)
+}
+
+#[command]
+pub fn redo(state: State<AppState>) -> Result<UndoStatusPayload, AnkiChessError> {
+    let mut col = state.col.lock()?;
+    let mut journal = state.undo_journal.lock()?;
+    UndoService::redo(&mut col, &mut journal)
+}
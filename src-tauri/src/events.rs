@@ -0,0 +1,33 @@
+use ankichess_core::models::job::JobInfo;
+use ankichess_core::models::lichessdb::{DownloadProgress, IndexingProgress};
+use ankichess_core::services::job_service::JobObserver;
+use tauri::{AppHandle, Emitter, Runtime};
+
+pub const JOB_PROGRESS_EVENT: &str = "JOB_PROGRESS";
+pub const DOWNLOAD_PROGRESS_EVENT: &str = "DOWNLOAD_PROGRESS";
+pub const INDEXING_PROGRESS_EVENT: &str = "INDEXING_PROGRESS";
+
+/// Forwards job progress to the webview as events.
+pub struct TauriEvents<R: Runtime> {
+    app_handle: AppHandle<R>,
+}
+
+impl<R: Runtime> TauriEvents<R> {
+    pub fn new(app_handle: AppHandle<R>) -> Self {
+        Self { app_handle }
+    }
+}
+
+impl<R: Runtime> JobObserver for TauriEvents<R> {
+    fn job_updated(&self, info: &JobInfo) {
+        self.app_handle.emit(JOB_PROGRESS_EVENT, info).ok();
+    }
+
+    fn download_progress(&self, progress: &DownloadProgress) {
+        self.app_handle.emit(DOWNLOAD_PROGRESS_EVENT, progress).ok();
+    }
+
+    fn indexing_progress(&self, progress: &IndexingProgress) {
+        self.app_handle.emit(INDEXING_PROGRESS_EVENT, progress).ok();
+    }
+}
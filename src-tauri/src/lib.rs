@@ -1,19 +1,19 @@
-use anki::collection::CollectionBuilder;
 use std::sync::{Arc, Mutex};
 use tauri::Manager; 
 
-mod error;
+mod api;
 mod commands;
-mod models;
-mod shared;
+mod events;
 mod state;
-mod repository;
-mod services;
 
 use state::AppState;
-use crate::models::bootstrap::AppBootstrapData;
-use crate::commands::{card::*, database::*, deck::*, import::*};
-use crate::repository::puzzle_repo::PuzzleRepository;
+use ankichess_core::config::CoreConfig;
+use ankichess_core::models::bootstrap::AppBootstrapData;
+use crate::api::ApiSettings;
+use crate::commands::{api::*, card::*, database::*, deck::*, import::*, job::*, undo::*};
+use crate::events::TauriEvents;
+use ankichess_core::services::job_service::JobManager;
+use ankichess_core::services::undo_service::UndoJournal;
 
 
 
@@ -29,17 +29,13 @@ pub fn run() {
             let app_data_dir = app.path().app_data_dir()
                 .expect("could not find app data dir");
 
-            std::fs::create_dir_all(&app_data_dir)
-                .expect("could not create app data dir");
-
-            let col_path = app_data_dir.join("collection.ankichess");
-
-            let col = CollectionBuilder::default()
-                .set_collection_path(col_path.to_str().expect("invalid path"))
-                .build()
+            let config = CoreConfig::new(app_data_dir);
+            //runs the schema migrations of the chess tables before anything reads them
+            let (col, upgrade) = config.open_collection()
                 .expect("error while trying to open anki collection");
-
-            let _ = PuzzleRepository::init_tables(col.storage.db());
+            for message in upgrade.messages() {
+                eprintln!("{}", message);
+            }
 
             //resources
             let resource_dir = app.path()
@@ -57,10 +53,28 @@ pub fn run() {
                 eprintln!("Failed to load themes tags: {}", e);
             }
 
-            app.manage(AppState {
+            let api_settings = ApiSettings::load(&col);
+            let state = AppState {
                 col: Arc::new(Mutex::new(col)),
+                config,
                 bootstrap_data: Arc::new(bootstrap_data),
-            });
+                undo_journal: Arc::new(Mutex::new(UndoJournal::default())),
+                jobs: Arc::new(JobManager::new(Arc::new(TauriEvents::new(app.handle().clone())))),
+                api: Default::default(),
+                study_card: Default::default(),
+            };
+
+            //a failing api must not keep the app from starting
+            match api_settings {
+                Ok(settings) => {
+                    if let Err(e) = state.api.apply(state.clone(), &settings) {
+                        eprintln!("Failed to start the local API: {}", e);
+                    }
+                }
+                Err(e) => eprintln!("Failed to load the local API settings: {}", e),
+            }
+
+            app.manage(state);
 
             Ok(())
         })
@@ -73,7 +87,11 @@ pub fn run() {
             delete_deck,
             set_deck_limits,
             get_deck_limits,
+            get_deck_grading,
+            set_deck_grading,
             export_deck_to_csv,
+            export_deck_to_pgn,
+            export_deck_to_apkg,
             //cards
             add_chess_note,
             delete_notes,
@@ -82,14 +100,41 @@ pub fn run() {
             //study
             get_next_card,
             answer_card,
+            end_study_session,
+            get_card_review_stats,
             browse_cards_in_deck,
+            //undo
+            get_undo_status,
+            undo,
+            redo,
             //lichessdb stuff
             import_puzzles_from_db,
+            preview_import_from_db,
             import_puzzles_from_csv,
+            import_puzzles_from_pgn,
+            import_anki_package,
+            get_import_report,
+            list_import_reports,
             get_puzzle_db_status,
             check_for_update,
+            get_puzzle_db_base_url,
+            set_puzzle_db_base_url,
             start_database_download_and_index,
-            cleanup_unused_puzzles
+            cancel_database_download,
+            index_local_puzzle_dump,
+            get_puzzle_db_changes,
+            sync_puzzle_metadata_to_decks,
+            cleanup_unused_puzzles,
+
+            //jobs
+            list_jobs,
+            get_job,
+            cancel_job,
+
+            //local api
+            get_api_settings,
+            set_api_settings,
+            regenerate_api_token
         ])
         .run(tauri::generate_context!())
         .expect("Error running the Tauri application."); 
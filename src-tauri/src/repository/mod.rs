@@ -1 +0,0 @@
-pub mod puzzle_repo;
\ No newline at end of file
@@ -1,4 +0,0 @@
-pub mod note_service;
-pub mod deck_service;
-pub mod import_service;
-pub mod lichessdb_service;
\ No newline at end of file
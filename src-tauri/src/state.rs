@@ -1,9 +1,23 @@
 use anki::collection::Collection;
 use std::sync::{Arc, Mutex};
 
-use crate::models::bootstrap::AppBootstrapData;
+use ankichess_core::config::CoreConfig;
+use ankichess_core::models::bootstrap::AppBootstrapData;
+use ankichess_core::models::card::StudyCard;
+use ankichess_core::services::job_service::JobManager;
+use ankichess_core::services::undo_service::UndoJournal;
 
+use crate::api::ApiServer;
+
+//cloned into the local api server, every field is shared
+#[derive(Clone)]
 pub struct AppState {
     pub col: Arc<Mutex<Collection>>,
+    pub config: CoreConfig,
     pub bootstrap_data: Arc<AppBootstrapData>,
+    pub undo_journal: Arc<Mutex<UndoJournal>>,
+    pub jobs: Arc<JobManager>,
+    pub api: Arc<ApiServer>,
+    //the card on the study screen, for AnkiConnect's guiCurrentCard
+    pub study_card: Arc<Mutex<Option<StudyCard>>>,
 }
@@ -0,0 +1,9 @@
+// the local servers. the JSON-RPC one takes the token as `Authorization: Bearer <token>`,
+// AnkiConnect tools take it as their api key
+export interface ApiSettings {
+  enabled: boolean;
+  port: number;
+  ankiConnectEnabled: boolean;
+  ankiConnectPort: number;
+  token: string;
+}
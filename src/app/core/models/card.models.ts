@@ -84,8 +84,32 @@ export interface StudyCard {
   openingTags: string;
   comment: string;
   hasSetupMove: boolean;
+  reviewStats: PuzzleReviewStats;
+  autoGrade: boolean;
+}
+
+export interface PuzzleReviewStats {
+  reviews: number;
+  cleanSolves: number;
+  wrongAttempts: number;
+  hintsUsed: number;
+  totalMs: number;
+  averageMs: number;
+  lastMs: number;
+}
+
+export interface PuzzleAttempt {
+  millisecondsTaken: number;
+  wrongAttempts: number;
+  hintsUsed: number;
 }
 
 export interface UpdatedStudyCard extends StudyCard {
   orientation: 'white' | 'black';
 }
+
+export interface UndoStatus {
+  reverted: string | null;
+  undoLabel: string | null;
+  redoLabel: string | null;
+}
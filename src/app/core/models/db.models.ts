@@ -7,11 +7,60 @@ export interface ImportOptions {
   limit?: number;
   themes?: string[];       
   opening_tags?: string[];
+  theme_match?: TagMatch;
+  exclude_themes?: string[];
+  opening_match?: TagMatch;
+  exclude_opening_tags?: string[];
+  sampling?: SamplingMode;
+  seed?: number;
+  rating_bucket_width?: number;
+  duplicates?: DuplicatePolicy;
 }
 
+// what happens to puzzles that are already in another deck, matched by id or position
+export type DuplicatePolicy = 'skip' | 'link' | 'move';
+
+export type SamplingMode = 'first' | 'random' | 'rating_stratified' | 'theme_balanced';
+
+export interface RatingBucket {
+  from: number;
+  to: number;
+  count: number;
+}
+
+export interface ThemeCount {
+  theme: string;
+  count: number;
+}
+
+// sample puzzles come straight from the backend ChessPuzzle, hence snake_case
+export interface PreviewPuzzle {
+  puzzle_id: string;
+  fen: string;
+  moves: string;
+  rating: number;
+  themes: string;
+  opening_tags: string;
+}
+
+export interface ImportPreview {
+  matching: number;
+  alreadyInDeck: number;
+  inOtherDecks: number;
+  toImport: number;
+  seed: number | null;
+  ratingHistogram: RatingBucket[];
+  themeBreakdown: ThemeCount[];
+  sample: PreviewPuzzle[];
+}
+
+// themes and openings match whole tags, 'any' keeps puzzles with at least one of them
+export type TagMatch = 'any' | 'all';
+
 export interface CsvImportPayload {
     deckId: number;
     csvContent: string;
+    duplicates?: DuplicatePolicy;
 }
 
 export interface DbStatus {
@@ -20,6 +69,51 @@ export interface DbStatus {
   puzzleCount: number;
 }
 
+export interface PuzzleChange {
+  puzzleId: string;
+  change: 'added' | 'removed' | 'updated';
+  oldRating: number | null;
+  newRating: number | null;
+  oldPopularity: number | null;
+  newPopularity: number | null;
+  oldThemes: string | null;
+  newThemes: string | null;
+}
+
+export interface PuzzleDbChangeReport {
+  indexed: number;
+  initial: boolean;
+  added: number;
+  removed: number;
+  updated: number;
+  changes: PuzzleChange[];
+  skipped: number;
+  skippedRows: SkippedRow[];
+}
+
+export type SkipReason = 'missing_column' | 'malformed_fen' | 'illegal_move' | 'duplicate_id' | 'duplicate_position' | 'invalid_value' | 'no_puzzle' | 'multiple_templates';
+
+// line is the csv line, the pgn game number or the package note number
+export interface SkippedRow {
+  line: number | null;
+  reason: SkipReason;
+  puzzleId: string | null;
+  detail: string;
+}
+
+export interface ImportReport {
+  id: number;
+  source: 'csv' | 'pgn' | 'apkg' | 'lichess_db';
+  deckId: number;
+  createdAt: number;
+  processed: number;
+  imported: number;
+  linked: number;
+  moved: number;
+  skipped: number;
+  skippedRows: SkippedRow[];
+}
+
 export interface DownloadProgress {
   downloaded: number;
   total: number;
@@ -14,4 +14,9 @@ export interface Deck {
 export interface DeckLimitsPayload {
   newCardsPerDay: number;
   reviewsPerDay: number;
-}
\ No newline at end of file
+}
+
+export interface DeckGradingPayload {
+  autoGrade: boolean;
+  targetSeconds: number;
+}
@@ -0,0 +1,19 @@
+export type JobKind = 'import' | 'download' | 'index';
+
+export type JobStatus = 'running' | 'completed' | 'failed' | 'cancelled';
+
+export interface JobInfo {
+  id: number;
+  kind: JobKind;
+  label: string;
+  status: JobStatus;
+  message: string;
+  processed: number;
+  imported: number;
+  skipped: number;
+  total: number | null;
+  error: string | null;
+  reportId: number | null;
+  startedAt: number;
+  finishedAt: number | null;
+}
@@ -2,24 +2,19 @@ import { Injectable, NgZone, OnDestroy } from '@angular/core';
 import { invoke } from '@tauri-apps/api/core';
 
 
-import type { DeckInfo, DeckLimitsPayload } from '../models/deck.models';
-import type { CardInfo, BrowseCardInfo, PaginatedBrowseResult, StudyCard } from '../models/card.models';
-import type { ImportOptions, DbStatus } from '../models/db.models';
+import type { DeckInfo, DeckLimitsPayload, DeckGradingPayload } from '../models/deck.models';
+import type { CardInfo, BrowseCardInfo, PaginatedBrowseResult, StudyCard, PuzzleAttempt, UndoStatus } from '../models/card.models';
+import type { DuplicatePolicy, ImportOptions, ImportPreview, ImportReport, DbStatus, PuzzleDbChangeReport } from '../models/db.models';
+import type { JobInfo } from '../models/job.models';
+import type { ApiSettings } from '../models/api.models';
 import { BehaviorSubject, Observable } from 'rxjs';
 import { listen, UnlistenFn } from '@tauri-apps/api/event';
 import { save } from '@tauri-apps/plugin-dialog';
 
 
-interface RustImportProgress {
-  message: string;
-  processed_count: number;
-  imported_count: number;
-  skipped_count: number;
-  total_to_import: number | null;
-}
-
-
 export interface ImportProgress {
+  jobId: number | null;
+  reportId: number | null;
   message: string;
   processedCount: number;
   importedCount: number;
@@ -49,58 +44,34 @@ export class TauriService implements OnDestroy {
   private async listenToImportProgress(): Promise<void> {
     try {
 
-      const unlistenProgress = await listen('import-progress', (event: any) => {
-        const payload = event.payload as RustImportProgress;
+      const unlistenJobs = await listen<JobInfo>('JOB_PROGRESS', (event) => {
+        const job = event.payload;
+        if (job.kind !== 'import') return;
 
         this.ngZone.run(() => {
-
-          const progress: ImportProgress = {
-            message: payload.message,
-            processedCount: payload.processed_count,
-            importedCount: payload.imported_count,
-            skippedCount: payload.skipped_count,
-            totalToImport: payload.total_to_import,
-            isError: false,
-
-            isComplete: payload.message.toLowerCase().includes('completed') || payload.message.toLowerCase().includes('finished'),
-          };
-          this.importProgressSubject.next(progress);
-        });
-      });
-
-
-
-      const unlistenStatus = await listen('IMPORT_STATUS', (event: any) => {
-        const message = event.payload as string;
-
-
-        if (message.startsWith('Import started')) return;
-
-        this.ngZone.run(() => {
-          const isError = message.startsWith('Error');
-          const isComplete = message.toLowerCase().includes('completed') || message.toLowerCase().includes('finished') || isError;
-
-
-          const currentState = this.importProgressSubject.value;
           this.importProgressSubject.next({
-            message: message,
-            processedCount: currentState?.processedCount ?? 0,
-            importedCount: currentState?.importedCount ?? 0,
-            skippedCount: currentState?.skippedCount ?? 0,
-            totalToImport: currentState?.totalToImport ?? null,
-            isError: isError,
-            isComplete: isComplete,
+            jobId: job.id,
+            reportId: job.reportId,
+            message: job.message,
+            processedCount: job.processed,
+            importedCount: job.imported,
+            skippedCount: job.skipped,
+            totalToImport: job.total,
+            isError: job.status === 'failed',
+            isComplete: job.status !== 'running',
           });
         });
       });
 
 
-      this.unlistenFns = [unlistenProgress, unlistenStatus];
+      this.unlistenFns = [unlistenJobs];
 
     } catch (error) {
       console.error("Failed to start listening for import events:", error);
       this.ngZone.run(() => {
         this.importProgressSubject.next({
+          jobId: null,
+          reportId: null,
           message: "Error connecting to the progress event.",
           processedCount: 0,
           importedCount: 0,
@@ -117,12 +88,16 @@ export class TauriService implements OnDestroy {
     this.importProgressSubject.next(null);
   }
 
+  async previewImportFromDb(options: ImportOptions, sampleSize?: number): Promise<ImportPreview> {
+    return invoke('preview_import_from_db', { payload: options, sampleSize });
+  }
+
   async importPuzzlesFromDb(options: ImportOptions): Promise<void> {
     try {
 
       this.clearImportProgress();
 
-      await invoke<void>('import_puzzles_from_db', {
+      await invoke<number>('import_puzzles_from_db', {
         payload: options,
       });
     } catch (error) {
@@ -130,6 +105,8 @@ export class TauriService implements OnDestroy {
 
       this.ngZone.run(() => {
         this.importProgressSubject.next({
+          jobId: null,
+          reportId: null,
           message: `Error starting command: ${String(error)}`,
           processedCount: 0,
           importedCount: 0,
@@ -143,13 +120,14 @@ export class TauriService implements OnDestroy {
     }
   }
 
-  async importPuzzlesFromCsv(deckId: number, csvContent: string): Promise<void> {
+  async importPuzzlesFromCsv(deckId: number, csvContent: string, duplicates: DuplicatePolicy = 'skip'): Promise<void> {
     try {
       this.clearImportProgress();
       await invoke('import_puzzles_from_csv', {
         payload: {
           deck_id: deckId,
-          csv_content: csvContent
+          csv_content: csvContent,
+          duplicates
         }
       });
     } catch (error) {
@@ -157,6 +135,8 @@ export class TauriService implements OnDestroy {
       this.ngZone.run(() => {
 
         this.importProgressSubject.next({
+          jobId: null,
+          reportId: null,
           message: `Error starting import: ${String(error)}`,
           processedCount: 0,
           importedCount: 0,
@@ -170,6 +150,28 @@ export class TauriService implements OnDestroy {
   }
 
 
+  async getImportReport(reportId: number): Promise<ImportReport | null> {
+    return invoke('get_import_report', { reportId });
+  }
+
+  async listImportReports(deckId?: number): Promise<ImportReport[]> {
+    return invoke('list_import_reports', { deckId });
+  }
+
+  async listJobs(): Promise<JobInfo[]> {
+    return invoke('list_jobs');
+  }
+
+  async getJob(jobId: number): Promise<JobInfo | null> {
+    return invoke('get_job', { jobId });
+  }
+
+  // the job stops at its next batch, puzzles already imported stay in the deck
+  async cancelJob(jobId: number): Promise<boolean> {
+    return invoke('cancel_job', { jobId });
+  }
+
+
   ngOnDestroy() {
     this.unlistenFns.forEach(fn => fn());
     this.unlistenFns = [];
@@ -189,15 +191,39 @@ export class TauriService implements OnDestroy {
     return invoke('check_for_update');
   }
 
-  async startDatabaseDownloadAndIndex(): Promise<void> {
+  async getPuzzleDbBaseUrl(): Promise<string> {
+    return invoke('get_puzzle_db_base_url');
+  }
+
+  async setPuzzleDbBaseUrl(baseUrl: string | null): Promise<void> {
+    return invoke('set_puzzle_db_base_url', { baseUrl });
+  }
+
+  async startDatabaseDownloadAndIndex(propagateToDecks = false): Promise<number> {
     try {
-      await invoke('start_database_download_and_index');
+      return await invoke('start_database_download_and_index', { propagateToDecks });
     } catch (e) {
       console.error('Failed to start DB download/index:', e);
       throw new Error(`Failed to start download: ${e}`);
     }
   }
 
+  async cancelDatabaseDownload(): Promise<number> {
+    return invoke('cancel_database_download');
+  }
+
+  async indexLocalPuzzleDump(filePath: string, propagateToDecks = false): Promise<number> {
+    return invoke('index_local_puzzle_dump', { filePath, propagateToDecks });
+  }
+
+  async getPuzzleDbChanges(limit?: number): Promise<PuzzleDbChangeReport | null> {
+    return invoke('get_puzzle_db_changes', { limit });
+  }
+
+  async syncPuzzleMetadataToDecks(): Promise<number> {
+    return invoke('sync_puzzle_metadata_to_decks');
+  }
+
   async getAllDecks(): Promise<DeckInfo[]> {
     return invoke('get_all_decks');
   }
@@ -272,14 +298,33 @@ export class TauriService implements OnDestroy {
     }
   }
 
-  async answerCard(cardId: number, rating: 1 | 2 | 3 | 4): Promise<void> {
+  // clears the card AnkiConnect tools see as the current one
+  async endStudySession(): Promise<void> {
+    return invoke('end_study_session');
+  }
+
+  // a null rating lets the backend grade the attempt with the deck's grading settings
+  async answerCard(cardId: number, rating: 1 | 2 | 3 | 4 | null, attempt?: PuzzleAttempt): Promise<number | null> {
     try {
-      await invoke('answer_card', { cardId, rating });
+      return await invoke('answer_card', { cardId, rating, ...attempt });
     } catch (e) {
       console.error("Failed to answer card:", e);
+      return null;
     }
   }
 
+  async undo(): Promise<UndoStatus> {
+    return invoke('undo');
+  }
+
+  async redo(): Promise<UndoStatus> {
+    return invoke('redo');
+  }
+
+  async getUndoStatus(): Promise<UndoStatus> {
+    return invoke('get_undo_status');
+  }
+
   async getDeckLimits(deckId: number): Promise<DeckLimitsPayload> {
     try {
       return await invoke('get_deck_limits', { deckId });
@@ -289,6 +334,14 @@ export class TauriService implements OnDestroy {
     }
   }
 
+  async getDeckGrading(deckId: number): Promise<DeckGradingPayload> {
+    return invoke('get_deck_grading', { deckId });
+  }
+
+  async setDeckGrading(deckId: number, grading: DeckGradingPayload): Promise<void> {
+    return invoke('set_deck_grading', { deckId, grading });
+  }
+
   async setDeckLimits(deckId: number, limits: DeckLimitsPayload): Promise<void> {
     try {
       await invoke('set_deck_limits', { deckId, limits });
@@ -328,6 +381,18 @@ export class TauriService implements OnDestroy {
     }
   }
 
+  async getApiSettings(): Promise<ApiSettings> {
+    return invoke('get_api_settings');
+  }
+
+  async setApiSettings(changes: Partial<Omit<ApiSettings, 'token'>>): Promise<ApiSettings> {
+    return invoke('set_api_settings', changes);
+  }
+
+  async regenerateApiToken(): Promise<ApiSettings> {
+    return invoke('regenerate_api_token');
+  }
+
   async cleanupUnusedPuzzles(): Promise<number> {
     try {
       return await invoke('cleanup_unused_puzzles');
@@ -2,6 +2,7 @@ import { Component, signal, inject, OnInit } from '@angular/core';
 import { CommonModule } from '@angular/common';
 import { DbStateService } from '../../services/db-state.service';
 import { TauriService } from '../../services/tauri.service';
+import { ApiSettings } from '../../models/api.models';
 
 @Component({
   selector: 'app-database-fab',
@@ -21,7 +22,16 @@ export class DatabaseFabComponent implements OnInit {
   
   cleanupStatus = signal<{ message: string; isError: boolean } | null>(null);
 
+  apiSettings = signal<ApiSettings | null>(null);
+  isSavingApi = signal(false);
+  apiError = signal<string | null>(null);
+  tokenCopied = signal(false);
+
   ngOnInit() {
+    this.tauriService.getApiSettings()
+      .then(settings => this.apiSettings.set(settings))
+      .catch(e => console.error('Failed to load API settings:', e));
+
     const hasSeenIntro = localStorage.getItem('db_intro_seen');
     if (!hasSeenIntro && !this.dbService.isInstalled()) {
       this.showBubble.set(true);
@@ -68,4 +78,36 @@ export class DatabaseFabComponent implements OnInit {
       setTimeout(() => this.cleanupStatus.set(null), 5000);
     }
   }
-}
\ No newline at end of file
+
+  async toggleApi() {
+    await this.saveApi(() => this.tauriService.setApiSettings({ enabled: !this.apiSettings()?.enabled }));
+  }
+
+  async toggleAnkiConnect() {
+    await this.saveApi(() => this.tauriService.setApiSettings({ ankiConnectEnabled: !this.apiSettings()?.ankiConnectEnabled }));
+  }
+
+  async regenerateApiToken() {
+    await this.saveApi(() => this.tauriService.regenerateApiToken());
+  }
+
+  private async saveApi(save: () => Promise<ApiSettings>) {
+    this.isSavingApi.set(true);
+    this.apiError.set(null);
+    try {
+      this.apiSettings.set(await save());
+    } catch (e) {
+      this.apiError.set(`Local API: ${e}`);
+    } finally {
+      this.isSavingApi.set(false);
+    }
+  }
+
+  async copyApiToken() {
+    const token = this.apiSettings()?.token;
+    if (!token) return;
+    await navigator.clipboard.writeText(token);
+    this.tokenCopied.set(true);
+    setTimeout(() => this.tokenCopied.set(false), 2000);
+  }
+}
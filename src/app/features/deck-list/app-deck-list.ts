@@ -7,7 +7,7 @@ import { Subscription } from 'rxjs';
 import { TauriService, ImportProgress } from '../../core/services/tauri.service';
 import { DbStateService } from '../../core/services/db-state.service';
 import { DeckInfo } from '../../core/models/deck.models';
-import { ImportOptions } from '../../core/models/db.models';
+import { ImportOptions, ImportReport } from '../../core/models/db.models';
 import { ImportOptionsComponent } from '../../shared/components/import-options/import-options';
 import { ModalComponent } from '../../shared/components/modal/modal.component';
 import { DeckSettingsComponent } from '../deck-settings/app-deck-settings';
@@ -36,6 +36,7 @@ export class DeckListComponent implements OnInit, OnDestroy {
   error = signal<string | null>(null);
 
   importProgress = signal<ImportProgress | null>(null);
+  importReport = signal<ImportReport | null>(null);
 
   showDbMissingModal = signal(false);
   showImportModalForDeck = signal<DeckInfo | null>(null);
@@ -197,6 +198,8 @@ export class DeckListComponent implements OnInit, OnDestroy {
 
   onImportStarted(message: string) {
     this.importProgress.set({
+      jobId: null,
+      reportId: null,
       message: message,
       processedCount: 0,
       importedCount: 0,
@@ -208,6 +211,23 @@ export class DeckListComponent implements OnInit, OnDestroy {
     this.closeImportModal();
   }
 
+  async cancelImport(jobId: number) {
+    await this.apiService.cancelJob(jobId);
+  }
+
+  async toggleImportReport(reportId: number) {
+    if (this.importReport()?.id === reportId) {
+      this.importReport.set(null);
+      return;
+    }
+    this.importReport.set(await this.apiService.getImportReport(reportId));
+  }
+
+  closeImportFeedback() {
+    this.importReport.set(null);
+    this.apiService.clearImportProgress();
+  }
+
   async exportDeck(deckId: number, deckName: string) {
     this.closeDropdown();
     try {
@@ -1,4 +1,4 @@
-import { Component, OnInit, inject, signal, ChangeDetectionStrategy, ViewChild } from '@angular/core';
+import { Component, OnDestroy, OnInit, inject, signal, ChangeDetectionStrategy, ViewChild } from '@angular/core';
 import { CommonModule } from '@angular/common';
 import { ActivatedRoute, RouterLink } from '@angular/router';
 import { TauriService } from '../../core/services/tauri.service';
@@ -17,7 +17,7 @@ import { FormsModule } from '@angular/forms';
   styleUrls: ['./app-study.css'],
   changeDetection: ChangeDetectionStrategy.OnPush,
 })
-export class StudyComponent implements OnInit {
+export class StudyComponent implements OnInit, OnDestroy {
   private apiService = inject(TauriService);
   private route = inject(ActivatedRoute);
 
@@ -33,6 +33,10 @@ export class StudyComponent implements OnInit {
   isEditingComment = signal<boolean>(false);
   commentText = signal<string>('');
 
+  private cardShownAt = 0;
+  private wrongAttempts = 0;
+  private hintsUsed = 0;
+
   @ViewChild(ChessAnalysisComponent) analysisComponent?: ChessAnalysisComponent;
 
   ngOnInit() {
@@ -40,6 +44,10 @@ export class StudyComponent implements OnInit {
     this.loadNextCard();
   }
 
+  ngOnDestroy() {
+    this.apiService.endStudySession().catch(e => console.error('Failed to end the study session:', e));
+  }
+
   async loadNextCard() {
     this.feedbackMessage.set('');
     this.showAnswerButtons.set(false);
@@ -67,6 +75,9 @@ export class StudyComponent implements OnInit {
         };
         
         this.currentCard.set(card);
+        this.cardShownAt = Date.now();
+        this.wrongAttempts = 0;
+        this.hintsUsed = 0;
       } else {
         this.feedbackMessage.set("Congratulations! You have finished the cards in this deck for today. 🎉");
       }
@@ -113,17 +124,30 @@ export class StudyComponent implements OnInit {
   }
 
   handlePuzzleSolved(): void {
-    this.feedbackMessage.set('Correct! Rate the difficulty:');
     this.incorrectMove.set(false);
+    if (this.currentCard()?.autoGrade) {
+      this.rateCard(null);
+      return;
+    }
+    this.feedbackMessage.set('Correct! Rate the difficulty:');
     this.showAnswerButtons.set(true);
   }
 
   handleIncorrectMove(): void {
+    this.wrongAttempts++;
+    if (this.currentCard()?.autoGrade) {
+      this.rateCard(null);
+      return;
+    }
     this.feedbackMessage.set('Incorrect move. Review the position and rate it');
     this.incorrectMove.set(true);
     this.showAnswerButtons.set(true);
   }
 
+  handleHintUsed(): void {
+    this.hintsUsed++;
+  }
+
   handleCommentVariation(uciMoves: string[]) {
     
     if (!this.isAnalyzing()) {
@@ -142,7 +166,7 @@ export class StudyComponent implements OnInit {
     this.isAnalyzing.update(v => !v);
   }
 
-  async rateCard(rating: 1 | 2 | 3 | 4): Promise<void> {
+  async rateCard(rating: 1 | 2 | 3 | 4 | null): Promise<void> {
     const card = this.currentCard();
     if (!card) return;
 
@@ -150,7 +174,11 @@ export class StudyComponent implements OnInit {
 
     try {
       
-      await this.apiService.answerCard(card.cardId, rating);
+      await this.apiService.answerCard(card.cardId, rating, {
+        millisecondsTaken: Date.now() - this.cardShownAt,
+        wrongAttempts: this.wrongAttempts,
+        hintsUsed: this.hintsUsed,
+      });
       this.feedbackMessage.set('Loading next card...');
       await this.loadNextCard();
     } catch (error) {
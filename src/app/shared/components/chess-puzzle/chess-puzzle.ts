@@ -46,9 +46,11 @@ export class ChessPuzzle implements AfterViewInit, OnChanges, OnDestroy {
 
   @Output() puzzleSolved = new EventEmitter<void>();
   @Output() incorrectMove = new EventEmitter<void>();
+  @Output() hintUsed = new EventEmitter<void>();
 
   private cg: Api | null = null;
   private currentMoveIndex = 0;
+  private hintedMoveIndex = -1;
   private position!: Position;
   
   private audioService = inject(AudioService);
@@ -180,6 +182,7 @@ export class ChessPuzzle implements AfterViewInit, OnChanges, OnDestroy {
 
   private resetPuzzleState(): void {
     this.currentMoveIndex = 0;
+    this.hintedMoveIndex = -1;
     this.moveFeedback.set(null); 
     const setup = parseFen(this.fen).unwrap();
     this.position = Chess.fromSetup(setup).unwrap();
@@ -259,6 +262,12 @@ export class ChessPuzzle implements AfterViewInit, OnChanges, OnDestroy {
     const to = solutionMoveUci.substring(2, 4) as Key;
 
     this.cg.setShapes([{ orig: from, dest: to, brush: 'green' }]);
+
+    //showing the same move again isn't another hint
+    if (this.hintedMoveIndex !== this.currentMoveIndex) {
+      this.hintedMoveIndex = this.currentMoveIndex;
+      this.hintUsed.emit();
+    }
   }
 
   private handleUserMove(orig: Key, dest: Key): void {
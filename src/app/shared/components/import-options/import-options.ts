@@ -2,7 +2,7 @@ import { Component, Input, Output, EventEmitter, signal, inject, NgZone, OnInit,
 import { CommonModule } from '@angular/common';
 import { FormsModule } from '@angular/forms';
 import { TauriService } from '../../../core/services/tauri.service';
-import { AppBootstrapData, ImportOptions, Opening, Theme } from '../../../core/models/db.models';
+import { AppBootstrapData, DuplicatePolicy, ImportOptions, ImportPreview, Opening, SamplingMode, TagMatch, Theme } from '../../../core/models/db.models';
 import { invoke } from '@tauri-apps/api/core';
 
 @Component({
@@ -32,12 +32,24 @@ export class ImportOptionsComponent implements OnInit {
   minPopularity = signal<string>('');
   maxPopularity = signal<string>('');
   limit = signal<string>('1000');
+  sampling = signal<SamplingMode>('first');
+  seed = signal<string>('');
+  ratingBucketWidth = signal<string>('');
+  duplicates = signal<DuplicatePolicy>('skip');
+
+  preview = signal<ImportPreview | null>(null);
+  isPreviewing = signal(false);
+  previewError = signal<string | null>(null);
+
+  previewMaxBucket = computed(() => Math.max(1, ...(this.preview()?.ratingHistogram ?? []).map(b => b.count)));
 
   availableThemes = signal<Theme[]>([]);
   availableOpenings = signal<Opening[]>([]);
 
   
   selectedThemes = signal<Theme[]>([]);
+  excludedThemes = signal<Theme[]>([]);
+  themeMatch = signal<TagMatch>('any');
   showThemePicker = signal(false);
   themeSearchText = signal('');
 
@@ -50,6 +62,7 @@ export class ImportOptionsComponent implements OnInit {
 
   
   selectedOpenings = signal<Opening[]>([]);
+  openingMatch = signal<TagMatch>('any');
   showOpeningPicker = signal(false);
   openingSearchText = signal('');
 
@@ -120,17 +133,26 @@ export class ImportOptionsComponent implements OnInit {
     if (this.showThemePicker()) { this.showOpeningPicker.set(false); this.themeSearchText.set(''); }
   }
 
+  // cycles through included -> excluded -> not used
   toggleTheme(theme: Theme) {
-    this.selectedThemes.update(current => {
-      const exists = current.find(t => t.id === theme.id);
-      return exists ? current.filter(t => t.id !== theme.id) : [...current, theme];
-    });
+    if (this.isThemeSelected(theme)) {
+      this.selectedThemes.update(current => current.filter(t => t.id !== theme.id));
+      this.excludedThemes.update(current => [...current, theme]);
+    } else if (this.isThemeExcluded(theme)) {
+      this.excludedThemes.update(current => current.filter(t => t.id !== theme.id));
+    } else {
+      this.selectedThemes.update(current => [...current, theme]);
+    }
   }
 
   isThemeSelected(theme: Theme): boolean {
     return this.selectedThemes().some(t => t.id === theme.id);
   }
 
+  isThemeExcluded(theme: Theme): boolean {
+    return this.excludedThemes().some(t => t.id === theme.id);
+  }
+
   toggleOpeningPicker() {
     this.showOpeningPicker.update(v => !v);
     if (this.showOpeningPicker()) { this.showThemePicker.set(false); this.openingSearchText.set(''); }
@@ -190,7 +212,7 @@ export class ImportOptionsComponent implements OnInit {
     this.isImporting.set(true);
     this.importStarted.emit(`Importing CSV...`);
     try {
-      await this.apiService.importPuzzlesFromCsv(this.deckId, content);
+      await this.apiService.importPuzzlesFromCsv(this.deckId, content, this.duplicates());
       this.closeModal.emit();
     } catch(e) { 
       this.isImporting.set(false);
@@ -199,13 +221,10 @@ export class ImportOptionsComponent implements OnInit {
     }
   }
 
-  startDbImport() {
-    this.isImporting.set(true);
-    this.importStarted.emit(`Importing from DB...`);
-    
+  private buildDbOptions(): ImportOptions {
     const parseNum = (val: string) => { const n = parseInt(val, 10); return isNaN(n) ? undefined : n; };
 
-    const options: ImportOptions = {
+    return {
       deck_id: this.deckId,
       min_rating: parseNum(this.minRating()),
       max_rating: parseNum(this.maxRating()),
@@ -213,10 +232,40 @@ export class ImportOptionsComponent implements OnInit {
       max_popularity: parseNum(this.maxPopularity()),
       limit: parseNum(this.limit()),
       themes: this.selectedThemes().map(t => t.id),
+      theme_match: this.themeMatch(),
+      exclude_themes: this.excludedThemes().map(t => t.id),
       opening_tags: this.selectedOpenings().map(o => this.formatNameForBackend(o.name)),
+      opening_match: this.openingMatch(),
+      sampling: this.sampling(),
+      seed: parseNum(this.seed()),
+      rating_bucket_width: parseNum(this.ratingBucketWidth()),
+      duplicates: this.duplicates(),
     };
+  }
+
+  async previewDbImport() {
+    this.isPreviewing.set(true);
+    this.previewError.set(null);
+    try {
+      const result = await this.apiService.previewImportFromDb(this.buildDbOptions());
+      // keep the previewed seed so the import picks the same puzzles
+      if (result.seed !== null && this.seed() === '') {
+        this.seed.set(String(result.seed));
+      }
+      this.preview.set(result);
+    } catch (e) {
+      this.previewError.set(`Preview failed: ${e}`);
+      this.preview.set(null);
+    } finally {
+      this.isPreviewing.set(false);
+    }
+  }
+
+  startDbImport() {
+    this.isImporting.set(true);
+    this.importStarted.emit(`Importing from DB...`);
 
-    this.importTriggered.emit(options);
+    this.importTriggered.emit(this.buildDbOptions());
     this.isImporting.set(false);
   }
 